@@ -1,16 +1,111 @@
-use aws_config::BehaviorVersion;
-use aws_sdk_dynamodb::Client as DynamoClient;
-use aws_sdk_s3::Client as S3Client;
+use aegis_common::{
+    aliased_projection, aws_config_loader, build_version, map_sdk_error, parse_flag,
+    warn_on_missing_scan_items, with_retry, ConfigTrace, EventEmitter, InitTiming, RetryBudget,
+    SdkResultExt,
+};
+use aws_sdk_cloudwatch::{
+    types::{Dimension, MetricDatum, StandardUnit},
+    Client as CloudWatchClient,
+};
+use aws_sdk_dynamodb::operation::scan::ScanOutput;
+use aws_sdk_dynamodb::{
+    operation::describe_table::DescribeTableError,
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, KeySchemaElement, KeyType, PutRequest,
+        ScalarAttributeType, TableStatus, Tag, WriteRequest,
+    },
+    Client as DynamoClient,
+};
+use aws_sdk_s3::{
+    operation::get_object::GetObjectError,
+    types::{Delete, Object, ObjectIdentifier},
+    Client as S3Client,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures::stream::{self, StreamExt};
 use lambda_runtime::Error;
 use serde::{Deserialize, Serialize};
-use serde_dynamo::{from_items, to_item};
-use tracing::info;
+use serde_dynamo::{from_item, from_items, to_item};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io::Write;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio::sync::OnceCell;
+use tracing::{error, info, warn};
+
+// Serialized backup size above which we warn that the function is close to OOM
+const DEFAULT_BACKUP_MEMORY_WARN_MB: usize = 256;
+
+// How long to wait for a freshly created table to become ACTIVE before writing
+const DEFAULT_TABLE_ACTIVE_TIMEOUT_SECONDS: u64 = 120;
+const TABLE_ACTIVE_MAX_POLL_SECONDS: u64 = 10;
+
+// Objects newer than this may belong to a backup whose metadata write is
+// still in flight, so they are never reported as orphaned
+const ORPHAN_MIN_AGE_SECONDS: i64 = 3600;
+// Backups younger than this may still be running, so they aren't incomplete yet
+const DEFAULT_INCOMPLETE_MIN_AGE_SECONDS: i64 = 3600;
+// DeleteObjects accepts at most 1000 keys
+const DELETE_OBJECTS_MAX_KEYS: usize = 1000;
+
+// Every scratch table the restore test creates carries this tag, so leftovers
+// can be found and only tables we created are ever force-deleted
+pub const SCRATCH_TABLE_TAG_KEY: &str = "aegis:scratch";
+pub const SCRATCH_TABLE_TAG_VALUE: &str = "restore-test";
+
+// Partition for items without a BACKUP_PARTITION_ATTR value
+pub const UNPARTITIONED: &str = "_unpartitioned";
+
+// BatchWriteItem accepts at most 25 put requests
+pub const RESTORE_BATCH_SIZE: usize = 25;
+// Attempts at a batch whose items keep coming back unprocessed
+const RESTORE_BATCH_MAX_ATTEMPTS: u32 = 5;
+// DynamoDB rejects items above 400 KB
+pub const MAX_ITEM_BYTES: usize = 400 * 1024;
+
+// Attempts at a scan page or backup upload that keeps failing transiently
+const SDK_RETRY_ATTEMPTS: u32 = 3;
+
+// Completed backups of a table, listed in backups/<table>/index.json so they
+// can be enumerated without paging through S3 or the metadata table
+pub const BACKUP_INDEX_FILE: &str = "index.json";
+
+// How many missing keys a verification report lists
+const MISSING_KEYS_SAMPLE_SIZE: usize = 10;
+
+// Item attribute (epoch seconds) compared against the last backup's timestamp
+// by incremental backups
+const DEFAULT_INCREMENTAL_ATTRIBUTE: &str = "updated_at";
+
+// Tables from `table_names` backed up at once
+const DEFAULT_BACKUP_CONCURRENCY: usize = 4;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Request {
+    // May be omitted when CONFIG_TABLE lists the tables to back up
+    #[serde(default)]
     pub table_name: String,
+    // Several tables in one invocation, backed up concurrently; table_name,
+    // when also set, is added to them
+    pub table_names: Option<Vec<String>>,
     pub backup_type: Option<String>, // "full" or "incremental"
+    // Smoke-test mode: return a canned response without calling AWS
+    #[serde(default)]
+    pub self_test: bool,
+    // "backup" (default), "find_orphans", "delete_orphans", "find_incomplete",
+    // "backup_growth", "verify_backup", "restore" or "cleanup"
+    pub action: Option<String>,
+    // Backups older than this many days are removed by "cleanup"
+    pub retention_days: Option<u32>,
+    // Backup to check or restore
+    pub backup_id: Option<String>,
+    // Table a restore writes into; it must already exist
+    pub target_table: Option<String>,
+    // Report where each config value came from in `config_trace`
+    #[serde(default)]
+    pub debug: bool,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -19,6 +114,191 @@ pub struct Response {
     pub backup_id: String,
     pub timestamp: String,
     pub items_backed_up: usize,
+    // The serialized backup exceeded BACKUP_MEMORY_WARN_MB
+    pub memory_pressure: bool,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_trace: Option<HashMap<String, String>>,
+    // cold_start and init_duration_ms, set by the handler
+    #[serde(flatten)]
+    pub init: Option<InitTiming>,
+}
+
+// One response per table when the tables came from CONFIG_TABLE
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum BackupOutput {
+    Single(Response),
+    Multiple(Vec<Response>),
+    Orphans(OrphanReport),
+    Incomplete(IncompleteReport),
+    Growth(GrowthReport),
+    Index(BackupIndexReport),
+    List(BackupListResponse),
+    Verification(RestoreVerification),
+    Restore(RestoreResponse),
+    Cleanup(CleanupReport),
+    Batch(BatchBackupResponse),
+}
+
+impl BackupOutput {
+    pub fn with_init(self, init: InitTiming) -> Self {
+        match self {
+            Self::Single(response) => Self::Single(Response {
+                init: Some(init),
+                ..response
+            }),
+            Self::Multiple(responses) => Self::Multiple(
+                responses
+                    .into_iter()
+                    .map(|response| Response {
+                        init: Some(init),
+                        ..response
+                    })
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    pub fn with_config_trace(self, trace: HashMap<String, String>) -> Self {
+        match self {
+            Self::Single(response) => Self::Single(Response {
+                config_trace: Some(trace),
+                ..response
+            }),
+            Self::Multiple(responses) => Self::Multiple(
+                responses
+                    .into_iter()
+                    .map(|response| Response {
+                        config_trace: Some(trace.clone()),
+                        ..response
+                    })
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+}
+
+// A backup object in S3 with no row in the metadata table
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OrphanedBackup {
+    pub key: String,
+    pub backup_id: String,
+    pub size_bytes: i64,
+}
+
+// A backup that started but never finished
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IncompleteBackup {
+    pub backup_id: String,
+    pub table_name: String,
+    pub status: String,
+    pub age_seconds: i64,
+    // Objects the metadata lists that aren't in the bucket
+    pub missing_objects: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IncompleteReport {
+    pub incomplete: Vec<IncompleteBackup>,
+    pub version: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OrphanReport {
+    pub orphans: Vec<OrphanedBackup>,
+    pub deleted: usize,
+    pub version: String,
+}
+
+// Results of backing up `table_names`. One table failing doesn't stop the rest.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BatchBackupResponse {
+    pub results: Vec<Response>,
+    pub failures: Vec<BackupFailure>,
+    pub total_items: usize,
+    pub version: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BackupFailure {
+    pub table_name: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CleanupReport {
+    pub retention_days: u32,
+    pub deleted: usize,
+    pub version: String,
+}
+
+// Change between two consecutive full backups of a table
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BackupGrowth {
+    pub from_backup_id: String,
+    pub to_backup_id: String,
+    pub item_delta: i64,
+    // None when either backup predates size tracking
+    pub size_delta: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GrowthReport {
+    pub table_name: String,
+    pub steps: Vec<BackupGrowth>,
+    pub total_item_delta: i64,
+    pub version: String,
+}
+
+// Backups recorded in the metadata table, newest first
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BackupListResponse {
+    pub backups: Vec<BackupMetadata>,
+    pub count: usize,
+    pub version: String,
+}
+
+// The table's backup index after a rebuild
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BackupIndexReport {
+    pub table_name: String,
+    pub backups: usize,
+    pub version: String,
+}
+
+// Outcome of checking a backup against what it should contain. Keys are the
+// table's key attribute values joined with '/'.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RestoreVerification {
+    pub backup_id: String,
+    pub table_name: String,
+    pub expected_count: usize,
+    pub actual_count: usize,
+    pub missing_keys_sample: Vec<String>,
+    pub pass: bool,
+    pub version: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RestoreResponse {
+    // "partial" when oversized items were left out
+    pub status: String,
+    pub backup_id: String,
+    pub target_table: String,
+    pub items_restored: usize,
+    pub oversized_items: Vec<OversizedItem>,
+    pub version: String,
+}
+
+// A backed-up item over DynamoDB's item size limit, which no write accepts
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OversizedItem {
+    // Key attribute values joined with '/'; None when the item lacks one
+    pub key: Option<String>,
+    pub size_bytes: usize,
 }
 
 // This struct is used to serialize/deserialize data to/from DynamoDB
@@ -29,6 +309,47 @@ pub struct BackupMetadata {
     pub timestamp: String,
     pub items_count: usize,
     pub status: String,
+    // Attributes included when the backup was restricted by BACKUP_PROJECTION;
+    // None means every attribute was backed up
+    #[serde(default)]
+    pub projection: Option<Vec<String>>,
+    // Set when BACKUP_PARTITION_ATTR split the backup into one object per value
+    // of this attribute, stored under backups/<table>/<partition>/
+    #[serde(default)]
+    pub partition_attr: Option<String>,
+    #[serde(default)]
+    pub partitions: Option<Vec<String>>,
+    #[serde(default)]
+    pub item_format: ItemFormat,
+    // Objects are gzipped and end in .json.gz; false for backups written
+    // before compression, stored as plain .json
+    #[serde(default)]
+    pub compressed: bool,
+    // Bytes stored in S3 across the backup's objects; None for backups
+    // written before this was recorded
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+}
+
+// How items are written to backup objects. Plain JSON loses the DynamoDB type
+// of sets, binary and nested maps; DynamodbJson wraps every value in its type,
+// e.g. {"SS": ["a", "b"]}, so a restore recreates the item exactly.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemFormat {
+    #[default]
+    Plain,
+    DynamodbJson,
+}
+
+impl ItemFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "plain" => Some(Self::Plain),
+            "dynamodb_json" => Some(Self::DynamodbJson),
+            _ => None,
+        }
+    }
 }
 
 // This is a generic struct that can be serialized from DynamoDB items
@@ -38,78 +359,650 @@ pub struct GenericItem {
     pub attributes: std::collections::HashMap<String, serde_json::Value>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatedBackup {
+    pub backup_id: String,
+    pub items_count: usize,
+    pub memory_pressure: bool,
+    pub partitions: Option<Vec<String>>,
+    pub size_bytes: u64,
+}
+
 pub struct BackupManagerService {
     pub dynamo_client: DynamoClient,
+    // In BACKUP_BUCKET_REGION when that differs from the function's region
     pub s3_client: S3Client,
+    pub cloudwatch_client: CloudWatchClient,
+    pub events: EventEmitter,
     pub backup_bucket: String,
     pub metadata_table: String,
+    // AUTO_CREATE_METADATA_TABLE: create metadata_table when it is missing
+    // instead of refusing to back up
+    pub auto_create_metadata_table: bool,
+    pub backup_projection: Option<Vec<String>>,
+    pub config_table: Option<String>,
+    pub memory_warn_mb: usize,
+    pub table_active_timeout_seconds: u64,
+    // BACKUP_TAG as (key, value), e.g. "dr:backup=true"
+    pub backup_tag: Option<(String, String)>,
+    // BACKUP_PARTITION_ATTR, e.g. "tenant_id", for per-tenant backup objects
+    pub partition_attr: Option<String>,
+    // BACKUP_STABLE_ORDER: sort items by primary key so identical contents
+    // produce byte-identical backups
+    pub stable_order: bool,
+    // BACKUP_ITEM_FORMAT: "plain" (default) or "dynamodb_json"
+    pub item_format: ItemFormat,
+    // INCREMENTAL_ATTRIBUTE: an incremental backup exports items whose value
+    // here is newer than the table's last completed backup
+    pub incremental_attribute: String,
+    pub incomplete_min_age_seconds: i64,
+    // RESTORE_FAIL_ON_OVERSIZED: fail a restore on an item over MAX_ITEM_BYTES
+    // instead of skipping it and reporting it in oversized_items
+    pub fail_on_oversized: bool,
+    // BACKUP_CONCURRENCY: tables of a multi-table request backed up at once
+    pub backup_concurrency: usize,
+    // Shared by every retried call in this invocation
+    pub retry_budget: RetryBudget,
+    // Tag discovery lists every table, so it runs at most once per invocation
+    discovered_tables: OnceCell<Vec<String>>,
+    // Source of each environment-backed setting above
+    pub config_trace: ConfigTrace,
 }
 
 impl BackupManagerService {
     pub async fn new() -> Result<Self, Error> {
-        let config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        let config = aws_config_loader().load().await;
 
         let backup_bucket = std::env::var("BACKUP_BUCKET")
             .unwrap_or_else(|_| "dr-demo-backup-bucket-primary".to_string());
         let metadata_table =
             std::env::var("METADATA_TABLE").unwrap_or_else(|_| "dr-backup-metadata".to_string());
+        let backup_projection = std::env::var("BACKUP_PROJECTION")
+            .ok()
+            .and_then(|value| parse_projection(&value));
+        let config_table = std::env::var("CONFIG_TABLE").ok();
+        let memory_warn_mb = std::env::var("BACKUP_MEMORY_WARN_MB")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_BACKUP_MEMORY_WARN_MB);
+        let table_active_timeout_seconds = std::env::var("TABLE_ACTIVE_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_TABLE_ACTIVE_TIMEOUT_SECONDS);
+        let backup_tag = std::env::var("BACKUP_TAG")
+            .ok()
+            .and_then(|value| parse_tag_filter(&value));
+        let partition_attr = std::env::var("BACKUP_PARTITION_ATTR")
+            .ok()
+            .filter(|value| !value.is_empty());
+        let stable_order = std::env::var("BACKUP_STABLE_ORDER")
+            .map(|value| parse_flag(&value))
+            .unwrap_or(false);
+        let auto_create_metadata_table = std::env::var("AUTO_CREATE_METADATA_TABLE")
+            .map(|value| parse_flag(&value))
+            .unwrap_or(false);
+        // The bucket may live in another region than the function
+        let bucket_region = std::env::var("BACKUP_BUCKET_REGION")
+            .ok()
+            .filter(|value| !value.is_empty());
+        let s3_client = match bucket_region {
+            Some(region) if config.region().map(|r| r.as_ref()) != Some(region.as_str()) => {
+                info!("Using backup bucket region {}", region);
+                let bucket_config = aws_config_loader()
+                    .region(aws_config::Region::new(region))
+                    .load()
+                    .await;
+                S3Client::new(&bucket_config)
+            }
+            _ => S3Client::new(&config),
+        };
+        let item_format = std::env::var("BACKUP_ITEM_FORMAT")
+            .ok()
+            .and_then(|value| ItemFormat::parse(&value))
+            .unwrap_or_default();
+        let incremental_attribute = std::env::var("INCREMENTAL_ATTRIBUTE")
+            .ok()
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| DEFAULT_INCREMENTAL_ATTRIBUTE.to_string());
+
+        let incomplete_min_age_seconds = std::env::var("INCOMPLETE_BACKUP_MIN_AGE_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_INCOMPLETE_MIN_AGE_SECONDS);
+
+        let fail_on_oversized = std::env::var("RESTORE_FAIL_ON_OVERSIZED")
+            .map(|value| parse_flag(&value))
+            .unwrap_or(false);
+
+        let backup_concurrency = std::env::var("BACKUP_CONCURRENCY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&value: &usize| value > 0)
+            .unwrap_or(DEFAULT_BACKUP_CONCURRENCY);
+
+        let mut config_trace = ConfigTrace::new();
+        config_trace.record_env(&[
+            "BACKUP_BUCKET",
+            "METADATA_TABLE",
+            "AUTO_CREATE_METADATA_TABLE",
+            "BACKUP_PROJECTION",
+            "CONFIG_TABLE",
+            "BACKUP_MEMORY_WARN_MB",
+            "TABLE_ACTIVE_TIMEOUT_SECONDS",
+            "BACKUP_TAG",
+            "BACKUP_PARTITION_ATTR",
+            "BACKUP_STABLE_ORDER",
+            "BACKUP_ITEM_FORMAT",
+            "BACKUP_BUCKET_REGION",
+            "INCREMENTAL_ATTRIBUTE",
+            "INCOMPLETE_BACKUP_MIN_AGE_SECONDS",
+            "RESTORE_FAIL_ON_OVERSIZED",
+            "BACKUP_CONCURRENCY",
+        ]);
 
         Ok(Self {
             dynamo_client: DynamoClient::new(&config),
-            s3_client: S3Client::new(&config),
+            s3_client,
+            cloudwatch_client: CloudWatchClient::new(&config),
+            events: EventEmitter::new(&config, env!("CARGO_PKG_NAME")),
             backup_bucket,
             metadata_table,
+            auto_create_metadata_table,
+            backup_projection,
+            config_table,
+            memory_warn_mb,
+            table_active_timeout_seconds,
+            backup_tag,
+            partition_attr,
+            stable_order,
+            item_format,
+            incremental_attribute,
+            incomplete_min_age_seconds,
+            fail_on_oversized,
+            backup_concurrency,
+            retry_budget: RetryBudget::from_env(),
+            discovered_tables: OnceCell::new(),
+            config_trace,
         })
     }
 
+    // Tables carrying BACKUP_TAG; empty when no tag is configured
+    pub async fn discover_tagged_tables(&self) -> Result<Vec<String>, Error> {
+        let (key, value) = match &self.backup_tag {
+            Some(tag) => tag,
+            None => return Ok(Vec::new()),
+        };
+
+        self.discovered_tables
+            .get_or_try_init(|| self.find_tables_with_tag(key, value))
+            .await
+            .cloned()
+    }
+
+    async fn find_tables_with_tag(&self, key: &str, value: &str) -> Result<Vec<String>, Error> {
+        let mut table_names = Vec::new();
+        let mut start_table_name = None;
+
+        loop {
+            let result = self
+                .dynamo_client
+                .list_tables()
+                .set_exclusive_start_table_name(start_table_name)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:ListTables", "*")?;
+
+            table_names.extend(result.table_names.unwrap_or_default());
+
+            match result.last_evaluated_table_name {
+                Some(name) => start_table_name = Some(name),
+                None => break,
+            }
+        }
+
+        let mut tagged = Vec::new();
+        for table_name in table_names {
+            let description = self
+                .dynamo_client
+                .describe_table()
+                .table_name(&table_name)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:DescribeTable", &table_name)?;
+
+            let table_arn = match description.table.and_then(|table| table.table_arn) {
+                Some(arn) => arn,
+                None => continue,
+            };
+
+            let tags = self.table_tags(&table_arn).await?;
+            if has_tag(&tags, key, value) {
+                tagged.push(table_name);
+            }
+        }
+
+        info!(
+            "Discovered {} tables tagged {}={}",
+            tagged.len(),
+            key,
+            value
+        );
+        Ok(tagged)
+    }
+
+    async fn table_tags(&self, table_arn: &str) -> Result<Vec<Tag>, Error> {
+        let mut tags = Vec::new();
+        let mut next_token = None;
+
+        loop {
+            let result = self
+                .dynamo_client
+                .list_tags_of_resource()
+                .resource_arn(table_arn)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:ListTagsOfResource", table_arn)?;
+
+            tags.extend(result.tags.unwrap_or_default());
+
+            match result.next_token {
+                Some(token) => next_token = Some(token),
+                None => break,
+            }
+        }
+
+        Ok(tags)
+    }
+
+    // Delete a scratch table, turning off deletion protection first if the
+    // account enforces it. Refuses tables without the scratch tag.
+    pub async fn delete_scratch_table(&self, table_name: &str) -> Result<(), Error> {
+        let table = self
+            .dynamo_client
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:DescribeTable", table_name)?
+            .table
+            .ok_or_else(|| Error::from(format!("Table {} has no description", table_name)))?;
+
+        let table_arn = table.table_arn.clone().unwrap_or_default();
+        let tags = self.table_tags(&table_arn).await?;
+        if !has_tag(&tags, SCRATCH_TABLE_TAG_KEY, SCRATCH_TABLE_TAG_VALUE) {
+            return Err(Error::from(format!(
+                "Refusing to delete {}: it is not tagged {}={}",
+                table_name, SCRATCH_TABLE_TAG_KEY, SCRATCH_TABLE_TAG_VALUE
+            )));
+        }
+
+        if table.deletion_protection_enabled == Some(true) {
+            info!(
+                "Disabling deletion protection on scratch table {}",
+                table_name
+            );
+            self.dynamo_client
+                .update_table()
+                .table_name(table_name)
+                .deletion_protection_enabled(false)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:UpdateTable", table_name)?;
+            self.wait_for_table_active(table_name).await?;
+        }
+
+        self.dynamo_client
+            .delete_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:DeleteTable", table_name)?;
+
+        info!("Deleted scratch table {}", table_name);
+        Ok(())
+    }
+
+    // Remove scratch tables left behind by restore tests that didn't clean up
+    pub async fn cleanup_scratch_tables(&self) -> Result<Vec<String>, Error> {
+        let leftovers = self
+            .find_tables_with_tag(SCRATCH_TABLE_TAG_KEY, SCRATCH_TABLE_TAG_VALUE)
+            .await?;
+
+        let mut deleted = Vec::with_capacity(leftovers.len());
+        for table_name in leftovers {
+            match self.delete_scratch_table(&table_name).await {
+                Ok(()) => deleted.push(table_name),
+                Err(e) => error!("Failed to delete scratch table {}: {}", table_name, e),
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    // Read the tables opted into backup from CONFIG_TABLE. Callers load this once
+    // per invocation and reuse the result.
+    pub async fn load_configured_tables(&self) -> Result<Vec<String>, Error> {
+        let config_table = match &self.config_table {
+            Some(config_table) => config_table,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut items = Vec::new();
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = self
+                .dynamo_client
+                .scan()
+                .table_name(config_table)
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:Scan", config_table)?;
+
+            items.extend(result.items.unwrap_or_default());
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(configured_table_names(&items, "backup"))
+    }
+
+    // Backups are recorded in metadata_table only after the upload, so a missing
+    // table would leave untracked objects in S3. Checked before any backup work.
+    pub async fn ensure_metadata_table(&self) -> Result<(), Error> {
+        let result = self
+            .dynamo_client
+            .describe_table()
+            .table_name(&self.metadata_table)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e)
+                if matches!(
+                    e.as_service_error(),
+                    Some(DescribeTableError::ResourceNotFoundException(_))
+                ) => {}
+            Err(e) => {
+                return Err(map_sdk_error(
+                    e,
+                    "dynamodb:DescribeTable",
+                    &self.metadata_table,
+                ))
+            }
+        }
+
+        if !self.auto_create_metadata_table {
+            return Err(Error::from(format!(
+                "Metadata table {} does not exist; create it or set AUTO_CREATE_METADATA_TABLE=true",
+                self.metadata_table
+            )));
+        }
+
+        warn!(
+            "Metadata table {} is missing; creating it",
+            self.metadata_table
+        );
+        self.dynamo_client
+            .create_table()
+            .table_name(&self.metadata_table)
+            .billing_mode(BillingMode::PayPerRequest)
+            .attribute_definitions(
+                AttributeDefinition::builder()
+                    .attribute_name("backup_id")
+                    .attribute_type(ScalarAttributeType::S)
+                    .build()?,
+            )
+            .key_schema(
+                KeySchemaElement::builder()
+                    .attribute_name("backup_id")
+                    .key_type(KeyType::Hash)
+                    .build()?,
+            )
+            .send()
+            .await
+            .map_sdk_err("dynamodb:CreateTable", &self.metadata_table)?;
+
+        self.wait_for_table_active(&self.metadata_table).await
+    }
+
+    // Writes to a table that is still CREATING fail, so anything that creates a
+    // table and then writes to it (restores) must wait here first
+    pub async fn wait_for_table_active(&self, table_name: &str) -> Result<(), Error> {
+        let deadline = std::time::Instant::now()
+            + std::time::Duration::from_secs(self.table_active_timeout_seconds);
+        let mut attempt = 0;
+
+        loop {
+            let result = self
+                .dynamo_client
+                .describe_table()
+                .table_name(table_name)
+                .send()
+                .await;
+
+            let status = match result {
+                Ok(response) => response.table.and_then(|table| table.table_status),
+                // A just-created table may not be visible yet
+                Err(e)
+                    if matches!(
+                        e.as_service_error(),
+                        Some(DescribeTableError::ResourceNotFoundException(_))
+                    ) =>
+                {
+                    None
+                }
+                Err(e) => return Err(map_sdk_error(e, "dynamodb:DescribeTable", table_name)),
+            };
+
+            if status == Some(TableStatus::Active) {
+                return Ok(());
+            }
+
+            let delay = table_poll_delay(attempt);
+            if std::time::Instant::now() + delay > deadline {
+                return Err(Error::from(format!(
+                    "Table {} did not become ACTIVE within {} seconds (last status: {:?})",
+                    table_name, self.table_active_timeout_seconds, status
+                )));
+            }
+
+            info!(
+                "Waiting for table {} to become ACTIVE (status: {:?})",
+                table_name, status
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
     pub async fn create_backup(
         &self,
         table_name: &str,
         backup_type: &str,
-    ) -> Result<(String, usize), Error> {
+    ) -> Result<CreatedBackup, Error> {
         let backup_id = format!("{}-{}-{}", table_name, backup_type, Utc::now().timestamp());
 
+        let last_backup = if backup_type == "incremental" {
+            self.get_last_backup_timestamp(table_name).await?
+        } else {
+            None
+        };
+        let filter = incremental_filter(backup_type, &self.incremental_attribute, last_backup);
+        if backup_type == "incremental" && filter.is_none() {
+            info!(
+                "No completed backup of {} yet; incremental backup exports every item",
+                table_name
+            );
+        }
+
         // Scan the table (for demo purposes - in production, use DynamoDB's built-in backup)
-        let mut items = Vec::new();
-        let mut last_evaluated_key = None;
+        let pages = scan_all_pages(|exclusive_start_key| {
+            let mut scan_request = self
+                .dynamo_client
+                .scan()
+                .table_name(table_name)
+                .set_exclusive_start_key(exclusive_start_key);
 
-        loop {
-            let mut scan_request = self.dynamo_client.scan().table_name(table_name);
+            // Only whitelisted attributes leave the table when a projection is configured
+            if let Some(projection) = &self.backup_projection {
+                let (expression, names) = aliased_projection(projection);
+                scan_request = scan_request.projection_expression(expression);
+                for (placeholder, name) in names {
+                    scan_request = scan_request.expression_attribute_names(placeholder, name);
+                }
+            }
+
+            if let Some(filter) = &filter {
+                scan_request = scan_request
+                    .filter_expression(&filter.expression)
+                    .expression_attribute_names(&filter.name_placeholder, &filter.attribute)
+                    .expression_attribute_values(&filter.value_placeholder, filter.value.clone());
+            }
 
-            if let Some(key) = last_evaluated_key {
-                scan_request = scan_request.set_exclusive_start_key(Some(key));
+            async move {
+                with_retry(&self.retry_budget, SDK_RETRY_ATTEMPTS, || {
+                    scan_request.clone().send()
+                })
+                .await
+                .map_sdk_err("dynamodb:Scan", table_name)
             }
+        })
+        .await?;
 
-            let result = scan_request.send().await?;
+        let mut items = Vec::new();
+        for page in pages {
+            warn_on_missing_scan_items(&page, table_name);
 
             // Convert DynamoDB items to a generic format
-            if let Some(scan_items) = result.items {
-                let generic_items: Vec<GenericItem> = from_items(scan_items)?;
+            if let Some(scan_items) = page.items {
+                let generic_items: Vec<GenericItem> = match self.item_format {
+                    ItemFormat::Plain => from_items(scan_items)?,
+                    ItemFormat::DynamodbJson => {
+                        scan_items.iter().map(item_to_dynamodb_json).collect()
+                    }
+                };
                 items.extend(generic_items);
             }
+        }
+
+        let items_count = items.len();
+
+        // Scan order varies between runs, which would defeat checksums and diffs
+        if self.stable_order {
+            let key_attributes = self.key_attributes(table_name).await?;
+            sort_items(&mut items, &key_attributes);
+        }
+
+        // One object for the whole table, or one per partition value
+        let (objects, partitions) = match &self.partition_attr {
+            Some(attribute) => {
+                let groups = partition_items(items, attribute);
+                let partitions: Vec<String> = groups.keys().cloned().collect();
+                let mut objects = Vec::with_capacity(groups.len());
+                for (partition, group) in groups {
+                    objects.push((
+                        format!("backups/{}/{}/{}.json.gz", table_name, partition, backup_id),
+                        serialize_items(&group, self.stable_order)?,
+                    ));
+                }
+                (objects, Some(partitions))
+            }
+            None => (
+                vec![(
+                    format!("backups/{}/{}.json.gz", table_name, backup_id),
+                    serialize_items(&items, self.stable_order)?,
+                )],
+                None,
+            ),
+        };
+        let backup_bytes: usize = objects.iter().map(|(_, data)| data.len()).sum();
+
+        // The whole table is held in memory until multipart streaming is in place
+        let memory_pressure = exceeds_memory_threshold(backup_bytes, self.memory_warn_mb);
+        if memory_pressure {
+            warn!(
+                "Backup of {} is {} bytes, above BACKUP_MEMORY_WARN_MB ({} MB); move this table to the streaming backup path before it exhausts Lambda memory",
+                table_name,
+                backup_bytes,
+                self.memory_warn_mb
+            );
 
-            if result.last_evaluated_key.is_none() {
-                break;
+            if let Err(e) = self.publish_memory_pressure(table_name, backup_bytes).await {
+                error!("Failed to publish memory pressure metric: {}", e);
             }
+        }
 
-            last_evaluated_key = result.last_evaluated_key;
+        let mut size_bytes = 0;
+        for (key, data) in objects {
+            let compressed = gzip(data.as_bytes())?;
+            size_bytes += compressed.len() as u64;
+            with_retry(&self.retry_budget, SDK_RETRY_ATTEMPTS, || {
+                self.s3_client
+                    .put_object()
+                    .bucket(&self.backup_bucket)
+                    .key(&key)
+                    .content_encoding("gzip")
+                    .body(compressed.clone().into())
+                    .send()
+            })
+            .await
+            .map_sdk_err("s3:PutObject", &format!("{}/{}", self.backup_bucket, key))?;
         }
 
-        // Convert items to JSON and upload to S3
-        let backup_data = serde_json::to_string(&items)?;
-        let key = format!("backups/{}/{}.json", table_name, backup_id);
+        match &partitions {
+            Some(partitions) => info!(
+                "Created backup {} with {} items in {} partitions",
+                backup_id,
+                items_count,
+                partitions.len()
+            ),
+            None => info!("Created backup {} with {} items", backup_id, items_count),
+        }
 
-        self.s3_client
-            .put_object()
-            .bucket(&self.backup_bucket)
-            .key(&key)
-            .body(backup_data.into_bytes().into())
+        Ok(CreatedBackup {
+            backup_id,
+            items_count,
+            memory_pressure,
+            partitions,
+            size_bytes,
+        })
+    }
+
+    async fn publish_memory_pressure(
+        &self,
+        table_name: &str,
+        payload_bytes: usize,
+    ) -> Result<(), Error> {
+        let metric = MetricDatum::builder()
+            .metric_name("BackupMemoryPressure")
+            .value(payload_bytes as f64)
+            .unit(StandardUnit::Bytes)
+            .dimensions(
+                Dimension::builder()
+                    .name("TableName")
+                    .value(table_name)
+                    .build(),
+            )
+            .timestamp(aws_sdk_cloudwatch::primitives::DateTime::from(
+                std::time::SystemTime::now(),
+            ))
+            .build();
+
+        self.cloudwatch_client
+            .put_metric_data()
+            .namespace("DisasterRecovery")
+            .metric_data(metric)
             .send()
             .await?;
 
-        info!("Created backup {} with {} items", backup_id, items.len());
-
-        Ok((backup_id, items.len()))
+        Ok(())
     }
 
     pub async fn update_backup_metadata(
@@ -117,6 +1010,8 @@ impl BackupManagerService {
         backup_id: &str,
         table_name: &str,
         items_count: usize,
+        partitions: Option<Vec<String>>,
+        size_bytes: u64,
     ) -> Result<(), Error> {
         let metadata = BackupMetadata {
             backup_id: backup_id.to_string(),
@@ -124,50 +1019,1601 @@ impl BackupManagerService {
             timestamp: Utc::now().timestamp().to_string(),
             items_count,
             status: "completed".to_string(),
+            projection: self.backup_projection.clone(),
+            partition_attr: partitions.as_ref().and(self.partition_attr.clone()),
+            item_format: self.item_format,
+            compressed: true,
+            size_bytes: Some(size_bytes),
+            partitions,
         };
 
         // Convert to DynamoDB item
-        let item = to_item(metadata)?;
+        let item = to_item(metadata.clone())?;
 
         self.dynamo_client
             .put_item()
             .table_name(&self.metadata_table)
             .set_item(Some(item))
             .send()
-            .await?;
+            .await
+            .map_sdk_err("dynamodb:PutItem", &self.metadata_table)?;
+
+        // The backup is complete either way; a stale index is fixed by a rebuild
+        if let Err(e) = self.add_to_index(metadata).await {
+            error!(
+                "Failed to add backup {} to the index of {}: {}",
+                backup_id, table_name, e
+            );
+        }
 
         Ok(())
     }
 
-    pub async fn run_backup(&self, table_name: &str, backup_type: &str) -> Result<Response, Error> {
-        // Create backup
-        let (backup_id, items_count) = self.create_backup(table_name, backup_type).await?;
-
-        // Update metadata
-        self.update_backup_metadata(&backup_id, table_name, items_count)
-            .await?;
+    async fn add_to_index(&self, metadata: BackupMetadata) -> Result<(), Error> {
+        let table_name = metadata.table_name.clone();
+        match self.read_backup_index(&table_name).await? {
+            Some(backups) => {
+                self.write_backup_index(&table_name, &index_with(backups, metadata))
+                    .await
+            }
+            None => self.rebuild_index(&table_name).await.map(|_| ()),
+        }
+    }
 
-        Ok(Response {
-            status: "success".to_string(),
-            backup_id,
-            timestamp: Utc::now().to_rfc3339(),
-            items_backed_up: items_count,
-        })
+    // None when the table has no index yet
+    async fn read_backup_index(
+        &self,
+        table_name: &str,
+    ) -> Result<Option<Vec<BackupMetadata>>, Error> {
+        let key = backup_index_key(table_name);
+        match self
+            .s3_client
+            .get_object()
+            .bucket(&self.backup_bucket)
+            .key(&key)
+            .send()
+            .await
+        {
+            Ok(object) => {
+                let body = object.body.collect().await?.into_bytes();
+                Ok(Some(serde_json::from_slice(&body)?))
+            }
+            Err(e) if matches!(e.as_service_error(), Some(GetObjectError::NoSuchKey(_))) => {
+                Ok(None)
+            }
+            Err(e) => Err(map_sdk_error(
+                e,
+                "s3:GetObject",
+                &format!("{}/{}", self.backup_bucket, key),
+            )),
+        }
     }
-}
 
-// Utility functions for testing
-pub fn generate_backup_id(table_name: &str, backup_type: &str, timestamp: i64) -> String {
-    format!("{}-{}-{}", table_name, backup_type, timestamp)
-}
+    async fn write_backup_index(
+        &self,
+        table_name: &str,
+        backups: &[BackupMetadata],
+    ) -> Result<(), Error> {
+        let key = backup_index_key(table_name);
+        self.s3_client
+            .put_object()
+            .bucket(&self.backup_bucket)
+            .key(&key)
+            .content_type("application/json")
+            .body(serde_json::to_vec(backups)?.into())
+            .send()
+            .await
+            .map_sdk_err("s3:PutObject", &format!("{}/{}", self.backup_bucket, key))?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        Ok(())
+    }
 
-    #[test]
-    fn test_request_deserialization() {
-        let json = r#"{"table_name": "test-table", "backup_type": "incremental"}"#;
+    // Rewrites the table's index from its backup objects in S3, keeping those
+    // with completed metadata. For an index that has drifted or was never written.
+    pub async fn rebuild_index(&self, table_name: &str) -> Result<Vec<BackupMetadata>, Error> {
+        let prefix = format!("backups/{}/", table_name);
+        let mut backup_ids = HashSet::new();
+        for object in self.list_backup_objects().await? {
+            if let Some(backup_id) = object
+                .key
+                .as_deref()
+                .filter(|key| key.starts_with(&prefix))
+                .and_then(backup_id_from_key)
+            {
+                backup_ids.insert(backup_id.to_string());
+            }
+        }
+
+        let mut backups = Vec::new();
+        for backup_id in backup_ids {
+            match self.load_backup_metadata(&backup_id).await {
+                Ok(metadata) if metadata.status == "completed" => {
+                    backups = index_with(backups, metadata)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Leaving backup {} out of the index: {}", backup_id, e),
+            }
+        }
+
+        self.write_backup_index(table_name, &backups).await?;
+        info!(
+            "Rebuilt the backup index of {} with {} backups",
+            table_name,
+            backups.len()
+        );
+
+        Ok(backups)
+    }
+
+    // Timestamp (epoch seconds) of the table's newest completed backup, full
+    // or incremental; None when it has never been backed up
+    pub async fn get_last_backup_timestamp(&self, table_name: &str) -> Result<Option<i64>, Error> {
+        let backups = self.list_backups(table_name).await?;
+        Ok(last_completed_timestamp(&backups, table_name))
+    }
+
+    // Completed backups of the table, oldest first, read from its index. The
+    // first call for a table without one builds it.
+    pub async fn list_backups(&self, table_name: &str) -> Result<Vec<BackupMetadata>, Error> {
+        match self.read_backup_index(table_name).await? {
+            Some(backups) => Ok(backups),
+            None => self.rebuild_index(table_name).await,
+        }
+    }
+
+    // How the table's full backups grew from one to the next. The newest
+    // step is published as BackupItemGrowth and BackupSizeGrowth.
+    pub async fn backup_growth(&self, table_name: &str) -> Result<GrowthReport, Error> {
+        let backups = self.list_backups(table_name).await?;
+        let steps = backup_growth(&backups);
+
+        if let Some(latest) = steps.last() {
+            info!(
+                "Latest backup of {} grew by {} items ({:?} bytes)",
+                table_name, latest.item_delta, latest.size_delta
+            );
+            if let Err(e) = self.publish_backup_growth(table_name, latest).await {
+                error!("Failed to publish backup growth metrics: {}", e);
+            }
+        }
+
+        Ok(GrowthReport {
+            table_name: table_name.to_string(),
+            total_item_delta: steps.iter().map(|step| step.item_delta).sum(),
+            steps,
+            version: version(),
+        })
+    }
+
+    async fn publish_backup_growth(
+        &self,
+        table_name: &str,
+        growth: &BackupGrowth,
+    ) -> Result<(), Error> {
+        let timestamp =
+            aws_sdk_cloudwatch::primitives::DateTime::from(std::time::SystemTime::now());
+        let dimension = Dimension::builder()
+            .name("TableName")
+            .value(table_name)
+            .build();
+
+        let mut metrics = vec![MetricDatum::builder()
+            .metric_name("BackupItemGrowth")
+            .value(growth.item_delta as f64)
+            .unit(StandardUnit::Count)
+            .dimensions(dimension.clone())
+            .timestamp(timestamp)
+            .build()];
+        if let Some(size_delta) = growth.size_delta {
+            metrics.push(
+                MetricDatum::builder()
+                    .metric_name("BackupSizeGrowth")
+                    .value(size_delta as f64)
+                    .unit(StandardUnit::Bytes)
+                    .dimensions(dimension)
+                    .timestamp(timestamp)
+                    .build(),
+            );
+        }
+
+        self.cloudwatch_client
+            .put_metric_data()
+            .namespace("DisasterRecovery")
+            .set_metric_data(Some(metrics))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    // Backup objects left behind when the upload succeeded but the metadata
+    // write didn't. Only objects older than ORPHAN_MIN_AGE_SECONDS are considered.
+    pub async fn find_orphaned_backups(&self) -> Result<Vec<OrphanedBackup>, Error> {
+        let cutoff = Utc::now().timestamp() - ORPHAN_MIN_AGE_SECONDS;
+        let objects = self
+            .list_backup_objects()
+            .await?
+            .into_iter()
+            .filter(|object| {
+                object
+                    .last_modified
+                    .is_some_and(|modified| modified.secs() < cutoff)
+            })
+            .filter_map(|object| Some((object.key?, object.size.unwrap_or(0))))
+            .collect();
+
+        let known = self.load_backup_ids().await?;
+        let orphans = orphaned_backups(objects, &known);
+
+        info!(
+            "Found {} orphaned backup objects in {}",
+            orphans.len(),
+            self.backup_bucket
+        );
+        Ok(orphans)
+    }
+
+    // Backups that never finished: a non-completed status, or an object listed
+    // in the metadata that isn't in S3. Only backups older than
+    // incomplete_min_age_seconds are considered, so running ones aren't reported.
+    pub async fn find_incomplete_backups(&self) -> Result<Vec<IncompleteBackup>, Error> {
+        let backups = self.load_all_backup_metadata().await?;
+
+        let object_keys: HashSet<String> = self
+            .list_backup_objects()
+            .await?
+            .into_iter()
+            .filter_map(|object| object.key)
+            .collect();
+
+        let incomplete = incomplete_backups(
+            &backups,
+            &object_keys,
+            Utc::now().timestamp(),
+            self.incomplete_min_age_seconds,
+        );
+
+        if incomplete.is_empty() {
+            info!("No incomplete backups among {} recorded", backups.len());
+        } else {
+            warn!(
+                "Found {} incomplete backups: {:?}",
+                incomplete.len(),
+                incomplete
+                    .iter()
+                    .map(|backup| &backup.backup_id)
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        if let Err(e) = self.publish_incomplete_backups(incomplete.len()).await {
+            error!("Failed to publish incomplete backups metric: {}", e);
+        }
+
+        Ok(incomplete)
+    }
+
+    // Every backup in the metadata table whatever its status, optionally only
+    // those of one table
+    pub async fn list_backup_records(
+        &self,
+        table_name: Option<&str>,
+    ) -> Result<BackupListResponse, Error> {
+        let backups = self.load_all_backup_metadata().await?;
+        Ok(backup_list(backups, table_name))
+    }
+
+    async fn load_all_backup_metadata(&self) -> Result<Vec<BackupMetadata>, Error> {
+        let pages = scan_all_pages(|exclusive_start_key| {
+            let scan_request = self
+                .dynamo_client
+                .scan()
+                .table_name(&self.metadata_table)
+                .set_exclusive_start_key(exclusive_start_key);
+
+            async move {
+                scan_request
+                    .send()
+                    .await
+                    .map_sdk_err("dynamodb:Scan", &self.metadata_table)
+            }
+        })
+        .await?;
+
+        // Other tools keep their own rows in the metadata table; only rows
+        // that parse as backup metadata are backups
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.items.unwrap_or_default())
+            .filter_map(|item| from_item(item).ok())
+            .collect())
+    }
+
+    async fn publish_incomplete_backups(&self, count: usize) -> Result<(), Error> {
+        let metric = MetricDatum::builder()
+            .metric_name("IncompleteBackups")
+            .value(count as f64)
+            .unit(StandardUnit::Count)
+            .timestamp(aws_sdk_cloudwatch::primitives::DateTime::from(
+                std::time::SystemTime::now(),
+            ))
+            .build();
+
+        self.cloudwatch_client
+            .put_metric_data()
+            .namespace("DisasterRecovery")
+            .metric_data(metric)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    // Every object under backups/ in the backup bucket
+    async fn list_backup_objects(&self) -> Result<Vec<Object>, Error> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let result = self
+                .s3_client
+                .list_objects_v2()
+                .bucket(&self.backup_bucket)
+                .prefix("backups/")
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_sdk_err("s3:ListBucket", &self.backup_bucket)?;
+
+            objects.extend(result.contents.unwrap_or_default());
+
+            match result.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
+    async fn load_backup_ids(&self) -> Result<HashSet<String>, Error> {
+        let mut backup_ids = HashSet::new();
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = self
+                .dynamo_client
+                .scan()
+                .table_name(&self.metadata_table)
+                .projection_expression("backup_id")
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:Scan", &self.metadata_table)?;
+
+            backup_ids.extend(
+                result
+                    .items
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|item| item.get("backup_id")?.as_s().ok().cloned()),
+            );
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(backup_ids)
+    }
+
+    pub async fn delete_orphaned_backups(
+        &self,
+        orphans: &[OrphanedBackup],
+    ) -> Result<usize, Error> {
+        let mut deleted = 0;
+
+        for chunk in orphans.chunks(DELETE_OBJECTS_MAX_KEYS) {
+            let objects = chunk
+                .iter()
+                .map(|orphan| ObjectIdentifier::builder().key(&orphan.key).build())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let result = self
+                .s3_client
+                .delete_objects()
+                .bucket(&self.backup_bucket)
+                .delete(Delete::builder().set_objects(Some(objects)).build()?)
+                .send()
+                .await
+                .map_sdk_err("s3:DeleteObject", &self.backup_bucket)?;
+
+            for failure in result.errors.unwrap_or_default() {
+                error!(
+                    "Failed to delete orphaned backup {}: {}",
+                    failure.key.unwrap_or_default(),
+                    failure.message.unwrap_or_default()
+                );
+            }
+            deleted += result.deleted.map(|deleted| deleted.len()).unwrap_or(0);
+        }
+
+        info!("Deleted {} orphaned backup objects", deleted);
+        Ok(deleted)
+    }
+
+    // Removes backups older than the retention period: their objects, their
+    // metadata rows and their index entries. A table's newest completed backup
+    // is always kept. Returns how many backups were deleted.
+    pub async fn cleanup_old_backups(&self, retention_days: u32) -> Result<usize, Error> {
+        let cutoff = retention_cutoff(Utc::now().timestamp(), retention_days);
+        let expired = expired_backups(self.load_all_backup_metadata().await?, cutoff);
+
+        let keys: Vec<String> = expired.iter().flat_map(backup_object_keys).collect();
+        let mut failed = HashSet::new();
+        for chunk in keys.chunks(DELETE_OBJECTS_MAX_KEYS) {
+            let objects = chunk
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let result = self
+                .s3_client
+                .delete_objects()
+                .bucket(&self.backup_bucket)
+                .delete(Delete::builder().set_objects(Some(objects)).build()?)
+                .send()
+                .await
+                .map_sdk_err("s3:DeleteObject", &self.backup_bucket)?;
+
+            for failure in result.errors.unwrap_or_default() {
+                let key = failure.key.unwrap_or_default();
+                error!(
+                    "Failed to delete backup object {}: {}",
+                    key,
+                    failure.message.unwrap_or_default()
+                );
+                if let Some(backup_id) = backup_id_from_key(&key) {
+                    failed.insert(backup_id.to_string());
+                }
+            }
+        }
+
+        // A backup whose objects couldn't all be deleted keeps its metadata,
+        // so the next cleanup tries again
+        let mut deleted: HashMap<String, HashSet<String>> = HashMap::new();
+        for backup in expired
+            .iter()
+            .filter(|backup| !failed.contains(&backup.backup_id))
+        {
+            self.dynamo_client
+                .delete_item()
+                .table_name(&self.metadata_table)
+                .key("backup_id", AttributeValue::S(backup.backup_id.clone()))
+                .send()
+                .await
+                .map_sdk_err("dynamodb:DeleteItem", &self.metadata_table)?;
+
+            deleted
+                .entry(backup.table_name.clone())
+                .or_default()
+                .insert(backup.backup_id.clone());
+        }
+
+        for (table_name, backup_ids) in &deleted {
+            if let Err(e) = self.remove_from_index(table_name, backup_ids).await {
+                error!(
+                    "Failed to remove deleted backups from the index of {}: {}",
+                    table_name, e
+                );
+            }
+        }
+
+        let count = deleted.values().map(HashSet::len).sum();
+        info!(
+            "Deleted {} backups older than {} days",
+            count, retention_days
+        );
+        Ok(count)
+    }
+
+    async fn remove_from_index(
+        &self,
+        table_name: &str,
+        backup_ids: &HashSet<String>,
+    ) -> Result<(), Error> {
+        if let Some(mut backups) = self.read_backup_index(table_name).await? {
+            backups.retain(|backup| !backup_ids.contains(&backup.backup_id));
+            self.write_backup_index(table_name, &backups).await?;
+        }
+        Ok(())
+    }
+
+    // Compares the backup object with the item count recorded in its metadata
+    // and with the keys currently in the source table. Items written after the
+    // backup was taken show up as missing, so run this soon after the backup.
+    pub async fn verify_backup_completeness(
+        &self,
+        backup_id: &str,
+    ) -> Result<RestoreVerification, Error> {
+        let metadata = self.load_backup_metadata(backup_id).await?;
+        let table_name = &metadata.table_name;
+
+        let mut items: Vec<GenericItem> = Vec::new();
+        for key in backup_object_keys(&metadata) {
+            items.extend(self.read_backup_object(&key, backup_id).await?);
+        }
+
+        let key_attributes = self.key_attributes(table_name).await?;
+        let backup_keys: HashSet<String> = items
+            .iter()
+            .filter_map(|item| item_key(item, &key_attributes))
+            .collect();
+        let source_keys = self.scan_keys(table_name, &key_attributes).await?;
+
+        let verification = RestoreVerification {
+            backup_id: backup_id.to_string(),
+            table_name: table_name.clone(),
+            ..restore_verification(metadata.items_count, items.len(), &backup_keys, source_keys)
+        };
+
+        if verification.pass {
+            info!("Backup {} verified with {} items", backup_id, items.len());
+        } else {
+            warn!(
+                "Backup {} failed verification: expected {} items, found {}, {} sampled keys missing",
+                backup_id,
+                verification.expected_count,
+                verification.actual_count,
+                verification.missing_keys_sample.len()
+            );
+        }
+
+        if let Err(e) = self.publish_restore_verification(&verification).await {
+            error!("Failed to publish restore verification metric: {}", e);
+        }
+
+        Ok(verification)
+    }
+
+    // Writes every item of a backup into target_table, overwriting items with
+    // the same key. Partitioned backups are restored one object at a time.
+    pub async fn restore_backup(
+        &self,
+        backup_id: &str,
+        target_table: &str,
+    ) -> Result<RestoreResponse, Error> {
+        let metadata = self.load_backup_metadata(backup_id).await?;
+        info!(
+            "Restoring backup {} of {} into {}",
+            backup_id, metadata.table_name, target_table
+        );
+
+        let key_attributes = self.key_attributes(target_table).await?;
+        let mut items_restored = 0;
+        let mut oversized_items = Vec::new();
+        for key in backup_object_keys(&metadata) {
+            let items = self.read_backup_object(&key, backup_id).await?;
+
+            // A single oversized item would fail the whole batch it's in
+            let mut writable = Vec::with_capacity(items.len());
+            for item in &items {
+                let restored = restore_item(item, metadata.item_format)?;
+                let size_bytes = item_size_bytes(&restored);
+                if size_bytes <= MAX_ITEM_BYTES {
+                    writable.push(restored);
+                    continue;
+                }
+
+                let oversized = OversizedItem {
+                    key: item_key(item, &key_attributes),
+                    size_bytes,
+                };
+                if self.fail_on_oversized {
+                    return Err(Error::from(format!(
+                        "Item {:?} in backup {} is {} bytes, above DynamoDB's {} byte limit",
+                        oversized.key, backup_id, size_bytes, MAX_ITEM_BYTES
+                    )));
+                }
+                warn!(
+                    "Skipping item {:?} of backup {}: {} bytes is above DynamoDB's item size limit",
+                    oversized.key, backup_id, size_bytes
+                );
+                oversized_items.push(oversized);
+            }
+
+            for chunk in writable.chunks(RESTORE_BATCH_SIZE) {
+                let requests = chunk
+                    .iter()
+                    .map(|item| {
+                        let put = PutRequest::builder().set_item(Some(item.clone())).build()?;
+                        Ok(WriteRequest::builder().put_request(put).build())
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                self.write_restore_batch(target_table, requests).await?;
+                items_restored += chunk.len();
+            }
+        }
+
+        info!(
+            "Restored {} items from backup {} into {} ({} oversized items skipped)",
+            items_restored,
+            backup_id,
+            target_table,
+            oversized_items.len()
+        );
+
+        Ok(RestoreResponse {
+            status: if oversized_items.is_empty() {
+                "success"
+            } else {
+                "partial"
+            }
+            .to_string(),
+            backup_id: backup_id.to_string(),
+            target_table: target_table.to_string(),
+            items_restored,
+            oversized_items,
+            version: version(),
+        })
+    }
+
+    // Items of one backup object. A missing .json.gz falls back to the plain
+    // .json object of the same name.
+    async fn read_backup_object(
+        &self,
+        key: &str,
+        backup_id: &str,
+    ) -> Result<Vec<GenericItem>, Error> {
+        let mut key = key.to_string();
+        loop {
+            match self
+                .s3_client
+                .get_object()
+                .bucket(&self.backup_bucket)
+                .key(&key)
+                .send()
+                .await
+            {
+                Ok(object) => {
+                    let body = object.body.collect().await?.into_bytes();
+                    return decode_backup_object(&key, &body);
+                }
+                Err(e) if matches!(e.as_service_error(), Some(GetObjectError::NoSuchKey(_))) => {
+                    match key.strip_suffix(".gz") {
+                        Some(plain_key) => key = plain_key.to_string(),
+                        None => {
+                            return Err(Error::from(format!(
+                                "Backup object s3://{}/{} for backup {} does not exist",
+                                self.backup_bucket, key, backup_id
+                            )))
+                        }
+                    }
+                }
+                Err(e) => {
+                    return Err(map_sdk_error(
+                        e,
+                        "s3:GetObject",
+                        &format!("{}/{}", self.backup_bucket, key),
+                    ))
+                }
+            }
+        }
+    }
+
+    // BatchWriteItem may accept only part of a batch under throttling; the
+    // rest is resent with backoff
+    async fn write_restore_batch(
+        &self,
+        target_table: &str,
+        mut requests: Vec<WriteRequest>,
+    ) -> Result<(), Error> {
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .dynamo_client
+                .batch_write_item()
+                .request_items(target_table, requests)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:BatchWriteItem", target_table)?;
+
+            requests = result
+                .unprocessed_items
+                .and_then(|mut unprocessed| unprocessed.remove(target_table))
+                .unwrap_or_default();
+            if requests.is_empty() {
+                return Ok(());
+            }
+
+            attempt += 1;
+            if attempt >= RESTORE_BATCH_MAX_ATTEMPTS {
+                return Err(Error::from(format!(
+                    "{} items were still unprocessed after {} BatchWriteItem attempts on {}",
+                    requests.len(),
+                    attempt,
+                    target_table
+                )));
+            }
+            tokio::time::sleep(table_poll_delay(attempt)).await;
+        }
+    }
+
+    async fn load_backup_metadata(&self, backup_id: &str) -> Result<BackupMetadata, Error> {
+        let result = self
+            .dynamo_client
+            .get_item()
+            .table_name(&self.metadata_table)
+            .key("backup_id", AttributeValue::S(backup_id.to_string()))
+            .send()
+            .await
+            .map_sdk_err("dynamodb:GetItem", &self.metadata_table)?;
+
+        match result.item {
+            Some(item) => Ok(from_item(item)?),
+            None => Err(Error::from(format!("No metadata for backup {}", backup_id))),
+        }
+    }
+
+    async fn key_attributes(&self, table_name: &str) -> Result<Vec<String>, Error> {
+        let result = self
+            .dynamo_client
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:DescribeTable", table_name)?;
+
+        Ok(result
+            .table
+            .map(|table| {
+                table
+                    .key_schema()
+                    .iter()
+                    .map(|key| key.attribute_name().to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn scan_keys(
+        &self,
+        table_name: &str,
+        key_attributes: &[String],
+    ) -> Result<Vec<String>, Error> {
+        let mut keys = Vec::new();
+        let mut last_evaluated_key = None;
+        let (projection, names) = aliased_projection(key_attributes);
+
+        loop {
+            let result = self
+                .dynamo_client
+                .scan()
+                .table_name(table_name)
+                .projection_expression(&projection)
+                .set_expression_attribute_names(Some(names.clone()))
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:Scan", table_name)?;
+
+            let items: Vec<GenericItem> = from_items(result.items.unwrap_or_default())?;
+            keys.extend(
+                items
+                    .iter()
+                    .filter_map(|item| item_key(item, key_attributes)),
+            );
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(keys)
+    }
+
+    async fn publish_restore_verification(
+        &self,
+        verification: &RestoreVerification,
+    ) -> Result<(), Error> {
+        let metric = MetricDatum::builder()
+            .metric_name("RestoreVerificationPass")
+            .value(if verification.pass { 1.0 } else { 0.0 })
+            .unit(StandardUnit::Count)
+            .dimensions(
+                Dimension::builder()
+                    .name("TableName")
+                    .value(&verification.table_name)
+                    .build(),
+            )
+            .timestamp(aws_sdk_cloudwatch::primitives::DateTime::from(
+                std::time::SystemTime::now(),
+            ))
+            .build();
+
+        self.cloudwatch_client
+            .put_metric_data()
+            .namespace("DisasterRecovery")
+            .metric_data(metric)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn run_backup(&self, table_name: &str, backup_type: &str) -> Result<Response, Error> {
+        let result = self.backup_table(table_name, backup_type).await;
+
+        if let Err(e) = &result {
+            self.events
+                .emit_event(
+                    "Backup Failed",
+                    &serde_json::json!({
+                        "table_name": table_name,
+                        "backup_type": backup_type,
+                        "error": e.to_string(),
+                        "timestamp": Utc::now().to_rfc3339(),
+                    }),
+                )
+                .await;
+        }
+
+        result
+    }
+
+    // Backs up each table, at most backup_concurrency at a time
+    pub async fn run_backups(&self, tables: &[String], backup_type: &str) -> BatchBackupResponse {
+        let outcomes = stream::iter(tables)
+            .map(|table_name| async move {
+                let result = self.run_backup(table_name, backup_type).await;
+                if let Err(e) = &result {
+                    error!("Backup of {} failed: {}", table_name, e);
+                }
+                (table_name.clone(), result)
+            })
+            .buffer_unordered(self.backup_concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        batch_backup_response(outcomes)
+    }
+
+    async fn backup_table(&self, table_name: &str, backup_type: &str) -> Result<Response, Error> {
+        // Create backup
+        let backup = self.create_backup(table_name, backup_type).await?;
+
+        // Update metadata
+        self.update_backup_metadata(
+            &backup.backup_id,
+            table_name,
+            backup.items_count,
+            backup.partitions,
+            backup.size_bytes,
+        )
+        .await?;
+
+        Ok(Response {
+            version: version(),
+            init: None,
+            status: "success".to_string(),
+            backup_id: backup.backup_id,
+            timestamp: Utc::now().to_rfc3339(),
+            items_backed_up: backup.items_count,
+            memory_pressure: backup.memory_pressure,
+            config_trace: None,
+        })
+    }
+}
+
+// Config table rows look like {"table_name": S, "backup": BOOL, "validate": BOOL};
+// a missing flag means the table is included
+pub fn configured_table_names(
+    items: &[std::collections::HashMap<String, AttributeValue>],
+    flag: &str,
+) -> Vec<String> {
+    items
+        .iter()
+        .filter(|item| {
+            item.get(flag)
+                .and_then(|value| value.as_bool().ok())
+                .copied()
+                .unwrap_or(true)
+        })
+        .filter_map(|item| item.get("table_name")?.as_s().ok().cloned())
+        .collect()
+}
+
+// "dr:backup=true" -> ("dr:backup", "true"); tag keys may contain ':' but not '='
+// table_name followed by table_names, without blanks or repeats
+pub fn requested_tables(table_name: &str, table_names: Option<&[String]>) -> Vec<String> {
+    let mut tables: Vec<String> = Vec::new();
+    for table in std::iter::once(table_name)
+        .chain(table_names.unwrap_or_default().iter().map(String::as_str))
+    {
+        let table = table.trim();
+        if !table.is_empty() && !tables.iter().any(|t| t == table) {
+            tables.push(table.to_string());
+        }
+    }
+    tables
+}
+
+// Results in table order; buffer_unordered finishes them in any order
+pub fn batch_backup_response(
+    mut outcomes: Vec<(String, Result<Response, Error>)>,
+) -> BatchBackupResponse {
+    outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut results = Vec::new();
+    let mut failures = Vec::new();
+    for (table_name, outcome) in outcomes {
+        match outcome {
+            Ok(response) => results.push(response),
+            Err(e) => failures.push(BackupFailure {
+                table_name,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    BatchBackupResponse {
+        total_items: results.iter().map(|r| r.items_backed_up).sum(),
+        results,
+        failures,
+        version: version(),
+    }
+}
+
+pub fn parse_tag_filter(value: &str) -> Option<(String, String)> {
+    let (key, value) = value.split_once('=')?;
+    let (key, value) = (key.trim(), value.trim());
+
+    if key.is_empty() {
+        None
+    } else {
+        Some((key.to_string(), value.to_string()))
+    }
+}
+
+// Tags to create scratch tables with
+pub fn scratch_table_tags() -> Result<Vec<Tag>, Error> {
+    Ok(vec![Tag::builder()
+        .key(SCRATCH_TABLE_TAG_KEY)
+        .value(SCRATCH_TABLE_TAG_VALUE)
+        .build()?])
+}
+
+pub fn has_tag(tags: &[Tag], key: &str, value: &str) -> bool {
+    tags.iter()
+        .any(|tag| tag.key() == key && tag.value() == value)
+}
+
+// Parse a comma-separated attribute list such as "id, created_at, status"
+pub fn parse_projection(value: &str) -> Option<Vec<String>> {
+    let attributes: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|attribute| !attribute.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if attributes.is_empty() {
+        None
+    } else {
+        Some(attributes)
+    }
+}
+
+// Package version plus the git hash of the build, reported in every response
+pub fn version() -> String {
+    build_version(env!("CARGO_PKG_VERSION"))
+}
+
+pub fn self_test_response() -> Response {
+    Response {
+        version: version(),
+        init: None,
+        status: "success".to_string(),
+        backup_id: "self-test".to_string(),
+        timestamp: Utc::now().to_rfc3339(),
+        items_backed_up: 0,
+        memory_pressure: false,
+        config_trace: None,
+    }
+}
+
+// Exponential backoff between describe_table polls: 1s, 2s, 4s, ... capped
+pub fn table_poll_delay(attempt: u32) -> std::time::Duration {
+    let seconds = 1u64
+        .checked_shl(attempt)
+        .unwrap_or(u64::MAX)
+        .min(TABLE_ACTIVE_MAX_POLL_SECONDS);
+    std::time::Duration::from_secs(seconds)
+}
+
+pub fn exceeds_memory_threshold(payload_bytes: usize, warn_mb: usize) -> bool {
+    payload_bytes > warn_mb.saturating_mul(1024 * 1024)
+}
+
+// Fetch every page of a scan. `fetch_page` gets the previous page's
+// LastEvaluatedKey (None for the first page); the scan is complete once a page
+// comes back without one. A page that hands back the key it was started from
+// would repeat forever, so that is an error.
+pub async fn scan_all_pages<F, Fut>(mut fetch_page: F) -> Result<Vec<ScanOutput>, Error>
+where
+    F: FnMut(Option<HashMap<String, AttributeValue>>) -> Fut,
+    Fut: Future<Output = Result<ScanOutput, Error>>,
+{
+    let mut pages = Vec::new();
+    let mut exclusive_start_key = None;
+
+    loop {
+        let mut page = fetch_page(exclusive_start_key.clone()).await?;
+        let last_evaluated_key = page.last_evaluated_key.take();
+        pages.push(page);
+
+        match last_evaluated_key {
+            None => return Ok(pages),
+            Some(key) if exclusive_start_key.as_ref() == Some(&key) => {
+                return Err(Error::from(format!(
+                    "Scan returned its start key as LastEvaluatedKey after {} pages",
+                    pages.len()
+                )));
+            }
+            Some(key) => exclusive_start_key = Some(key),
+        }
+    }
+}
+
+// Read a JSONL backup body one line at a time, handing items to `write_batch`
+// in groups of `batch_size` so only one batch is ever held in memory. Blank
+// lines are skipped. Returns the number of items read.
+pub async fn read_jsonl_batches<R, F, Fut>(
+    reader: R,
+    batch_size: usize,
+    mut write_batch: F,
+) -> Result<usize, Error>
+where
+    R: AsyncBufRead + Unpin,
+    F: FnMut(Vec<GenericItem>) -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let batch_size = batch_size.max(1);
+    let mut lines = reader.lines();
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut line_number = 0;
+    let mut total = 0;
+
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let item: GenericItem = serde_json::from_str(&line).map_err(|e| {
+            Error::from(format!(
+                "Invalid backup item on line {}: {}",
+                line_number, e
+            ))
+        })?;
+        batch.push(item);
+
+        if batch.len() == batch_size {
+            total += batch.len();
+            write_batch(std::mem::replace(
+                &mut batch,
+                Vec::with_capacity(batch_size),
+            ))
+            .await?;
+        }
+    }
+
+    if !batch.is_empty() {
+        total += batch.len();
+        write_batch(batch).await?;
+    }
+
+    Ok(total)
+}
+
+// Partition names become S3 key segments, so '/' is replaced
+pub fn partition_value(item: &GenericItem, attribute: &str) -> String {
+    let text = match item.attributes.get(attribute) {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(value) => value_text(value),
+    };
+
+    if text.is_empty() {
+        UNPARTITIONED.to_string()
+    } else {
+        text.replace('/', "_")
+    }
+}
+
+// Text of a key or partition value. A DynamoDB JSON scalar such as {"S": "a"}
+// reads the same as its plain form, so both backup formats key alike.
+fn value_text(value: &serde_json::Value) -> String {
+    if let Some(map) = value.as_object().filter(|map| map.len() == 1) {
+        if let Some(serde_json::Value::String(s)) = map.get("S").or_else(|| map.get("N")) {
+            return s.clone();
+        }
+    }
+
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn to_dynamodb_json(value: &AttributeValue) -> serde_json::Value {
+    use serde_json::{json, Value};
+
+    let binary = |b: &aws_sdk_dynamodb::primitives::Blob| STANDARD.encode(b.as_ref());
+    match value {
+        AttributeValue::S(s) => json!({ "S": s }),
+        AttributeValue::N(n) => json!({ "N": n }),
+        AttributeValue::B(b) => json!({ "B": binary(b) }),
+        AttributeValue::Bool(b) => json!({ "BOOL": b }),
+        AttributeValue::Null(_) => json!({ "NULL": true }),
+        AttributeValue::Ss(values) => json!({ "SS": values }),
+        AttributeValue::Ns(values) => json!({ "NS": values }),
+        AttributeValue::Bs(values) => {
+            json!({ "BS": values.iter().map(binary).collect::<Vec<_>>() })
+        }
+        AttributeValue::L(values) => {
+            json!({ "L": values.iter().map(to_dynamodb_json).collect::<Vec<_>>() })
+        }
+        AttributeValue::M(map) => json!({ "M": item_to_dynamodb_json(map).attributes }),
+        _ => Value::Null,
+    }
+}
+
+pub fn from_dynamodb_json(value: &serde_json::Value) -> Result<AttributeValue, Error> {
+    use aws_sdk_dynamodb::primitives::Blob;
+    use serde_json::Value;
+
+    let invalid = || Error::from(format!("Invalid DynamoDB JSON value: {}", value));
+    let (kind, inner) = value
+        .as_object()
+        .filter(|map| map.len() == 1)
+        .and_then(|map| map.iter().next())
+        .ok_or_else(invalid)?;
+    let string = |v: &Value| v.as_str().map(str::to_string).ok_or_else(invalid);
+    let strings = |v: &Value| -> Result<Vec<String>, Error> {
+        v.as_array()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(string)
+            .collect()
+    };
+    let blob = |v: &Value| -> Result<Blob, Error> {
+        let encoded = v.as_str().ok_or_else(invalid)?;
+        Ok(Blob::new(STANDARD.decode(encoded)?))
+    };
+
+    Ok(match kind.as_str() {
+        "S" => AttributeValue::S(string(inner)?),
+        "N" => AttributeValue::N(string(inner)?),
+        "B" => AttributeValue::B(blob(inner)?),
+        "BOOL" => AttributeValue::Bool(inner.as_bool().ok_or_else(invalid)?),
+        "NULL" => AttributeValue::Null(true),
+        "SS" => AttributeValue::Ss(strings(inner)?),
+        "NS" => AttributeValue::Ns(strings(inner)?),
+        "BS" => AttributeValue::Bs(
+            inner
+                .as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(blob)
+                .collect::<Result<_, _>>()?,
+        ),
+        "L" => AttributeValue::L(
+            inner
+                .as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(from_dynamodb_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        "M" => AttributeValue::M(
+            inner
+                .as_object()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), from_dynamodb_json(value)?)))
+                .collect::<Result<_, Error>>()?,
+        ),
+        _ => return Err(invalid()),
+    })
+}
+
+pub fn item_to_dynamodb_json(item: &HashMap<String, AttributeValue>) -> GenericItem {
+    GenericItem {
+        attributes: item
+            .iter()
+            .map(|(name, value)| (name.clone(), to_dynamodb_json(value)))
+            .collect(),
+    }
+}
+
+// Rebuilds an item written with ItemFormat::DynamodbJson, for restores
+pub fn item_from_dynamodb_json(
+    item: &GenericItem,
+) -> Result<HashMap<String, AttributeValue>, Error> {
+    item.attributes
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), from_dynamodb_json(value)?)))
+        .collect()
+}
+
+// Turns a backed-up item back into a DynamoDB item
+pub fn restore_item(
+    item: &GenericItem,
+    format: ItemFormat,
+) -> Result<HashMap<String, AttributeValue>, Error> {
+    match format {
+        ItemFormat::Plain => Ok(to_item(&item.attributes)?),
+        ItemFormat::DynamodbJson => item_from_dynamodb_json(item),
+    }
+}
+
+// Size as DynamoDB counts it against the item limit: attribute names plus
+// values, numbers at roughly one byte per two digits, and 3 bytes of
+// overhead per list or map
+pub fn item_size_bytes(item: &HashMap<String, AttributeValue>) -> usize {
+    item.iter()
+        .map(|(name, value)| name.len() + attribute_size_bytes(value))
+        .sum()
+}
+
+fn attribute_size_bytes(value: &AttributeValue) -> usize {
+    let number_size = |number: &String| number.trim_start_matches('-').len().div_ceil(2) + 1;
+    match value {
+        AttributeValue::S(text) => text.len(),
+        AttributeValue::N(number) => number_size(number),
+        AttributeValue::B(blob) => blob.as_ref().len(),
+        AttributeValue::Ss(texts) => texts.iter().map(String::len).sum(),
+        AttributeValue::Ns(numbers) => numbers.iter().map(number_size).sum(),
+        AttributeValue::Bs(blobs) => blobs.iter().map(|blob| blob.as_ref().len()).sum(),
+        AttributeValue::L(values) => {
+            3 + values
+                .iter()
+                .map(|v| 1 + attribute_size_bytes(v))
+                .sum::<usize>()
+        }
+        AttributeValue::M(map) => {
+            3 + map
+                .iter()
+                .map(|(name, v)| 1 + name.len() + attribute_size_bytes(v))
+                .sum::<usize>()
+        }
+        _ => 1,
+    }
+}
+
+pub fn partition_items(
+    items: Vec<GenericItem>,
+    attribute: &str,
+) -> std::collections::BTreeMap<String, Vec<GenericItem>> {
+    let mut groups = std::collections::BTreeMap::new();
+    for item in items {
+        groups
+            .entry(partition_value(&item, attribute))
+            .or_insert_with(Vec::new)
+            .push(item);
+    }
+    groups
+}
+
+// Every S3 object making up a backup
+pub fn backup_object_keys(metadata: &BackupMetadata) -> Vec<String> {
+    let extension = if metadata.compressed {
+        "json.gz"
+    } else {
+        "json"
+    };
+    match &metadata.partitions {
+        Some(partitions) => partitions
+            .iter()
+            .map(|partition| {
+                format!(
+                    "backups/{}/{}/{}.{}",
+                    metadata.table_name, partition, metadata.backup_id, extension
+                )
+            })
+            .collect(),
+        None => vec![format!(
+            "backups/{}/{}.{}",
+            metadata.table_name, metadata.backup_id, extension
+        )],
+    }
+}
+
+// Backups are stored as backups/<table>/<backup_id>.json.gz, or
+// backups/<table>/<partition>/<backup_id>.json.gz when partitioned. Older
+// backups end in plain .json.
+pub fn backup_id_from_key(key: &str) -> Option<&str> {
+    let file_name = key.strip_prefix("backups/")?.rsplit('/').next()?;
+    if file_name == BACKUP_INDEX_FILE {
+        return None;
+    }
+    file_name
+        .strip_suffix(".json.gz")
+        .or_else(|| file_name.strip_suffix(".json"))
+        .filter(|backup_id| !backup_id.is_empty())
+}
+
+pub fn backup_list(
+    mut backups: Vec<BackupMetadata>,
+    table_name: Option<&str>,
+) -> BackupListResponse {
+    if let Some(table_name) = table_name {
+        backups.retain(|backup| backup.table_name == table_name);
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.timestamp.parse::<i64>().unwrap_or(0)));
+
+    BackupListResponse {
+        count: backups.len(),
+        backups,
+        version: version(),
+    }
+}
+
+// Epoch seconds before which a backup is past retention
+pub fn retention_cutoff(now: i64, retention_days: u32) -> i64 {
+    now - i64::from(retention_days) * 86_400
+}
+
+// Backups taken before `cutoff`, leaving out each table's newest completed
+// backup so no table is left without one
+pub fn expired_backups(backups: Vec<BackupMetadata>, cutoff: i64) -> Vec<BackupMetadata> {
+    let mut newest: HashMap<&str, (i64, &str)> = HashMap::new();
+    for backup in backups.iter().filter(|backup| backup.status == "completed") {
+        let timestamp = backup.timestamp.parse().unwrap_or(0);
+        let entry = newest
+            .entry(backup.table_name.as_str())
+            .or_insert((timestamp, backup.backup_id.as_str()));
+        if timestamp > entry.0 {
+            *entry = (timestamp, backup.backup_id.as_str());
+        }
+    }
+    let keep: HashSet<String> = newest
+        .into_values()
+        .map(|(_, backup_id)| backup_id.to_string())
+        .collect();
+
+    backups
+        .into_iter()
+        .filter(|backup| {
+            backup.timestamp.parse::<i64>().unwrap_or(0) < cutoff
+                && !keep.contains(&backup.backup_id)
+        })
+        .collect()
+}
+
+pub fn backup_index_key(table_name: &str) -> String {
+    format!("backups/{}/{}", table_name, BACKUP_INDEX_FILE)
+}
+
+// `backups` with `metadata` added, replacing an entry with the same backup id,
+// oldest first
+pub fn index_with(
+    mut backups: Vec<BackupMetadata>,
+    metadata: BackupMetadata,
+) -> Vec<BackupMetadata> {
+    backups.retain(|backup| backup.backup_id != metadata.backup_id);
+    backups.push(metadata);
+    backups.sort_by_key(|backup| backup.timestamp.parse::<i64>().unwrap_or(0));
+    backups
+}
+
+pub fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+// Objects ending in .gz are gunzipped before parsing
+pub fn decode_backup_object(key: &str, body: &[u8]) -> Result<Vec<GenericItem>, Error> {
+    if key.ends_with(".gz") {
+        Ok(serde_json::from_reader(GzDecoder::new(body))?)
+    } else {
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+pub fn orphaned_backups(
+    objects: Vec<(String, i64)>,
+    known: &HashSet<String>,
+) -> Vec<OrphanedBackup> {
+    objects
+        .into_iter()
+        .filter_map(|(key, size_bytes)| {
+            let backup_id = backup_id_from_key(&key)?.to_string();
+            (!known.contains(&backup_id)).then_some(OrphanedBackup {
+                key,
+                backup_id,
+                size_bytes,
+            })
+        })
+        .collect()
+}
+
+// Deltas between consecutive full backups, given oldest first. Incremental
+// backups only hold changed items, so they're left out.
+pub fn backup_growth(backups: &[BackupMetadata]) -> Vec<BackupGrowth> {
+    let full: Vec<&BackupMetadata> = backups
+        .iter()
+        .filter(|backup| {
+            !backup
+                .backup_id
+                .starts_with(&format!("{}-incremental-", backup.table_name))
+        })
+        .collect();
+
+    full.windows(2)
+        .map(|pair| BackupGrowth {
+            from_backup_id: pair[0].backup_id.clone(),
+            to_backup_id: pair[1].backup_id.clone(),
+            item_delta: pair[1].items_count as i64 - pair[0].items_count as i64,
+            size_delta: match (pair[0].size_bytes, pair[1].size_bytes) {
+                (Some(from), Some(to)) => Some(to as i64 - from as i64),
+                _ => None,
+            },
+        })
+        .collect()
+}
+
+// Backups at least `min_age_seconds` old that aren't completed or are missing
+// one of their objects. A timestamp that doesn't parse counts as old.
+pub fn incomplete_backups(
+    backups: &[BackupMetadata],
+    object_keys: &HashSet<String>,
+    now: i64,
+    min_age_seconds: i64,
+) -> Vec<IncompleteBackup> {
+    backups
+        .iter()
+        .filter_map(|backup| {
+            let age_seconds = backup
+                .timestamp
+                .parse::<i64>()
+                .map(|timestamp| now - timestamp)
+                .unwrap_or(i64::MAX);
+            if age_seconds < min_age_seconds {
+                return None;
+            }
+
+            let missing_objects: Vec<String> = backup_object_keys(backup)
+                .into_iter()
+                .filter(|key| !object_keys.contains(key))
+                .collect();
+            (backup.status != "completed" || !missing_objects.is_empty()).then(|| {
+                IncompleteBackup {
+                    backup_id: backup.backup_id.clone(),
+                    table_name: backup.table_name.clone(),
+                    status: backup.status.clone(),
+                    age_seconds,
+                    missing_objects,
+                }
+            })
+        })
+        .collect()
+}
+
+// Items missing a key attribute sort after the rest, by their full contents
+pub fn sort_items(items: &mut [GenericItem], key_attributes: &[String]) {
+    items.sort_by_cached_key(|item| match item_key(item, key_attributes) {
+        Some(key) => (false, key),
+        None => (
+            true,
+            serde_json::to_value(item)
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+        ),
+    });
+}
+
+// Attributes live in a HashMap, so a stable backup goes through
+// `serde_json::Value` to get its keys written in sorted order
+pub fn serialize_items(items: &[GenericItem], stable: bool) -> Result<String, serde_json::Error> {
+    if stable {
+        serde_json::to_string(&serde_json::to_value(items)?)
+    } else {
+        serde_json::to_string(items)
+    }
+}
+
+// None when the item lacks one of the key attributes, e.g. because the backup
+// projection excluded it
+pub fn item_key(item: &GenericItem, key_attributes: &[String]) -> Option<String> {
+    if key_attributes.is_empty() {
+        return None;
+    }
+
+    key_attributes
+        .iter()
+        .map(|attribute| item.attributes.get(attribute).map(value_text))
+        .collect::<Option<Vec<_>>>()
+        .map(|parts| parts.join("/"))
+}
+
+pub fn restore_verification(
+    expected_count: usize,
+    actual_count: usize,
+    backup_keys: &HashSet<String>,
+    source_keys: impl IntoIterator<Item = String>,
+) -> RestoreVerification {
+    let missing_keys_sample: Vec<String> = source_keys
+        .into_iter()
+        .filter(|key| !backup_keys.contains(key))
+        .take(MISSING_KEYS_SAMPLE_SIZE)
+        .collect();
+
+    RestoreVerification {
+        backup_id: String::new(),
+        table_name: String::new(),
+        pass: actual_count == expected_count && missing_keys_sample.is_empty(),
+        expected_count,
+        actual_count,
+        missing_keys_sample,
+        version: version(),
+    }
+}
+
+// Utility functions for testing
+// Scan filter for an incremental backup: items whose `attribute` is newer than
+// `last_backup`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncrementalFilter {
+    pub expression: String,
+    pub name_placeholder: String,
+    pub attribute: String,
+    pub value_placeholder: String,
+    pub value: AttributeValue,
+}
+
+// None for full backups, and for an incremental with no earlier backup to
+// build on, which therefore exports the whole table like a full one
+pub fn incremental_filter(
+    backup_type: &str,
+    attribute: &str,
+    last_backup: Option<i64>,
+) -> Option<IncrementalFilter> {
+    if backup_type != "incremental" {
+        return None;
+    }
+
+    last_backup.map(|timestamp| IncrementalFilter {
+        expression: "#watermark > :watermark".to_string(),
+        name_placeholder: "#watermark".to_string(),
+        attribute: attribute.to_string(),
+        value_placeholder: ":watermark".to_string(),
+        value: AttributeValue::N(timestamp.to_string()),
+    })
+}
+
+pub fn last_completed_timestamp(backups: &[BackupMetadata], table_name: &str) -> Option<i64> {
+    backups
+        .iter()
+        .filter(|backup| backup.table_name == table_name && backup.status == "completed")
+        .filter_map(|backup| backup.timestamp.parse().ok())
+        .max()
+}
+
+pub fn generate_backup_id(table_name: &str, backup_type: &str, timestamp: i64) -> String {
+    format!("{}-{}-{}", table_name, backup_type, timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_deserialization() {
+        let json = r#"{"table_name": "test-table", "backup_type": "incremental"}"#;
         let request: Request = serde_json::from_str(json).unwrap();
         assert_eq!(request.table_name, "test-table");
         assert_eq!(request.backup_type, Some("incremental".to_string()));
@@ -176,10 +2622,14 @@ mod tests {
     #[test]
     fn test_response_serialization() {
         let response = Response {
+            version: version(),
+            init: None,
             status: "success".to_string(),
             backup_id: "test-123".to_string(),
             timestamp: "2025-01-06T12:00:00Z".to_string(),
             items_backed_up: 100,
+            memory_pressure: false,
+            config_trace: None,
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -196,6 +2646,12 @@ mod tests {
             timestamp: "1234567890".to_string(),
             items_count: 50,
             status: "completed".to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: false,
+            size_bytes: None,
         };
 
         assert_eq!(metadata.backup_id, "backup-123");
@@ -205,13 +2661,13 @@ mod tests {
     #[test]
     fn test_generic_item_serialization() {
         use std::collections::HashMap;
-        
+
         let mut attributes = HashMap::new();
         attributes.insert("id".to_string(), serde_json::json!("123"));
         attributes.insert("name".to_string(), serde_json::json!("test"));
-        
+
         let item = GenericItem { attributes };
-        
+
         let json = serde_json::to_string(&item).unwrap();
         assert!(json.contains("\"id\":\"123\""));
         assert!(json.contains("\"name\":\"test\""));
@@ -222,4 +2678,778 @@ mod tests {
         let id = generate_backup_id("my-table", "full", 1234567890);
         assert_eq!(id, "my-table-full-1234567890");
     }
+
+    #[test]
+    fn test_parse_projection() {
+        assert_eq!(
+            parse_projection("id, created_at,status"),
+            Some(vec![
+                "id".to_string(),
+                "created_at".to_string(),
+                "status".to_string()
+            ])
+        );
+        assert_eq!(parse_projection(""), None);
+        assert_eq!(parse_projection(" , "), None);
+    }
+
+    #[test]
+    fn test_configured_table_names() {
+        use std::collections::HashMap;
+
+        let row = |name: &str, backup: Option<bool>| {
+            let mut item = HashMap::new();
+            item.insert(
+                "table_name".to_string(),
+                AttributeValue::S(name.to_string()),
+            );
+            if let Some(backup) = backup {
+                item.insert("backup".to_string(), AttributeValue::Bool(backup));
+            }
+            item
+        };
+
+        let items = vec![
+            row("orders", Some(true)),
+            row("sessions", Some(false)),
+            row("customers", None),
+            HashMap::new(), // Rows without a table name are ignored
+        ];
+
+        assert_eq!(
+            configured_table_names(&items, "backup"),
+            vec!["orders".to_string(), "customers".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_self_test_request() {
+        let request: Request = serde_json::from_str(r#"{"self_test": true}"#).unwrap();
+        assert!(request.self_test);
+        assert!(request.table_name.is_empty());
+
+        let json = serde_json::to_value(self_test_response()).unwrap();
+        assert_eq!(json["status"], "success");
+        assert_eq!(json["items_backed_up"], 0);
+    }
+
+    #[test]
+    fn test_exceeds_memory_threshold() {
+        assert!(!exceeds_memory_threshold(0, 256));
+        assert!(!exceeds_memory_threshold(256 * 1024 * 1024, 256));
+        assert!(exceeds_memory_threshold(256 * 1024 * 1024 + 1, 256));
+        assert!(exceeds_memory_threshold(1, 0));
+    }
+
+    #[test]
+    fn test_table_poll_delay() {
+        assert_eq!(table_poll_delay(0).as_secs(), 1);
+        assert_eq!(table_poll_delay(1).as_secs(), 2);
+        assert_eq!(table_poll_delay(3).as_secs(), 8);
+        assert_eq!(table_poll_delay(4).as_secs(), 10);
+        assert_eq!(table_poll_delay(100).as_secs(), 10);
+    }
+
+    #[test]
+    fn test_parse_tag_filter() {
+        assert_eq!(
+            parse_tag_filter("dr:backup=true"),
+            Some(("dr:backup".to_string(), "true".to_string()))
+        );
+        assert_eq!(
+            parse_tag_filter(" team = payments "),
+            Some(("team".to_string(), "payments".to_string()))
+        );
+        assert_eq!(parse_tag_filter("dr:backup"), None);
+        assert_eq!(parse_tag_filter("=true"), None);
+    }
+
+    #[test]
+    fn test_has_tag() {
+        let tag = |key: &str, value: &str| Tag::builder().key(key).value(value).build().unwrap();
+        let tags = vec![tag("team", "payments"), tag("dr:backup", "true")];
+
+        assert!(has_tag(&tags, "dr:backup", "true"));
+        assert!(!has_tag(&tags, "dr:backup", "false"));
+        assert!(!has_tag(&[], "dr:backup", "true"));
+    }
+
+    #[test]
+    fn test_restore_item_round_trip() {
+        let items = vec![
+            GenericItem {
+                attributes: HashMap::from([
+                    ("id".to_string(), serde_json::json!("order-1")),
+                    ("total".to_string(), serde_json::json!(42.5)),
+                    ("paid".to_string(), serde_json::json!(true)),
+                    ("note".to_string(), serde_json::Value::Null),
+                ]),
+            },
+            GenericItem {
+                attributes: HashMap::from([
+                    ("id".to_string(), serde_json::json!("order-2")),
+                    (
+                        "lines".to_string(),
+                        serde_json::json!([{"sku": "a", "qty": 2}]),
+                    ),
+                ]),
+            },
+        ];
+
+        let body = serialize_items(&items, false).unwrap();
+        let restored: Vec<GenericItem> = serde_json::from_str(&body).unwrap();
+
+        for (original, restored) in items.iter().zip(&restored) {
+            let item = restore_item(restored, ItemFormat::Plain).unwrap();
+            assert_eq!(
+                item,
+                to_item::<_, HashMap<String, AttributeValue>>(&original.attributes).unwrap()
+            );
+
+            let back: HashMap<String, serde_json::Value> = from_item(item).unwrap();
+            assert_eq!(back, original.attributes);
+        }
+        assert_eq!(
+            restore_item(&restored[0], ItemFormat::Plain).unwrap()["id"],
+            AttributeValue::S("order-1".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_all_pages() {
+        let key = |id: &str| HashMap::from([("id".to_string(), AttributeValue::S(id.into()))]);
+        let page = |ids: &[&str], last: Option<&str>| {
+            ScanOutput::builder()
+                .set_items(Some(ids.iter().map(|id| key(id)).collect()))
+                .set_last_evaluated_key(last.map(key))
+                .build()
+        };
+        let mut start_keys = Vec::new();
+
+        let pages = scan_all_pages(|start_key| {
+            start_keys.push(start_key.clone());
+            let result = match start_key {
+                None => page(&["1", "2"], Some("2")),
+                Some(k) if k == key("2") => page(&["3", "4"], Some("4")),
+                Some(_) => page(&["5"], None),
+            };
+            async { Ok(result) }
+        })
+        .await
+        .unwrap();
+
+        let ids: Vec<_> = pages
+            .iter()
+            .flat_map(|page| page.items().iter().map(|item| item["id"].clone()))
+            .collect();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids[4], AttributeValue::S("5".into()));
+        assert_eq!(start_keys, vec![None, Some(key("2")), Some(key("4"))]);
+    }
+
+    #[tokio::test]
+    async fn test_scan_all_pages_stops_on_repeated_key() {
+        let key = HashMap::from([("id".to_string(), AttributeValue::S("1".into()))]);
+        let mut calls = 0;
+
+        let err = scan_all_pages(|_| {
+            calls += 1;
+            let page = ScanOutput::builder()
+                .set_last_evaluated_key(Some(key.clone()))
+                .build();
+            async { Ok(page) }
+        })
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("start key"));
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_read_jsonl_batches() {
+        let body = b"{\"id\": \"1\"}\n{\"id\": \"2\"}\n\n{\"id\": \"3\"}\n";
+        let mut batches = Vec::new();
+
+        let total = read_jsonl_batches(&body[..], 2, |batch| {
+            batches.push(batch.len());
+            async { Ok(()) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(total, 3);
+        assert_eq!(batches, vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_read_jsonl_batches_reports_bad_line() {
+        let body = b"{\"id\": \"1\"}\nnot json\n";
+
+        let err = read_jsonl_batches(&body[..], RESTORE_BATCH_SIZE, |_| async { Ok(()) })
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_orphaned_backups() {
+        assert_eq!(
+            backup_id_from_key("backups/orders/orders-full-1704556800.json"),
+            Some("orders-full-1704556800")
+        );
+        assert_eq!(backup_id_from_key("exports/orders.json"), None);
+        assert_eq!(backup_id_from_key("backups/orders/notes.txt"), None);
+        assert_eq!(backup_id_from_key("backups/orders/index.json"), None);
+
+        let objects = vec![
+            ("backups/orders/orders-full-1.json".to_string(), 10),
+            ("backups/orders/orders-full-2.json".to_string(), 20),
+            ("backups/orders/readme.txt".to_string(), 5),
+        ];
+        let known = HashSet::from(["orders-full-1".to_string()]);
+
+        let orphans = orphaned_backups(objects, &known);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].backup_id, "orders-full-2");
+        assert_eq!(orphans[0].size_bytes, 20);
+    }
+
+    #[test]
+    fn test_scratch_table_tags() {
+        let tags = scratch_table_tags().unwrap();
+        assert!(has_tag(
+            &tags,
+            SCRATCH_TABLE_TAG_KEY,
+            SCRATCH_TABLE_TAG_VALUE
+        ));
+    }
+
+    #[test]
+    fn test_item_key() {
+        let item: GenericItem =
+            serde_json::from_str(r#"{"pk": "user#1", "sk": 42, "name": "a"}"#).unwrap();
+
+        assert_eq!(
+            item_key(&item, &["pk".to_string()]),
+            Some("user#1".to_string())
+        );
+        assert_eq!(
+            item_key(&item, &["pk".to_string(), "sk".to_string()]),
+            Some("user#1/42".to_string())
+        );
+        assert_eq!(item_key(&item, &["missing".to_string()]), None);
+        assert_eq!(item_key(&item, &[]), None);
+    }
+
+    #[test]
+    fn test_restore_verification() {
+        let backup_keys: HashSet<String> = ["a", "b"].iter().map(|k| k.to_string()).collect();
+
+        let report = restore_verification(2, 2, &backup_keys, vec!["a".into(), "b".into()]);
+        assert!(report.pass);
+        assert!(report.missing_keys_sample.is_empty());
+
+        let report = restore_verification(3, 2, &backup_keys, vec!["a".into(), "b".into()]);
+        assert!(!report.pass);
+
+        let source: Vec<String> = (0..20).map(|i| format!("k{}", i)).collect();
+        let report = restore_verification(2, 2, &backup_keys, source);
+        assert!(!report.pass);
+        assert_eq!(report.missing_keys_sample.len(), MISSING_KEYS_SAMPLE_SIZE);
+        assert_eq!(report.missing_keys_sample[0], "k0");
+    }
+
+    #[test]
+    fn test_partition_items() {
+        let items: Vec<GenericItem> = [
+            r#"{"id": "1", "tenant": "acme"}"#,
+            r#"{"id": "2", "tenant": "globex"}"#,
+            r#"{"id": "3", "tenant": "acme"}"#,
+            r#"{"id": "4"}"#,
+            r#"{"id": "5", "tenant": "a/b"}"#,
+        ]
+        .iter()
+        .map(|json| serde_json::from_str(json).unwrap())
+        .collect();
+
+        let groups = partition_items(items, "tenant");
+        let names: Vec<&str> = groups.keys().map(String::as_str).collect();
+        assert_eq!(names, vec![UNPARTITIONED, "a_b", "acme", "globex"]);
+        assert_eq!(groups["acme"].len(), 2);
+    }
+
+    #[test]
+    fn test_backup_object_keys() {
+        let mut metadata = BackupMetadata {
+            backup_id: "orders-full-1".to_string(),
+            table_name: "orders".to_string(),
+            timestamp: "1".to_string(),
+            items_count: 3,
+            status: "completed".to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: false,
+            size_bytes: None,
+        };
+        assert_eq!(
+            backup_object_keys(&metadata),
+            vec!["backups/orders/orders-full-1.json"]
+        );
+
+        metadata.partition_attr = Some("tenant".to_string());
+        metadata.partitions = Some(vec!["acme".to_string(), "globex".to_string()]);
+        let keys = backup_object_keys(&metadata);
+        assert_eq!(keys[0], "backups/orders/acme/orders-full-1.json");
+        assert_eq!(backup_id_from_key(&keys[1]), Some("orders-full-1"));
+
+        metadata.compressed = true;
+        let keys = backup_object_keys(&metadata);
+        assert_eq!(keys[0], "backups/orders/acme/orders-full-1.json.gz");
+        assert_eq!(backup_id_from_key(&keys[1]), Some("orders-full-1"));
+    }
+
+    #[test]
+    fn test_stable_order_is_independent_of_scan_order() {
+        let item = |id: &str, value: i64| GenericItem {
+            attributes: [
+                ("id".to_string(), serde_json::json!(id)),
+                ("value".to_string(), serde_json::json!(value)),
+                ("tags".to_string(), serde_json::json!({"b": 1, "a": 2})),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let keys = vec!["id".to_string()];
+
+        let mut first = vec![item("b", 2), item("a", 1), item("c", 3)];
+        let mut second = vec![item("c", 3), item("b", 2), item("a", 1)];
+        sort_items(&mut first, &keys);
+        sort_items(&mut second, &keys);
+
+        let ids: Vec<_> = first.iter().map(|i| i.attributes["id"].clone()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(
+            serialize_items(&first, true).unwrap(),
+            serialize_items(&second, true).unwrap()
+        );
+        assert!(serialize_items(&first, true)
+            .unwrap()
+            .starts_with(r#"[{"id":"a","tags":{"a":2,"b":1},"value":1}"#));
+    }
+
+    #[test]
+    fn test_stable_order_falls_back_to_contents_without_keys() {
+        let item = |value: i64| GenericItem {
+            attributes: [("value".to_string(), serde_json::json!(value))]
+                .into_iter()
+                .collect(),
+        };
+        let keys = vec!["id".to_string()];
+
+        let mut items = vec![item(2), item(1)];
+        sort_items(&mut items, &keys);
+        assert_eq!(items[0].attributes["value"], 1);
+    }
+
+    #[test]
+    fn test_dynamodb_json_round_trips_sets_and_maps() {
+        let item: HashMap<String, AttributeValue> = HashMap::from([
+            ("id".to_string(), AttributeValue::S("user#1".to_string())),
+            (
+                "tags".to_string(),
+                AttributeValue::Ss(vec!["a".to_string(), "b".to_string()]),
+            ),
+            (
+                "scores".to_string(),
+                AttributeValue::Ns(vec!["1".to_string(), "2.5".to_string()]),
+            ),
+            (
+                "profile".to_string(),
+                AttributeValue::M(HashMap::from([
+                    ("active".to_string(), AttributeValue::Bool(true)),
+                    (
+                        "history".to_string(),
+                        AttributeValue::L(vec![
+                            AttributeValue::N("3".to_string()),
+                            AttributeValue::Null(true),
+                        ]),
+                    ),
+                    (
+                        "avatar".to_string(),
+                        AttributeValue::B(aws_sdk_dynamodb::primitives::Blob::new(vec![0, 255])),
+                    ),
+                ])),
+            ),
+        ]);
+
+        let backed_up = item_to_dynamodb_json(&item);
+        assert_eq!(
+            backed_up.attributes["tags"],
+            serde_json::json!({"SS": ["a", "b"]})
+        );
+
+        // Through the backup file and back
+        let line = serde_json::to_string(&backed_up).unwrap();
+        let restored: GenericItem = serde_json::from_str(&line).unwrap();
+        assert_eq!(item_from_dynamodb_json(&restored).unwrap(), item);
+
+        // Keys read the same as in a plain backup
+        assert_eq!(
+            item_key(&restored, &["id".to_string()]),
+            Some("user#1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_dynamodb_json_rejects_untyped_values() {
+        assert!(from_dynamodb_json(&serde_json::json!("plain")).is_err());
+        assert!(from_dynamodb_json(&serde_json::json!({"SS": "not a list"})).is_err());
+        assert_eq!(
+            ItemFormat::parse("dynamodb_json"),
+            Some(ItemFormat::DynamodbJson)
+        );
+        assert_eq!(ItemFormat::parse("xml"), None);
+    }
+
+    #[test]
+    fn test_first_incremental_backs_up_everything() {
+        assert_eq!(incremental_filter("incremental", "updated_at", None), None);
+        assert_eq!(last_completed_timestamp(&[], "orders"), None);
+        // Full backups never filter, even with an earlier backup
+        assert_eq!(incremental_filter("full", "updated_at", Some(100)), None);
+    }
+
+    #[test]
+    fn test_subsequent_incremental_uses_last_backup_watermark() {
+        let backup = |table: &str, timestamp: &str, status: &str| BackupMetadata {
+            backup_id: format!("{}-full-{}", table, timestamp),
+            table_name: table.to_string(),
+            timestamp: timestamp.to_string(),
+            items_count: 1,
+            status: status.to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: false,
+            size_bytes: None,
+        };
+        let backups = vec![
+            backup("orders", "1000", "completed"),
+            backup("orders", "3000", "failed"),
+            backup("orders", "2000", "completed"),
+            backup("users", "4000", "completed"),
+        ];
+
+        let last_backup = last_completed_timestamp(&backups, "orders");
+        assert_eq!(last_backup, Some(2000));
+
+        let filter = incremental_filter("incremental", "modified", last_backup).unwrap();
+        assert_eq!(filter.expression, "#watermark > :watermark");
+        assert_eq!(filter.attribute, "modified");
+        assert_eq!(filter.value, AttributeValue::N("2000".to_string()));
+    }
+
+    #[test]
+    fn test_incomplete_backups() {
+        let backup = |id: &str, timestamp: i64, status: &str| BackupMetadata {
+            backup_id: id.to_string(),
+            table_name: "orders".to_string(),
+            timestamp: timestamp.to_string(),
+            items_count: 1,
+            status: status.to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: false,
+            size_bytes: None,
+        };
+        let backups = vec![
+            backup("done", 1_000, "completed"),
+            backup("stuck", 1_000, "in_progress"),
+            backup("running", 9_500, "in_progress"),
+            backup("lost", 1_000, "completed"),
+        ];
+        let object_keys = HashSet::from([
+            "backups/orders/done.json".to_string(),
+            "backups/orders/stuck.json".to_string(),
+        ]);
+
+        let incomplete = incomplete_backups(&backups, &object_keys, 10_000, 3600);
+
+        assert_eq!(incomplete.len(), 2);
+        assert_eq!(incomplete[0].backup_id, "stuck");
+        assert_eq!(incomplete[0].age_seconds, 9_000);
+        assert!(incomplete[0].missing_objects.is_empty());
+        assert_eq!(incomplete[1].backup_id, "lost");
+        assert_eq!(
+            incomplete[1].missing_objects,
+            vec!["backups/orders/lost.json"]
+        );
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let items: Vec<GenericItem> = (0..50)
+            .map(|i| GenericItem {
+                attributes: HashMap::from([
+                    ("id".to_string(), serde_json::json!(format!("user#{}", i))),
+                    ("score".to_string(), serde_json::json!(i)),
+                ]),
+            })
+            .collect();
+        let data = serialize_items(&items, true).unwrap();
+
+        let compressed = gzip(data.as_bytes()).unwrap();
+        assert!(compressed.len() < data.len());
+
+        let restored =
+            decode_backup_object("backups/users/users-full-1.json.gz", &compressed).unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&items).unwrap()
+        );
+
+        // Backups from before compression still read as plain JSON
+        let plain = decode_backup_object("backups/users/users-full-1.json", data.as_bytes());
+        assert_eq!(plain.unwrap().len(), 50);
+    }
+
+    #[test]
+    fn test_backup_growth() {
+        let backup = |id: &str, items_count: usize, size_bytes: Option<u64>| BackupMetadata {
+            backup_id: id.to_string(),
+            table_name: "orders".to_string(),
+            timestamp: "1".to_string(),
+            items_count,
+            status: "completed".to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: true,
+            size_bytes,
+        };
+        let backups = vec![
+            backup("orders-full-1", 100, None),
+            backup("orders-full-2", 150, Some(4_000)),
+            backup("orders-incremental-3", 5, Some(200)),
+            backup("orders-full-4", 140, Some(3_800)),
+        ];
+
+        let steps = backup_growth(&backups);
+
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].item_delta, 50);
+        assert_eq!(steps[0].size_delta, None);
+        assert_eq!(steps[1].from_backup_id, "orders-full-2");
+        assert_eq!(steps[1].to_backup_id, "orders-full-4");
+        assert_eq!(steps[1].item_delta, -10);
+        assert_eq!(steps[1].size_delta, Some(-200));
+        assert!(backup_growth(&backups[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_index_with() {
+        let backup = |id: &str, timestamp: &str, items_count: usize| BackupMetadata {
+            backup_id: id.to_string(),
+            table_name: "orders".to_string(),
+            timestamp: timestamp.to_string(),
+            items_count,
+            status: "completed".to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: true,
+            size_bytes: None,
+        };
+
+        let index = index_with(Vec::new(), backup("orders-full-20", "20", 5));
+        let index = index_with(index, backup("orders-full-10", "10", 3));
+        let ids: Vec<&str> = index.iter().map(|b| b.backup_id.as_str()).collect();
+        assert_eq!(ids, vec!["orders-full-10", "orders-full-20"]);
+
+        // Re-adding a backup replaces its entry
+        let index = index_with(index, backup("orders-full-20", "20", 6));
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[1].items_count, 6);
+
+        assert_eq!(backup_index_key("orders"), "backups/orders/index.json");
+    }
+
+    #[test]
+    fn test_backup_list() {
+        let backup = |id: &str, table_name: &str, timestamp: &str| BackupMetadata {
+            backup_id: id.to_string(),
+            table_name: table_name.to_string(),
+            timestamp: timestamp.to_string(),
+            items_count: 1,
+            status: "completed".to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: true,
+            size_bytes: None,
+        };
+
+        let empty = backup_list(Vec::new(), Some("orders"));
+        assert_eq!(empty.count, 0);
+        assert!(empty.backups.is_empty());
+
+        let backups = vec![
+            backup("orders-full-100", "orders", "100"),
+            backup("users-full-300", "users", "300"),
+            backup("orders-full-200", "orders", "200"),
+        ];
+
+        let all = backup_list(backups.clone(), None);
+        let ids: Vec<&str> = all.backups.iter().map(|b| b.backup_id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["users-full-300", "orders-full-200", "orders-full-100"]
+        );
+        assert_eq!(all.count, 3);
+
+        let orders = backup_list(backups, Some("orders"));
+        let ids: Vec<&str> = orders
+            .backups
+            .iter()
+            .map(|b| b.backup_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["orders-full-200", "orders-full-100"]);
+        assert_eq!(orders.count, 2);
+    }
+
+    #[test]
+    fn test_requested_tables() {
+        let names = vec![
+            "orders".to_string(),
+            " users ".to_string(),
+            "".to_string(),
+            "orders".to_string(),
+        ];
+
+        assert_eq!(requested_tables("orders", None), vec!["orders"]);
+        assert_eq!(requested_tables("", Some(&names)), vec!["orders", "users"]);
+        assert_eq!(
+            requested_tables("invoices", Some(&names)),
+            vec!["invoices", "orders", "users"]
+        );
+        assert!(requested_tables("", None).is_empty());
+    }
+
+    #[test]
+    fn test_batch_backup_response_reports_partial_failure() {
+        let mut response = self_test_response();
+        response.items_backed_up = 5;
+
+        let batch = batch_backup_response(vec![
+            ("users".to_string(), Ok(response.clone())),
+            (
+                "missing".to_string(),
+                Err(Error::from("ResourceNotFoundException")),
+            ),
+            ("orders".to_string(), Ok(response)),
+        ]);
+
+        assert_eq!(batch.results.len(), 2);
+        assert_eq!(batch.total_items, 10);
+        assert_eq!(
+            batch.failures,
+            vec![BackupFailure {
+                table_name: "missing".to_string(),
+                error: "ResourceNotFoundException".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_retention_cutoff() {
+        assert_eq!(retention_cutoff(1_700_000_000, 0), 1_700_000_000);
+        assert_eq!(
+            retention_cutoff(1_700_000_000, 30),
+            1_700_000_000 - 2_592_000
+        );
+    }
+
+    #[test]
+    fn test_expired_backups_keeps_newest_per_table() {
+        let backup = |id: &str, table_name: &str, timestamp: &str, status: &str| BackupMetadata {
+            backup_id: id.to_string(),
+            table_name: table_name.to_string(),
+            timestamp: timestamp.to_string(),
+            items_count: 1,
+            status: status.to_string(),
+            projection: None,
+            partition_attr: None,
+            partitions: None,
+            item_format: ItemFormat::Plain,
+            compressed: true,
+            size_bytes: None,
+        };
+
+        let backups = vec![
+            backup("orders-full-100", "orders", "100", "completed"),
+            backup("orders-full-200", "orders", "200", "completed"),
+            backup("orders-full-300", "orders", "300", "failed"),
+            backup("orders-full-900", "orders", "900", "completed"),
+            // Every backup of users is past retention; the newest stays
+            backup("users-full-150", "users", "150", "completed"),
+            backup("users-full-250", "users", "250", "completed"),
+        ];
+
+        let ids = |backups: Vec<BackupMetadata>| {
+            let mut ids: Vec<String> = backups.into_iter().map(|b| b.backup_id).collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(
+            ids(expired_backups(backups.clone(), 500)),
+            vec![
+                "orders-full-100",
+                "orders-full-200",
+                "orders-full-300",
+                "users-full-150"
+            ]
+        );
+        // The cutoff itself isn't past retention
+        assert_eq!(
+            ids(expired_backups(backups.clone(), 200)),
+            vec!["orders-full-100", "users-full-150"]
+        );
+        assert!(expired_backups(backups, 100).is_empty());
+    }
+
+    #[test]
+    fn test_item_size_bytes_flags_oversized_items() {
+        let item = |payload: usize| {
+            HashMap::from([
+                ("id".to_string(), AttributeValue::S("user#1".to_string())),
+                ("score".to_string(), AttributeValue::N("12345".to_string())),
+                (
+                    "payload".to_string(),
+                    AttributeValue::S("x".repeat(payload)),
+                ),
+            ])
+        };
+
+        // "id" + "user#1", "score" + 4 bytes for 5 digits, "payload" + its text
+        assert_eq!(item_size_bytes(&item(10)), 8 + 9 + 17);
+        assert!(item_size_bytes(&item(1024)) <= MAX_ITEM_BYTES);
+        assert!(item_size_bytes(&item(MAX_ITEM_BYTES)) > MAX_ITEM_BYTES);
+
+        let nested = HashMap::from([(
+            "tags".to_string(),
+            AttributeValue::L(vec![AttributeValue::Bool(true), AttributeValue::Null(true)]),
+        )]);
+        assert_eq!(item_size_bytes(&nested), 4 + 3 + 2 + 2);
+    }
 }
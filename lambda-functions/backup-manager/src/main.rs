@@ -1,16 +1,190 @@
-use backup_manager::{BackupManagerService, Request, Response};
+use aegis_common::{publish_init_duration, ConfigSource, InitTiming};
+use backup_manager::{
+    requested_tables, self_test_response, version, BackupIndexReport, BackupManagerService,
+    BackupOutput, CleanupReport, IncompleteReport, OrphanReport, Request,
+};
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use std::time::Instant;
+use tracing::error;
 
-async fn function_handler(event: LambdaEvent<Request>) -> Result<Response, Error> {
+#[tracing::instrument(skip_all, fields(version = %version()))]
+async fn function_handler(event: LambdaEvent<Request>) -> Result<BackupOutput, Error> {
+    let payload = event.payload;
+    if payload.self_test {
+        return Ok(BackupOutput::Single(self_test_response()));
+    }
+
+    let init_started = Instant::now();
     let service = BackupManagerService::new().await?;
+    let init = InitTiming::measure(init_started);
+
+    if let Err(e) =
+        publish_init_duration(&service.cloudwatch_client, env!("CARGO_PKG_NAME"), init).await
+    {
+        error!("Failed to publish init duration metric: {}", e);
+    }
+    let debug = payload.debug;
+
+    let mut config_trace = service.config_trace.clone();
+    config_trace.record(
+        "table_name",
+        if payload.table_name.is_empty() {
+            ConfigSource::Env
+        } else {
+            ConfigSource::Request
+        },
+    );
+    config_trace.record(
+        "backup_type",
+        ConfigSource::of_request(&payload.backup_type, ConfigSource::Default),
+    );
+
+    let output = handle_request(&service, payload).await?.with_init(init);
+    Ok(if debug {
+        output.with_config_trace(config_trace.into_map())
+    } else {
+        output
+    })
+}
+
+async fn handle_request(
+    service: &BackupManagerService,
+    payload: Request,
+) -> Result<BackupOutput, Error> {
+    match payload.action.as_deref().unwrap_or("backup") {
+        "backup" => {}
+        action @ ("find_orphans" | "delete_orphans") => {
+            let orphans = service.find_orphaned_backups().await?;
+            let deleted = if action == "delete_orphans" {
+                service.delete_orphaned_backups(&orphans).await?
+            } else {
+                0
+            };
+
+            return Ok(BackupOutput::Orphans(OrphanReport {
+                orphans,
+                deleted,
+                version: version(),
+            }));
+        }
+        "backup_growth" => {
+            if payload.table_name.is_empty() {
+                return Err(Error::from("table_name is required for backup_growth"));
+            }
+
+            return service
+                .backup_growth(&payload.table_name)
+                .await
+                .map(BackupOutput::Growth);
+        }
+        "list" => {
+            let table_name = Some(payload.table_name.as_str()).filter(|name| !name.is_empty());
+            return service
+                .list_backup_records(table_name)
+                .await
+                .map(BackupOutput::List);
+        }
+        "rebuild_index" => {
+            if payload.table_name.is_empty() {
+                return Err(Error::from("table_name is required for rebuild_index"));
+            }
+
+            let backups = service.rebuild_index(&payload.table_name).await?;
+            return Ok(BackupOutput::Index(BackupIndexReport {
+                table_name: payload.table_name,
+                backups: backups.len(),
+                version: version(),
+            }));
+        }
+        "cleanup" => {
+            let retention_days = payload
+                .retention_days
+                .ok_or_else(|| Error::from("retention_days is required for cleanup"))?;
+
+            return Ok(BackupOutput::Cleanup(CleanupReport {
+                retention_days,
+                deleted: service.cleanup_old_backups(retention_days).await?,
+                version: version(),
+            }));
+        }
+        "find_incomplete" => {
+            return Ok(BackupOutput::Incomplete(IncompleteReport {
+                incomplete: service.find_incomplete_backups().await?,
+                version: version(),
+            }));
+        }
+        "verify_backup" => {
+            let backup_id = payload
+                .backup_id
+                .as_deref()
+                .ok_or_else(|| Error::from("backup_id is required for verify_backup"))?;
+
+            return service
+                .verify_backup_completeness(backup_id)
+                .await
+                .map(BackupOutput::Verification);
+        }
+        "restore" => {
+            let backup_id = payload
+                .backup_id
+                .as_deref()
+                .ok_or_else(|| Error::from("backup_id is required for restore"))?;
+            let target_table = payload
+                .target_table
+                .as_deref()
+                .ok_or_else(|| Error::from("target_table is required for restore"))?;
+
+            return service
+                .restore_backup(backup_id, target_table)
+                .await
+                .map(BackupOutput::Restore);
+        }
+        action => return Err(Error::from(format!("Invalid action: {}", action))),
+    }
+
+    let table_name = &payload.table_name;
+    let backup_type = payload.backup_type.unwrap_or_else(|| "full".to_string());
+
+    service.ensure_metadata_table().await?;
+
+    if let Some(table_names) = payload.table_names.as_deref() {
+        let tables = requested_tables(table_name, Some(table_names));
+        if tables.is_empty() {
+            return Err(Error::from("table_names must name at least one table"));
+        }
+
+        return Ok(BackupOutput::Batch(
+            service.run_backups(&tables, &backup_type).await,
+        ));
+    }
+
+    if !table_name.is_empty() {
+        return service
+            .run_backup(table_name, &backup_type)
+            .await
+            .map(BackupOutput::Single);
+    }
+
+    // No explicit table: back up everything CONFIG_TABLE or BACKUP_TAG opts in
+    let mut tables = service.load_configured_tables().await?;
+    for table_name in service.discover_tagged_tables().await? {
+        if !tables.contains(&table_name) {
+            tables.push(table_name);
+        }
+    }
+
+    if tables.is_empty() {
+        return Err(Error::from(
+            "table_name is required when neither CONFIG_TABLE nor BACKUP_TAG selects any tables",
+        ));
+    }
 
-    let table_name = &event.payload.table_name;
-    let backup_type = event
-        .payload
-        .backup_type
-        .unwrap_or_else(|| "full".to_string());
+    let mut responses = Vec::with_capacity(tables.len());
+    for table_name in &tables {
+        responses.push(service.run_backup(table_name, &backup_type).await?);
+    }
 
-    service.run_backup(table_name, &backup_type).await
+    Ok(BackupOutput::Multiple(responses))
 }
 
 #[tokio::main]
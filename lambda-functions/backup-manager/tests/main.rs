@@ -1,4 +1,6 @@
-use backup_manager::{generate_backup_id, BackupMetadata, GenericItem, Request, Response};
+use backup_manager::{
+    generate_backup_id, version, BackupMetadata, GenericItem, ItemFormat, Request, Response,
+};
 use lambda_runtime::{Context, LambdaEvent};
 use serde_json::json;
 use std::collections::HashMap;
@@ -23,15 +25,32 @@ fn test_request_parsing() {
     let request_minimal: Request = serde_json::from_value(json_minimal).unwrap();
     assert_eq!(request_minimal.table_name, "another-table");
     assert_eq!(request_minimal.backup_type, None);
+    assert_eq!(request_minimal.table_names, None);
+
+    // Test with several tables
+    let json_multiple = json!({
+        "table_name": "my-table",
+        "table_names": ["orders", "users"]
+    });
+
+    let request_multiple: Request = serde_json::from_value(json_multiple).unwrap();
+    assert_eq!(
+        request_multiple.table_names,
+        Some(vec!["orders".to_string(), "users".to_string()])
+    );
 }
 
 #[test]
 fn test_response_structure() {
     let response = Response {
+        version: version(),
+        init: None,
         status: "success".to_string(),
         backup_id: "table-full-1234567890".to_string(),
         timestamp: "2025-01-06T12:00:00Z".to_string(),
         items_backed_up: 150,
+        memory_pressure: false,
+        config_trace: None,
     };
 
     let json = serde_json::to_value(&response).unwrap();
@@ -49,6 +68,12 @@ fn test_backup_metadata_serialization() {
         timestamp: "1234567890".to_string(),
         items_count: 75,
         status: "completed".to_string(),
+        projection: None,
+        partition_attr: None,
+        partitions: None,
+        item_format: ItemFormat::Plain,
+        compressed: false,
+        size_bytes: None,
     };
 
     // Test serialization
@@ -62,6 +87,32 @@ fn test_backup_metadata_serialization() {
     assert_eq!(deserialized.items_count, metadata.items_count);
 }
 
+#[test]
+fn test_backup_metadata_projection() {
+    // Metadata written before projections existed has no projection field
+    let legacy = json!({
+        "backup_id": "legacy-backup",
+        "table_name": "test-table",
+        "timestamp": "1234567890",
+        "items_count": 10,
+        "status": "completed"
+    });
+
+    let metadata: BackupMetadata = serde_json::from_value(legacy).unwrap();
+    assert_eq!(metadata.projection, None);
+    // Written before compression, so its objects are plain .json
+    assert!(!metadata.compressed);
+
+    // A partial backup records which attributes it contains
+    let partial = BackupMetadata {
+        projection: Some(vec!["id".to_string(), "created_at".to_string()]),
+        ..metadata
+    };
+
+    let json = serde_json::to_value(&partial).unwrap();
+    assert_eq!(json["projection"], json!(["id", "created_at"]));
+}
+
 #[test]
 fn test_generic_item_handling() {
     let mut attributes = HashMap::new();
@@ -180,6 +231,12 @@ mod performance_tests {
                 timestamp: i.to_string(),
                 items_count: i * 10,
                 status: "completed".to_string(),
+                projection: None,
+                partition_attr: None,
+                partitions: None,
+                item_format: ItemFormat::Plain,
+                compressed: false,
+                size_bytes: None,
             };
 
             let _ = serde_json::to_string(&metadata).unwrap();
@@ -0,0 +1,1280 @@
+use aws_sdk_cloudwatch::{
+    types::{Dimension, MetricDatum, StandardUnit},
+    Client as CloudWatchClient,
+};
+use aws_sdk_dynamodb::config::http::HttpResponse;
+use aws_sdk_dynamodb::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_dynamodb::operation::scan::ScanOutput;
+use aws_sdk_eventbridge::{types::PutEventsRequestEntry, Client as EventBridgeClient};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lambda_runtime::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// Total time one invocation may spend sleeping between retries, across all calls
+const DEFAULT_RETRY_BUDGET_MS: u64 = 10_000;
+// Longest single backoff delay
+const DEFAULT_RETRY_MAX_BACKOFF_MS: u64 = 2_000;
+const RETRY_BASE_DELAY_MS: u64 = 100;
+
+// Short git hash of the commit this was built from, or "unknown"
+pub const GIT_HASH: &str = env!("AEGIS_GIT_HASH");
+
+// e.g. "0.1.0+3f2c1ab"; callers pass their own CARGO_PKG_VERSION
+pub fn build_version(pkg_version: &str) -> String {
+    format!("{}+{}", pkg_version, GIT_HASH)
+}
+
+// Sentinel attribute holding the epoch-seconds write time that lag checks
+// read. Every function writing or reading it must agree on the name.
+pub const DEFAULT_LAG_TIMESTAMP_ATTR: &str = "last_updated";
+
+// LAG_TIMESTAMP_ATTR, or last_updated
+pub fn lag_timestamp_attr() -> String {
+    std::env::var("LAG_TIMESTAMP_ATTR")
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| DEFAULT_LAG_TIMESTAMP_ATTR.to_string())
+}
+
+// Table holding the sentinel item that lag checks read and the validator's
+// latency probes write
+pub const DEFAULT_SENTINEL_TABLE: &str = "dr-sentinel-table";
+
+// SENTINEL_TABLE, or dr-sentinel-table
+pub fn sentinel_table() -> String {
+    std::env::var("SENTINEL_TABLE")
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| DEFAULT_SENTINEL_TABLE.to_string())
+}
+
+// USE_FIPS_ENDPOINTS=true sends every client to FIPS endpoints, which the
+// default endpoint resolution never selects
+pub fn use_fips_endpoints() -> bool {
+    std::env::var("USE_FIPS_ENDPOINTS")
+        .map(|value| parse_flag(&value))
+        .unwrap_or(false)
+}
+
+pub fn parse_flag(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "true" | "1" | "yes"
+    )
+}
+
+// Starting point for every SDK config, so endpoint settings apply to all
+// services and regions alike
+pub fn aws_config_loader() -> aws_config::ConfigLoader {
+    aws_config::defaults(aws_config::BehaviorVersion::latest()).use_fips(use_fips_endpoints())
+}
+
+// Set by the first invocation in a container; later ones are warm starts
+static FIRST_INVOCATION: Once = Once::new();
+
+// Whether an invocation paid the cold-start cost, and how long building its
+// service (SDK config, credentials, clients) took
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct InitTiming {
+    pub cold_start: bool,
+    pub init_duration_ms: u64,
+}
+
+impl InitTiming {
+    // `init_started` is taken just before the service is constructed
+    pub fn measure(init_started: Instant) -> Self {
+        let mut cold_start = false;
+        FIRST_INVOCATION.call_once(|| cold_start = true);
+
+        Self {
+            cold_start,
+            init_duration_ms: init_started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+pub async fn publish_init_duration(
+    client: &CloudWatchClient,
+    function_name: &str,
+    timing: InitTiming,
+) -> Result<(), Error> {
+    let metric = MetricDatum::builder()
+        .metric_name("InitDuration")
+        .value(timing.init_duration_ms as f64)
+        .unit(StandardUnit::Milliseconds)
+        .dimensions(
+            Dimension::builder()
+                .name("FunctionName")
+                .value(function_name)
+                .build(),
+        )
+        .dimensions(
+            Dimension::builder()
+                .name("ColdStart")
+                .value(timing.cold_start.to_string())
+                .build(),
+        )
+        .timestamp(aws_sdk_cloudwatch::primitives::DateTime::from(
+            std::time::SystemTime::now(),
+        ))
+        .build();
+
+    client
+        .put_metric_data()
+        .namespace("DisasterRecovery")
+        .metric_data(metric)
+        .send()
+        .await?;
+
+    Ok(())
+}
+
+// A projection expression for `attributes` that names every attribute through
+// a #placeholder, so reserved words such as `status` or `timestamp` work.
+// Dotted paths alias each segment; list indexes are kept as-is.
+pub fn aliased_projection(attributes: &[String]) -> (String, HashMap<String, String>) {
+    // attribute name -> placeholder, so a repeated name reuses its placeholder
+    let mut placeholders: HashMap<String, String> = HashMap::new();
+    let mut placeholder_for = |name: &str| -> String {
+        let next = format!("#p{}", placeholders.len());
+        placeholders.entry(name.to_string()).or_insert(next).clone()
+    };
+
+    let expression = attributes
+        .iter()
+        .map(|path| {
+            path.split('.')
+                .map(|segment| {
+                    let (name, indexes) =
+                        segment.split_at(segment.find('[').unwrap_or(segment.len()));
+                    format!("{}{}", placeholder_for(name), indexes)
+                })
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let names = placeholders
+        .into_iter()
+        .map(|(name, placeholder)| (placeholder, name))
+        .collect();
+    (expression, names)
+}
+
+// DR events (failover completed, validation degraded, backup failed) put on
+// the EVENT_BUS_NAME bus so other systems can react without polling our
+// responses. Best-effort: failures are logged, never returned.
+#[derive(Clone, Debug)]
+pub struct EventEmitter {
+    client: EventBridgeClient,
+    bus_name: Option<String>,
+    source: String,
+}
+
+impl EventEmitter {
+    // `function` becomes the event source, e.g. "aegis.failover-controller"
+    pub fn new(config: &aws_config::SdkConfig, function: &str) -> Self {
+        Self {
+            client: EventBridgeClient::new(config),
+            bus_name: std::env::var("EVENT_BUS_NAME")
+                .ok()
+                .filter(|value| !value.is_empty()),
+            source: format!("aegis.{}", function),
+        }
+    }
+
+    pub async fn emit_event<T: Serialize>(&self, detail_type: &str, detail: &T) {
+        let bus_name = match &self.bus_name {
+            Some(bus_name) => bus_name,
+            None => return,
+        };
+
+        let entry = match event_entry(&self.source, bus_name, detail_type, detail) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Failed to serialize {} event: {}", detail_type, e);
+                return;
+            }
+        };
+
+        match self.client.put_events().entries(entry).send().await {
+            Ok(output) if output.failed_entry_count() > 0 => {
+                let reason = output
+                    .entries()
+                    .iter()
+                    .find_map(|entry| entry.error_message())
+                    .unwrap_or("unknown error");
+                warn!("EventBridge rejected {} event: {}", detail_type, reason);
+            }
+            Ok(_) => info!("Emitted {} event to {}", detail_type, bus_name),
+            Err(e) => warn!("Failed to emit {} event: {}", detail_type, e),
+        }
+    }
+}
+
+pub fn event_entry<T: Serialize>(
+    source: &str,
+    bus_name: &str,
+    detail_type: &str,
+    detail: &T,
+) -> Result<PutEventsRequestEntry, serde_json::Error> {
+    Ok(PutEventsRequestEntry::builder()
+        .source(source)
+        .event_bus_name(bus_name)
+        .detail_type(detail_type)
+        .detail(serde_json::to_string(detail)?)
+        .build())
+}
+
+// Error codes AWS services use when the caller's IAM policy rejects a request
+const ACCESS_DENIED_CODES: &[&str] = &[
+    "AccessDenied",
+    "AccessDeniedException",
+    "UnauthorizedOperation",
+];
+
+// A permissions failure, kept separate from generic SDK errors so it doesn't
+// read like a service outage in the logs
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessDeniedError {
+    pub action: String,
+    pub resource: String,
+    pub message: Option<String>,
+}
+
+impl fmt::Display for AccessDeniedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Access denied: the execution role is not allowed to perform {} on {}; check its IAM policy",
+            self.action, self.resource
+        )?;
+
+        if let Some(message) = &self.message {
+            write!(f, " ({})", message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for AccessDeniedError {}
+
+// Error codes AWS services use when a request was rejected for exceeding a rate
+const THROTTLING_CODES: &[&str] = &[
+    "ThrottlingException",
+    "Throttling",
+    "ProvisionedThroughputExceededException",
+    "RequestLimitExceeded",
+    "TooManyRequestsException",
+    "SlowDown",
+];
+
+// Server-side failures that usually clear up on their own
+const TRANSIENT_CODES: &[&str] = &[
+    "InternalServerError",
+    "InternalFailure",
+    "InternalError",
+    "ServiceUnavailable",
+    "RequestTimeout",
+    "RequestTimeoutException",
+];
+
+// Failure modes a caller can tell apart, e.g. to retry a throttled call but not
+// a denied one. Handlers still return lambda_runtime::Error; `?` converts.
+#[derive(Debug)]
+pub enum AegisError {
+    DynamoUnavailable(String),
+    S3Unavailable(String),
+    Throttled(String),
+    RegionUnhealthy { region: String, reason: String },
+    AccessDenied(AccessDeniedError),
+    InvalidRequest(String),
+    Internal(String),
+}
+
+impl AegisError {
+    // Classify an SDK error by its code, then by the service named in `action`
+    // (e.g. "dynamodb:GetItem")
+    pub fn from_sdk<E>(err: E, action: &str, resource: &str) -> Self
+    where
+        E: ProvideErrorMetadata + std::error::Error,
+    {
+        if let Some(denied) = access_denied(&err, action, resource) {
+            return Self::AccessDenied(denied);
+        }
+
+        let detail = format!("{} on {}: {}", action, resource, DisplayErrorContext(&err));
+        if is_throttled(err.code()) {
+            return Self::Throttled(detail);
+        }
+
+        match action.split(':').next() {
+            Some("dynamodb") => Self::DynamoUnavailable(detail),
+            Some("s3") => Self::S3Unavailable(detail),
+            _ => Self::Internal(detail),
+        }
+    }
+}
+
+impl fmt::Display for AegisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DynamoUnavailable(detail) => write!(f, "DynamoDB unavailable: {}", detail),
+            Self::S3Unavailable(detail) => write!(f, "S3 unavailable: {}", detail),
+            Self::Throttled(detail) => write!(f, "Throttled: {}", detail),
+            Self::RegionUnhealthy { region, reason } => {
+                write!(f, "Region {} is unhealthy: {}", region, reason)
+            }
+            Self::AccessDenied(denied) => denied.fmt(f),
+            Self::InvalidRequest(detail) => write!(f, "Invalid request: {}", detail),
+            Self::Internal(detail) => write!(f, "Internal error: {}", detail),
+        }
+    }
+}
+
+impl std::error::Error for AegisError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::AccessDenied(denied) => Some(denied),
+            _ => None,
+        }
+    }
+}
+
+impl From<AccessDeniedError> for AegisError {
+    fn from(denied: AccessDeniedError) -> Self {
+        Self::AccessDenied(denied)
+    }
+}
+
+impl From<aws_sdk_dynamodb::Error> for AegisError {
+    fn from(err: aws_sdk_dynamodb::Error) -> Self {
+        Self::from_sdk(err, "dynamodb", "unknown resource")
+    }
+}
+
+impl From<aws_sdk_s3::Error> for AegisError {
+    fn from(err: aws_sdk_s3::Error) -> Self {
+        Self::from_sdk(err, "s3", "unknown resource")
+    }
+}
+
+// For code still returning lambda_runtime::Error: an AegisError or
+// AccessDeniedError inside keeps its variant, anything else is Internal
+impl From<Error> for AegisError {
+    fn from(err: Error) -> Self {
+        match err.downcast::<AegisError>() {
+            Ok(err) => *err,
+            Err(err) => match err.downcast::<AccessDeniedError>() {
+                Ok(denied) => Self::AccessDenied(*denied),
+                Err(err) => Self::Internal(err.to_string()),
+            },
+        }
+    }
+}
+
+pub fn is_throttled(code: Option<&str>) -> bool {
+    code.is_some_and(|code| THROTTLING_CODES.contains(&code))
+}
+
+pub fn is_transient_code(code: Option<&str>) -> bool {
+    is_throttled(code) || code.is_some_and(|code| TRANSIENT_CODES.contains(&code))
+}
+
+pub fn is_access_denied(code: Option<&str>) -> bool {
+    code.is_some_and(|code| ACCESS_DENIED_CODES.contains(&code))
+}
+
+pub fn access_denied<E: ProvideErrorMetadata>(
+    err: &E,
+    action: &str,
+    resource: &str,
+) -> Option<AccessDeniedError> {
+    is_access_denied(err.code()).then(|| AccessDeniedError {
+        action: action.to_string(),
+        resource: resource.to_string(),
+        message: err.message().map(str::to_string),
+    })
+}
+
+// Translate an SDK error into an AccessDeniedError naming the denied action and
+// resource; anything else passes through unchanged
+pub fn map_sdk_error<E>(err: E, action: &str, resource: &str) -> Error
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    match access_denied(&err, action, resource) {
+        Some(denied) => Box::new(denied),
+        None => Box::new(err),
+    }
+}
+
+pub trait SdkResultExt<T> {
+    fn map_sdk_err(self, action: &str, resource: &str) -> Result<T, Error>;
+    fn map_aegis_err(self, action: &str, resource: &str) -> Result<T, AegisError>;
+}
+
+impl<T, E> SdkResultExt<T> for Result<T, E>
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    fn map_sdk_err(self, action: &str, resource: &str) -> Result<T, Error> {
+        self.map_err(|err| map_sdk_error(err, action, resource))
+    }
+
+    fn map_aegis_err(self, action: &str, resource: &str) -> Result<T, AegisError> {
+        self.map_err(|err| AegisError::from_sdk(err, action, resource))
+    }
+}
+
+// A scan page with no `items` at all, although DynamoDB examined items, points
+// at a projection or filter problem rather than an empty table. Logs a warning
+// and returns true in that case.
+pub fn warn_on_missing_scan_items(output: &ScanOutput, table_name: &str) -> bool {
+    let missing = output.items.is_none() && output.scanned_count > 0;
+    if missing {
+        warn!(
+            "Scan of {} examined {} items but returned no items; check the projection and filter expressions",
+            table_name, output.scanned_count
+        );
+    }
+    missing
+}
+
+// Which CloudWatch metrics a request wants published. Disabled publishes
+// nothing; otherwise only names on the allowlist, or everything without one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricFilter {
+    pub enabled: bool,
+    pub allowlist: Option<Vec<String>>,
+}
+
+impl MetricFilter {
+    pub fn new(enabled: bool, allowlist: Option<Vec<String>>) -> Self {
+        Self { enabled, allowlist }
+    }
+
+    pub fn all() -> Self {
+        Self::new(true, None)
+    }
+
+    pub fn allows(&self, metric_name: &str) -> bool {
+        self.enabled
+            && self
+                .allowlist
+                .as_ref()
+                .is_none_or(|allowlist| allowlist.iter().any(|name| name == metric_name))
+    }
+}
+
+impl Default for MetricFilter {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+// serde default for request `publish_metrics` flags
+pub fn default_publish_metrics() -> bool {
+    true
+}
+
+// Where a resolved config value came from, reported in `config_trace` when a
+// request sets `debug`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigSource {
+    Request,
+    Env,
+    Ssm,
+    Default,
+}
+
+impl ConfigSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Env => "env",
+            Self::Ssm => "ssm",
+            Self::Default => "default",
+        }
+    }
+
+    // Env when the variable is set to a non-empty value, otherwise Default
+    pub fn of_env(name: &str) -> Self {
+        match std::env::var(name) {
+            Ok(value) if !value.is_empty() => Self::Env,
+            _ => Self::Default,
+        }
+    }
+
+    // Request when the request supplied the value, otherwise `fallback`
+    pub fn of_request<T>(value: &Option<T>, fallback: Self) -> Self {
+        if value.is_some() {
+            Self::Request
+        } else {
+            fallback
+        }
+    }
+}
+
+// Config key -> source name, e.g. {"BACKUP_BUCKET": "env"}
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigTrace {
+    entries: HashMap<String, String>,
+}
+
+impl ConfigTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, key: &str, source: ConfigSource) {
+        self.entries
+            .insert(key.to_string(), source.as_str().to_string());
+    }
+
+    // Record each environment-backed key as env or default
+    pub fn record_env(&mut self, keys: &[&str]) {
+        for key in keys {
+            self.record(key, ConfigSource::of_env(key));
+        }
+    }
+
+    pub fn into_map(self) -> HashMap<String, String> {
+        self.entries
+    }
+}
+
+// How a handler's response is returned, from RESPONSE_FORMAT ("json" or
+// "msgpack"). MessagePack keeps large responses under the Step Functions
+// 256KB payload limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl ResponseFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "msgpack" | "messagepack" => Some(Self::MessagePack),
+            _ => None,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        match std::env::var("RESPONSE_FORMAT") {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                warn!("Unknown RESPONSE_FORMAT {:?}, using json", value);
+                Self::Json
+            }),
+            Err(_) => Self::Json,
+        }
+    }
+}
+
+// JSON responses pass through as-is; MessagePack ones become a base64 string
+// of the named-field encoding
+pub fn encode_response<T: Serialize>(
+    response: &T,
+    format: ResponseFormat,
+) -> Result<serde_json::Value, Error> {
+    match format {
+        ResponseFormat::Json => Ok(serde_json::to_value(response)?),
+        ResponseFormat::MessagePack => {
+            let bytes = rmp_serde::to_vec_named(response)?;
+            Ok(serde_json::Value::String(STANDARD.encode(bytes)))
+        }
+    }
+}
+
+// Per-region clients (or SDK configs) built on first use and reused for the
+// rest of the invocation, so repeated calls against a region don't reload
+// credentials and config each time
+pub struct RegionCache<T> {
+    entries: tokio::sync::Mutex<HashMap<String, T>>,
+}
+
+impl<T: Clone> RegionCache<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    // The lock is held while loading so concurrent callers don't load twice
+    pub async fn get_or_load<F, Fut>(&self, region: &str, load: F) -> T
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut entries = self.entries.lock().await;
+        if let Some(entry) = entries.get(region) {
+            return entry.clone();
+        }
+
+        let entry = load(region.to_string()).await;
+        entries.insert(region.to_string(), entry.clone());
+        entry
+    }
+}
+
+impl<T: Clone> Default for RegionCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Shared by every retrying call in an invocation so retries can't compound past
+// the Lambda timeout. Clones share the same remaining budget.
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    remaining_ms: Arc<AtomicU64>,
+    max_backoff: Duration,
+}
+
+impl RetryBudget {
+    pub fn new(total: Duration, max_backoff: Duration) -> Self {
+        Self {
+            remaining_ms: Arc::new(AtomicU64::new(total.as_millis() as u64)),
+            max_backoff,
+        }
+    }
+
+    // RETRY_BUDGET_MS and RETRY_MAX_BACKOFF_MS; create one per invocation
+    pub fn from_env() -> Self {
+        let env_ms = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self::new(
+            Duration::from_millis(env_ms("RETRY_BUDGET_MS", DEFAULT_RETRY_BUDGET_MS)),
+            Duration::from_millis(env_ms("RETRY_MAX_BACKOFF_MS", DEFAULT_RETRY_MAX_BACKOFF_MS)),
+        )
+    }
+
+    pub fn remaining(&self) -> Duration {
+        Duration::from_millis(self.remaining_ms.load(Ordering::SeqCst))
+    }
+
+    // Take `delay` out of the budget; false (and nothing taken) if it doesn't fit
+    fn try_spend(&self, delay: Duration) -> bool {
+        let delay_ms = delay.as_millis() as u64;
+        self.remaining_ms
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                remaining.checked_sub(delay_ms)
+            })
+            .is_ok()
+    }
+
+    // Exponential backoff from RETRY_BASE_DELAY_MS, capped at max_backoff
+    pub fn backoff_delay(&self, retry: u32) -> Duration {
+        let delay_ms = RETRY_BASE_DELAY_MS
+            .checked_shl(retry)
+            .unwrap_or(u64::MAX)
+            .min(self.max_backoff.as_millis() as u64);
+        Duration::from_millis(delay_ms)
+    }
+}
+
+// Errors worth retrying: throttling, timeouts, dropped connections and 5xx
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+impl<E: ProvideErrorMetadata> Transient for SdkError<E, HttpResponse> {
+    fn is_transient(&self) -> bool {
+        match self {
+            SdkError::TimeoutError(_)
+            | SdkError::DispatchFailure(_)
+            | SdkError::ResponseError(_) => true,
+            SdkError::ServiceError(context) => {
+                is_transient_code(context.err().code()) || context.raw().status().is_server_error()
+            }
+            _ => false,
+        }
+    }
+}
+
+// Between half and all of `delay`, so callers that failed together don't all
+// retry at the same moment
+fn jitter(delay: Duration) -> Duration {
+    let half_ms = delay.as_millis() as u64 / 2;
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_millis(half_ms + random % (half_ms + 1))
+}
+
+// Retry `op` up to `max_attempts` times. Once the budget can't cover the next
+// delay the last error is returned immediately instead of sleeping.
+pub async fn retry_with_backoff<F, Fut, T, E>(
+    budget: &RetryBudget,
+    max_attempts: u32,
+    op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: fmt::Display,
+{
+    retry_if(budget, max_attempts, op, |_| true, false).await
+}
+
+// Like retry_with_backoff, but gives up at once on errors that aren't
+// transient and jitters each delay
+pub async fn with_retry<F, Fut, T, E>(
+    budget: &RetryBudget,
+    max_attempts: u32,
+    op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Transient + fmt::Display,
+{
+    retry_if(budget, max_attempts, op, E::is_transient, true).await
+}
+
+async fn retry_if<F, Fut, T, E>(
+    budget: &RetryBudget,
+    max_attempts: u32,
+    mut op: F,
+    retryable: impl Fn(&E) -> bool,
+    jittered: bool,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: fmt::Display,
+{
+    let mut retry = 0;
+
+    loop {
+        let err = match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        if retry + 1 >= max_attempts || !retryable(&err) {
+            return Err(err);
+        }
+
+        let delay = match jittered {
+            true => jitter(budget.backoff_delay(retry)),
+            false => budget.backoff_delay(retry),
+        };
+        if !budget.try_spend(delay) {
+            warn!(
+                "Retry budget exhausted ({:?} left), not retrying: {}",
+                budget.remaining(),
+                err
+            );
+            return Err(err);
+        }
+
+        warn!(
+            "Attempt {} failed, retrying in {:?}: {}",
+            retry + 1,
+            delay,
+            err
+        );
+        tokio::time::sleep(delay).await;
+        retry += 1;
+    }
+}
+
+// Opens after `threshold` consecutive failures and stays open, so calls into a
+// region that stopped answering are skipped rather than each waiting out its
+// timeout. Clones share the same state; create one per invocation.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    consecutive_failures: Arc<AtomicU32>,
+    open: Arc<AtomicBool>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            consecutive_failures: Arc::new(AtomicU32::new(0)),
+            open: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+    }
+
+    // True only for the failure that opened the breaker
+    pub fn record_failure(&self) -> bool {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        failures >= self.threshold && !self.open.swap(true, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::error::ErrorMetadata;
+    use aws_sdk_dynamodb::operation::get_item::GetItemError;
+
+    fn sdk_error(code: &str) -> GetItemError {
+        GetItemError::generic(
+            ErrorMetadata::builder()
+                .code(code)
+                .message("User is not authorized to perform: dynamodb:GetItem")
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_is_access_denied() {
+        assert!(is_access_denied(Some("AccessDenied")));
+        assert!(is_access_denied(Some("AccessDeniedException")));
+        assert!(is_access_denied(Some("UnauthorizedOperation")));
+        assert!(!is_access_denied(Some("ThrottlingException")));
+        assert!(!is_access_denied(None));
+    }
+
+    #[test]
+    fn test_map_sdk_error_access_denied() {
+        let err = map_sdk_error(
+            sdk_error("AccessDeniedException"),
+            "dynamodb:GetItem",
+            "dr-sentinel-table",
+        );
+
+        let denied = err.downcast_ref::<AccessDeniedError>().unwrap();
+        assert_eq!(denied.action, "dynamodb:GetItem");
+        assert_eq!(denied.resource, "dr-sentinel-table");
+        assert!(err
+            .to_string()
+            .contains("dynamodb:GetItem on dr-sentinel-table"));
+    }
+
+    #[test]
+    fn test_map_sdk_error_passes_through_other_errors() {
+        let result: Result<(), GetItemError> = Err(sdk_error("ThrottlingException"));
+        let err = result
+            .map_sdk_err("dynamodb:GetItem", "dr-sentinel-table")
+            .unwrap_err();
+
+        assert!(err.downcast_ref::<AccessDeniedError>().is_none());
+        assert!(err.downcast_ref::<GetItemError>().is_some());
+    }
+
+    #[test]
+    fn test_aegis_error_from_sdk() {
+        let err = AegisError::from_sdk(
+            sdk_error("AccessDeniedException"),
+            "dynamodb:GetItem",
+            "dr-sentinel-table",
+        );
+        assert!(
+            matches!(&err, AegisError::AccessDenied(denied) if denied.action == "dynamodb:GetItem")
+        );
+
+        let err = AegisError::from_sdk(
+            sdk_error("ProvisionedThroughputExceededException"),
+            "dynamodb:GetItem",
+            "dr-sentinel-table",
+        );
+        assert!(matches!(err, AegisError::Throttled(_)));
+
+        let err = AegisError::from_sdk(
+            sdk_error("InternalServerError"),
+            "dynamodb:GetItem",
+            "dr-sentinel-table",
+        );
+        assert!(
+            matches!(&err, AegisError::DynamoUnavailable(detail) if detail.contains("dr-sentinel-table"))
+        );
+
+        let result: Result<(), GetItemError> = Err(sdk_error("NoSuchBucket"));
+        let err = result
+            .map_aegis_err("s3:ListBucket", "backup-bucket")
+            .unwrap_err();
+        assert!(matches!(err, AegisError::S3Unavailable(_)));
+    }
+
+    #[test]
+    fn test_aegis_error_from_service_errors() {
+        let err = AegisError::from(aws_sdk_dynamodb::Error::from(sdk_error(
+            "ThrottlingException",
+        )));
+        assert!(matches!(err, AegisError::Throttled(_)));
+
+        let err = AegisError::from(aws_sdk_s3::Error::NoSuchBucket(
+            aws_sdk_s3::types::error::NoSuchBucket::builder().build(),
+        ));
+        assert!(matches!(err, AegisError::S3Unavailable(_)));
+    }
+
+    #[test]
+    fn test_aegis_error_round_trips_through_lambda_error() {
+        let err: Error = AegisError::InvalidRequest("table_name is required".to_string()).into();
+        assert!(matches!(
+            AegisError::from(err),
+            AegisError::InvalidRequest(_)
+        ));
+
+        let err = map_sdk_error(
+            sdk_error("AccessDeniedException"),
+            "dynamodb:GetItem",
+            "dr-sentinel-table",
+        );
+        assert!(matches!(AegisError::from(err), AegisError::AccessDenied(_)));
+
+        let err = Error::from("something unexpected");
+        assert!(matches!(AegisError::from(err), AegisError::Internal(_)));
+    }
+
+    #[test]
+    fn test_build_version() {
+        let version = build_version("1.2.3");
+        assert!(version.starts_with("1.2.3+"));
+        assert!(version.len() > "1.2.3+".len());
+    }
+
+    #[test]
+    fn test_backoff_delay_is_capped() {
+        let budget = RetryBudget::new(Duration::from_secs(10), Duration::from_millis(500));
+        assert_eq!(budget.backoff_delay(0), Duration::from_millis(100));
+        assert_eq!(budget.backoff_delay(1), Duration::from_millis(200));
+        assert_eq!(budget.backoff_delay(3), Duration::from_millis(500));
+        assert_eq!(budget.backoff_delay(80), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_retries_until_success() {
+        let budget = RetryBudget::new(Duration::from_secs(1), Duration::from_millis(1));
+        let mut calls = 0;
+
+        let result: Result<u32, String> = retry_with_backoff(&budget, 3, || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt < 3 {
+                    Err(format!("attempt {} failed", attempt))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(3));
+        assert_eq!(calls, 3);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Throttled,
+        Denied,
+    }
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl Transient for TestError {
+        fn is_transient(&self) -> bool {
+            *self == TestError::Throttled
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_retries_transient_errors() {
+        let budget = RetryBudget::new(Duration::from_secs(1), Duration::from_millis(1));
+        let mut calls = 0;
+
+        let result = with_retry(&budget, 5, || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt <= 2 {
+                    Err(TestError::Throttled)
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(3));
+        assert_eq!(calls, 3);
+
+        // Never more than max_attempts calls
+        calls = 0;
+        let result: Result<(), _> = with_retry(&budget, 2, || {
+            calls += 1;
+            async { Err(TestError::Throttled) }
+        })
+        .await;
+        assert_eq!(result, Err(TestError::Throttled));
+        assert_eq!(calls, 2);
+
+        // A permanent error is returned without retrying
+        calls = 0;
+        let result: Result<(), _> = with_retry(&budget, 5, || {
+            calls += 1;
+            async { Err(TestError::Denied) }
+        })
+        .await;
+        assert_eq!(result, Err(TestError::Denied));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_delay() {
+        for _ in 0..100 {
+            let delay = jitter(Duration::from_millis(400));
+            assert!(delay >= Duration::from_millis(200));
+            assert!(delay <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn test_transient_codes() {
+        assert!(is_transient_code(Some(
+            "ProvisionedThroughputExceededException"
+        )));
+        assert!(is_transient_code(Some("InternalServerError")));
+        assert!(!is_transient_code(Some("ValidationException")));
+        assert!(!is_transient_code(None));
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_is_shared_and_fails_fast() {
+        // Enough budget for exactly one 100ms retry across both calls
+        let budget = RetryBudget::new(Duration::from_millis(150), Duration::from_secs(1));
+        let shared = budget.clone();
+        let mut calls = 0;
+
+        let first: Result<(), &str> = retry_with_backoff(&budget, 5, || {
+            calls += 1;
+            async { Err("throttled") }
+        })
+        .await;
+        assert!(first.is_err());
+        assert_eq!(calls, 2); // one retry, then the 200ms delay doesn't fit
+
+        calls = 0;
+        let second: Result<(), &str> = retry_with_backoff(&shared, 5, || {
+            calls += 1;
+            async { Err("throttled") }
+        })
+        .await;
+        assert!(second.is_err());
+        assert_eq!(calls, 1); // budget already spent, no retries
+        assert_eq!(shared.remaining(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_metric_filter() {
+        assert!(MetricFilter::all().allows("ReplicationLag"));
+        assert!(!MetricFilter::new(false, None).allows("ReplicationLag"));
+
+        let lag_only = MetricFilter::new(true, Some(vec!["ReplicationLag".to_string()]));
+        assert!(lag_only.allows("ReplicationLag"));
+        assert!(!lag_only.allows("S3Health"));
+
+        let disabled = MetricFilter::new(false, Some(vec!["ReplicationLag".to_string()]));
+        assert!(!disabled.allows("ReplicationLag"));
+    }
+
+    #[test]
+    fn test_response_format_parse() {
+        assert_eq!(ResponseFormat::parse("json"), Some(ResponseFormat::Json));
+        assert_eq!(
+            ResponseFormat::parse(" MsgPack "),
+            Some(ResponseFormat::MessagePack)
+        );
+        assert_eq!(ResponseFormat::parse("xml"), None);
+    }
+
+    #[test]
+    fn test_encode_response() {
+        #[derive(Serialize)]
+        struct Payload {
+            status: &'static str,
+        }
+        let payload = Payload { status: "healthy" };
+
+        let json = encode_response(&payload, ResponseFormat::Json).unwrap();
+        assert_eq!(json["status"], "healthy");
+
+        let encoded = encode_response(&payload, ResponseFormat::MessagePack).unwrap();
+        let bytes = STANDARD.decode(encoded.as_str().unwrap()).unwrap();
+        assert_eq!(bytes, rmp_serde::to_vec_named(&payload).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_region_cache_loads_once_per_region() {
+        let cache = RegionCache::new();
+        let loads = AtomicU64::new(0);
+        let load = |region: String| {
+            loads.fetch_add(1, Ordering::SeqCst);
+            async move { format!("client-{}", region) }
+        };
+
+        assert_eq!(
+            cache.get_or_load("us-east-1", load).await,
+            "client-us-east-1"
+        );
+        assert_eq!(
+            cache.get_or_load("us-east-1", load).await,
+            "client-us-east-1"
+        );
+        assert_eq!(
+            cache.get_or_load("us-west-2", load).await,
+            "client-us-west-2"
+        );
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        assert!(parse_flag("true"));
+        assert!(parse_flag(" TRUE "));
+        assert!(parse_flag("1"));
+        assert!(!parse_flag("false"));
+        assert!(!parse_flag(""));
+    }
+
+    #[test]
+    fn test_config_trace() {
+        std::env::set_var("AEGIS_TEST_CONFIG_TRACE_SET", "value");
+        std::env::set_var("AEGIS_TEST_CONFIG_TRACE_EMPTY", "");
+
+        let mut trace = ConfigTrace::new();
+        trace.record_env(&[
+            "AEGIS_TEST_CONFIG_TRACE_SET",
+            "AEGIS_TEST_CONFIG_TRACE_EMPTY",
+            "AEGIS_TEST_CONFIG_TRACE_UNSET",
+        ]);
+        trace.record(
+            "table_name",
+            ConfigSource::of_request(&Some("t"), ConfigSource::Default),
+        );
+        trace.record(
+            "backup_type",
+            ConfigSource::of_request(&None::<String>, ConfigSource::Default),
+        );
+        trace.record("runbook", ConfigSource::Ssm);
+
+        let map = trace.into_map();
+        assert_eq!(map["AEGIS_TEST_CONFIG_TRACE_SET"], "env");
+        assert_eq!(map["AEGIS_TEST_CONFIG_TRACE_EMPTY"], "default");
+        assert_eq!(map["AEGIS_TEST_CONFIG_TRACE_UNSET"], "default");
+        assert_eq!(map["table_name"], "request");
+        assert_eq!(map["backup_type"], "default");
+        assert_eq!(map["runbook"], "ssm");
+    }
+
+    #[test]
+    fn test_init_timing_cold_start_once() {
+        let first = InitTiming::measure(Instant::now());
+        let second = InitTiming::measure(Instant::now());
+
+        // Only one invocation per process is ever the cold start
+        assert!(!second.cold_start);
+        assert!(first.init_duration_ms < 1000);
+
+        let json = serde_json::to_value(second).unwrap();
+        assert_eq!(json["cold_start"], false);
+    }
+
+    #[test]
+    fn test_aliased_projection_reserved_words() {
+        let attributes: Vec<String> = ["id", "status", "timestamp", "address.status", "tags[0]"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+
+        let (expression, names) = aliased_projection(&attributes);
+        assert_eq!(expression, "#p0, #p1, #p2, #p3.#p1, #p4[0]");
+        assert_eq!(names["#p1"], "status");
+        assert_eq!(names["#p3"], "address");
+        assert_eq!(names["#p4"], "tags");
+        assert_eq!(names.len(), 5);
+        assert!(!expression.contains("status"));
+    }
+
+    #[test]
+    fn test_event_entry() {
+        let entry = event_entry(
+            "aegis.failover-controller",
+            "dr-events",
+            "Failover Completed",
+            &serde_json::json!({"target_region": "us-west-2"}),
+        )
+        .unwrap();
+
+        assert_eq!(entry.detail_type(), Some("Failover Completed"));
+        let detail: serde_json::Value = serde_json::from_str(entry.detail().unwrap()).unwrap();
+        assert_eq!(detail["target_region"], "us-west-2");
+    }
+
+    #[test]
+    fn test_warn_on_missing_scan_items() {
+        let missing = ScanOutput::builder().scanned_count(5).build();
+        assert!(warn_on_missing_scan_items(&missing, "dr-application-table"));
+
+        let empty_page = ScanOutput::builder()
+            .set_items(Some(Vec::new()))
+            .scanned_count(5)
+            .build();
+        assert!(!warn_on_missing_scan_items(
+            &empty_page,
+            "dr-application-table"
+        ));
+
+        let empty_table = ScanOutput::builder().scanned_count(0).build();
+        assert!(!warn_on_missing_scan_items(
+            &empty_table,
+            "dr-application-table"
+        ));
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3);
+        let shared = breaker.clone();
+
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        breaker.record_success();
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert!(!shared.is_open());
+
+        assert!(shared.record_failure());
+        assert!(breaker.is_open());
+
+        // Stays open, and only the tripping failure reports it
+        breaker.record_success();
+        assert!(!breaker.record_failure());
+        assert!(shared.is_open());
+    }
+}
@@ -0,0 +1,3958 @@
+use aegis_common::{
+    aws_config_loader, build_version, default_publish_metrics, encode_response, lag_timestamp_attr,
+    map_sdk_error, publish_init_duration, sentinel_table, warn_on_missing_scan_items, with_retry,
+    CircuitBreaker, ConfigSource, ConfigTrace, EventEmitter, InitTiming, MetricFilter, RegionCache,
+    ResponseFormat, RetryBudget, SdkResultExt,
+};
+use anyhow::{anyhow, Result};
+use aws_config::sts::AssumeRoleProvider;
+use aws_config::SdkConfig;
+use aws_sdk_cloudwatch::{
+    types::Dimension, types::MetricDatum, types::StandardUnit, Client as CloudWatchClient,
+};
+use aws_sdk_dynamodb::{
+    operation::{put_item::PutItemError, transact_write_items::TransactWriteItemsError},
+    types::{
+        AttributeValue, BillingMode, KeySchemaElement, Put, Select, TableDescription,
+        TransactWriteItem,
+    },
+    Client as DynamoClient,
+};
+use aws_sdk_s3::{
+    operation::get_object_attributes::{GetObjectAttributesError, GetObjectAttributesOutput},
+    types::ObjectAttributes,
+    Client as S3Client,
+};
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use lambda_runtime::{Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+// Number of batch entries validated at the same time unless the request overrides it
+const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+
+// Timed put/get round-trips against the DR sentinel table per run
+const DR_LATENCY_PROBES: usize = 5;
+
+// Attempts at a validation read that keeps failing transiently
+const SDK_RETRY_ATTEMPTS: u32 = 3;
+
+// Concurrent DR writes during a sync unless the request overrides it
+const DEFAULT_SYNC_CONCURRENCY: usize = 8;
+
+// Most items one sync writes to a DR table unless the request overrides it
+const DEFAULT_MAX_SYNC_ITEMS: usize = 1000;
+
+// TransactWriteItems accepts at most 100 actions
+const TRANSACT_WRITE_MAX_ITEMS: usize = 100;
+
+// Tables validated when neither the request nor CONFIG_TABLE names any
+const DEFAULT_TABLES: [&str; 2] = ["dr-application-table", "dr-sentinel-table"];
+const DEFAULT_DR_LATENCY_THRESHOLD_MS: u64 = 250;
+
+// Consistency below this is Degraded unless the request sets
+// consistency_threshold; below the failed threshold it is Failed
+const DEFAULT_CONSISTENCY_THRESHOLD: f64 = 95.0;
+const DEFAULT_FAILED_THRESHOLD: f64 = 50.0;
+
+// Consecutive failed DR calls after which the rest are skipped
+const DEFAULT_DR_CIRCUIT_BREAKER_THRESHOLD: u32 = 5;
+
+// Mismatches reported per table when diffing exports, as many as a live run samples
+const EXPORT_MISMATCH_SAMPLE: usize = 10;
+
+// Runs, including this one, averaged into smoothed_consistency
+const DEFAULT_SMOOTHING_WINDOW: usize = 5;
+
+// Baselines live in dr-backup-metadata under "validation_baseline#<table>"
+const BASELINE_ID_PREFIX: &str = "validation_baseline#";
+// Resumable runs record their position under "validation_checkpoint#<DR region>"
+const CHECKPOINT_ID_PREFIX: &str = "validation_checkpoint#";
+const DEFAULT_TABLES_PER_RUN: usize = 10;
+
+// Newest backup objects whose checksums are compared across buckets per run
+const DEFAULT_CHECKSUM_SAMPLE_SIZE: usize = 20;
+
+// Lag monitoring stops this long before the invocation's deadline, leaving time
+// for the last sample and the checks after it
+const LAG_MONITOR_RESERVE: Duration = Duration::from_secs(30);
+const DEFAULT_LAG_SAMPLE_INTERVAL_SECONDS: u64 = 5;
+
+#[derive(Deserialize, Clone)]
+pub struct ValidationRequest {
+    pub validation_type: Option<String>, // "full", "incremental", or "specific"
+    pub table_name: Option<String>,
+    pub source_region: Option<String>,
+    pub target_region: Option<String>,
+    // Validate the primary against each of these DR regions instead of
+    // target_region. Single requests only.
+    pub target_regions: Option<Vec<String>>,
+    // Assumed for the DR region's clients when DR lives in another account
+    pub role_arn: Option<String>,
+    // Per-table source/target region and role, for tables replicating to
+    // different DR regions or accounts. Each table is validated on its own,
+    // falling back to the request's regions and role; responses come back as
+    // a batch in table name order. Single requests only.
+    pub table_targets: Option<HashMap<String, TableTarget>>,
+    pub action: Option<String>, // "validate", "sync" or "capture_baseline"
+    // Report each table's drift since its captured baseline. Fingerprinting
+    // scans primary and DR in full.
+    #[serde(default)]
+    pub compare_baseline: bool,
+    // Consistency score (percent) below which the run is Failed rather than
+    // Degraded; defaults to 50
+    pub failed_threshold: Option<f64>,
+    // Consistency score (percent, 0-100) below which the run is Degraded and
+    // mismatches are flagged; defaults to 95
+    pub consistency_threshold: Option<f64>,
+    // Per-table floor on the primary item count; below it the run is degraded
+    pub min_expected_items: Option<HashMap<String, usize>>,
+    // Intentionally async-replicated tables: still validated and reported under
+    // `excluded_from_score`, but left out of consistency_score and the status
+    pub exclude_from_score: Option<Vec<String>>,
+    // Compare describe_table's estimate against a counting scan (costs a full scan)
+    pub check_count_drift: Option<bool>,
+    // Per-table maximum age in hours of the newest completed backup
+    pub backup_sla_hours: Option<HashMap<String, f64>>,
+    // Smoke-test mode: return a canned response without calling AWS
+    #[serde(default)]
+    pub self_test: bool,
+    // Attribute to order sync writes by (e.g. parent rows before children).
+    // Items sharing a value are written concurrently; unset means unordered.
+    pub sync_order_by: Option<String>,
+    pub sync_concurrency: Option<usize>,
+    // Missing items past this many are left for the next sync
+    pub max_sync_items: Option<usize>,
+    // Write each sync batch with TransactWriteItems (up to 100 items at a time)
+    // so interdependent items land together or not at all
+    #[serde(default)]
+    pub sync_transactional: bool,
+    // Checks primary must pass before a sync may copy its data into DR
+    #[serde(default)]
+    pub sync_preconditions: SyncPreconditions,
+    // Last-modified attribute, e.g. "updated_at". Sync never overwrites an item
+    // DR wrote in the meantime; with this set it still replaces one whose value
+    // is older than primary's.
+    pub sync_version_attribute: Option<String>,
+    #[serde(default)]
+    pub compare_target: CompareTarget,
+    // Scan both tables in full and compare every item's content hash instead
+    // of counts and a sample; only applies when comparing against the DR table
+    #[serde(default)]
+    pub deep: bool,
+    // Diff native exports of table_name instead of reading the live tables
+    pub exports: Option<ExportSources>,
+    // Validate at most tables_per_run tables, continuing from where the last
+    // resumable run stopped, so a pass over many tables spans invocations
+    #[serde(default)]
+    pub resume: bool,
+    pub tables_per_run: Option<usize>,
+    #[serde(default = "default_publish_metrics")]
+    pub publish_metrics: bool,
+    // Only these metric names are published, e.g. ["ValidationConsistencyScore"]
+    pub metric_allowlist: Option<Vec<String>>,
+    // Include each table's counts and sampled mismatches in the response
+    #[serde(default)]
+    pub include_table_details: bool,
+    // Report where each config value came from in `config_trace`
+    #[serde(default)]
+    pub debug: bool,
+    // Compare the S3 checksums of the newest checksum_sample_size backup
+    // objects between BACKUP_BUCKET and DR_BACKUP_BUCKET
+    #[serde(default)]
+    pub verify_object_checksums: bool,
+    pub checksum_sample_size: Option<usize>,
+    // Sample replication lag every lag_sample_interval_seconds for this long,
+    // publishing each sample; unset takes the usual single sample
+    pub lag_monitor_seconds: Option<u64>,
+    pub lag_sample_interval_seconds: Option<u64>,
+    // Invocation deadline in epoch milliseconds, taken from the Lambda context
+    #[serde(skip)]
+    pub deadline_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TableTarget {
+    pub source_region: Option<String>,
+    pub target_region: Option<String>,
+    pub role_arn: Option<String>,
+}
+
+// What primary is compared against
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareTarget {
+    #[default]
+    DrTable,
+    // The newest backup of the table in S3, to check backup fidelity
+    LatestBackup,
+}
+
+// S3 locations of DYNAMODB_JSON exports of one table, e.g.
+// "s3://bucket/prefix/AWSDynamoDB/01234567890123-abcdef12". Both are read with
+// the primary region's S3 client.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExportSources {
+    pub primary: String,
+    pub dr: String,
+}
+
+// Items of an export keyed by `id`; items without a string id are only counted
+#[derive(Debug, Default)]
+pub struct ExportData {
+    pub count: usize,
+    pub items: HashMap<String, serde_json::Value>,
+}
+
+// A corrupt or truncated primary must not be propagated into DR by a sync.
+// Every unset check passes.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SyncPreconditions {
+    // Primary must hold at least this many items
+    pub min_primary_items: Option<usize>,
+    // Primary must hold at least this fraction of DR's items; a primary that
+    // suddenly shrank is more likely damaged than DR is stale
+    pub min_primary_to_dr_ratio: Option<f64>,
+    // This id must exist in primary's sentinel table (SENTINEL_TABLE)
+    pub required_sentinel_id: Option<String>,
+}
+
+pub type Item = HashMap<String, AttributeValue>;
+
+// Sort key for ordered syncs: numbers before strings, items without the attribute last
+#[derive(Debug, PartialEq, PartialOrd)]
+pub enum SyncOrderKey {
+    Number(f64),
+    Text(String),
+    Missing,
+}
+
+// Declared in order of severity, so the worst of several is their max
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationStatus {
+    Healthy,
+    Degraded,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct ValidationResponse {
+    pub status: ValidationStatus,
+    pub validation_type: String,
+    pub timestamp: String,
+    pub results: ValidationResults,
+    pub recommendations: Vec<String>,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_trace: Option<HashMap<String, String>>,
+    // cold_start and init_duration_ms, set once the service is built
+    #[serde(flatten)]
+    pub init: Option<InitTiming>,
+}
+
+#[derive(Deserialize)]
+pub struct BatchValidationRequest {
+    pub requests: Vec<ValidationRequest>,
+    pub max_concurrency: Option<usize>,
+}
+
+// A batch is recognised by its `requests` field; anything else is a single request
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ValidationEvent {
+    Batch(BatchValidationRequest),
+    Single(Box<ValidationRequest>),
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ValidationOutput {
+    Batch(Vec<ValidationResponse>),
+    Single(Box<ValidationResponse>),
+    MultiRegion(MultiRegionValidation),
+}
+
+// One primary validated against several DR regions; healthy only when every
+// DR region is
+#[derive(Serialize)]
+pub struct MultiRegionValidation {
+    pub status: ValidationStatus,
+    pub regions: Vec<RegionValidation>,
+    pub timestamp: String,
+    pub version: String,
+}
+
+#[derive(Serialize)]
+pub struct RegionValidation {
+    pub target_region: String,
+    pub response: ValidationResponse,
+}
+
+#[derive(Serialize)]
+pub struct ValidationResults {
+    pub tables_validated: usize,
+    // Tables requested but not validated, with why
+    pub tables_skipped: usize,
+    pub skipped_reasons: HashMap<String, String>,
+    pub records_checked: usize,
+    pub mismatches_found: usize,
+    pub replication_lag_seconds: Option<i64>,
+    // Every sample taken in lag monitoring mode, oldest first
+    pub lag_samples: Vec<LagSample>,
+    pub dr_latency: Option<DrLatency>,
+    pub backup_status: BackupStatus,
+    pub backup_sla: Vec<BackupSla>,
+    pub consistency_score: f64,
+    // Weighted average of this and the previous runs' scores, newest weighted
+    // most. None without AUDIT_BUCKET.
+    pub smoothed_consistency: Option<f64>,
+    pub tables_below_min_items: Vec<String>,
+    pub count_estimate_drift: Vec<CountEstimateDrift>,
+    pub schema_drift: Vec<SchemaDrift>,
+    pub excluded_from_score: Vec<ExcludedTable>,
+    pub throughput: Vec<ThroughputComparison>,
+    pub sync_refused: Vec<SyncRefusal>,
+    pub sync_results: Vec<TableSync>,
+    // DR stopped answering mid-run; its remaining checks were skipped
+    pub dr_unreachable: bool,
+    pub baseline_drift: Vec<BaselineDrift>,
+    // Only reported when the request asked for verify_object_checksums
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_checksums: Option<ObjectChecksumReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<ValidationProgress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table_details: Option<Vec<TableDetail>>,
+}
+
+// How far a resumable pass has got; complete once every table was validated
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ValidationProgress {
+    pub tables_done: usize,
+    pub tables_total: usize,
+    pub complete: bool,
+}
+
+// Where a resumable pass stopped. A different table list starts a new pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationCheckpoint {
+    pub tables: Vec<String>,
+    pub next_index: usize,
+}
+
+// Per-table breakdown returned when the request sets include_table_details
+#[derive(Serialize, Debug, Clone)]
+pub struct TableDetail {
+    pub table_name: String,
+    pub primary_count: usize,
+    pub dr_count: usize,
+    pub sample_mismatches: Vec<Mismatch>,
+}
+
+// Capacity settings of one table; capacities are None in on-demand mode
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ThroughputSnapshot {
+    pub billing_mode: String,
+    pub read_capacity_units: Option<i64>,
+    pub write_capacity_units: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ThroughputComparison {
+    pub table_name: String,
+    pub primary: ThroughputSnapshot,
+    pub dr: ThroughputSnapshot,
+    // DR would throttle if it took primary's load right now
+    pub dr_under_provisioned: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExcludedTable {
+    pub table_name: String,
+    pub records_checked: usize,
+    pub mismatches_found: usize,
+}
+
+// A sync skipped because primary failed its preconditions
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SyncRefusal {
+    pub table_name: String,
+    pub reason: String,
+}
+
+// None when the probe record hadn't replicated by the end of the sample
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LagSample {
+    pub timestamp: String,
+    pub lag_seconds: Option<i64>,
+}
+
+// Items a sync wrote to DR, those it left alone because DR's copy was
+// written after primary's was read, and those over max_sync_items
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct TableSync {
+    pub table_name: String,
+    pub items_synced: usize,
+    pub items_skipped_newer: usize,
+    pub items_deferred: usize,
+}
+
+// How a sync writes the items missing from DR
+#[derive(Debug, Clone, Copy)]
+pub struct SyncOptions<'a> {
+    pub order_by: Option<&'a str>,
+    pub concurrency: usize,
+    pub transactional: bool,
+    pub version_attribute: Option<&'a str>,
+    pub max_items: usize,
+}
+
+// Condition on a DR write, with its placeholders
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteCondition {
+    pub expression: String,
+    pub names: HashMap<String, String>,
+    pub values: Option<HashMap<String, AttributeValue>>,
+}
+
+// Exact counts and content hashes of both sides of a table at one point in time
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TableBaseline {
+    pub table_name: String,
+    pub primary_count: usize,
+    pub dr_count: usize,
+    pub primary_hash: String,
+    pub dr_hash: String,
+    pub captured_at: String,
+}
+
+// Change since the baseline, so steady-state skew between primary and DR is
+// told apart from divergence introduced after it was captured
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BaselineDrift {
+    pub table_name: String,
+    pub baseline_captured_at: String,
+    pub primary_count_change: i64,
+    pub dr_count_change: i64,
+    // primary_count - dr_count
+    pub skew_at_baseline: i64,
+    pub skew_now: i64,
+    pub diverged: bool,
+}
+
+// Structural differences between a table's primary and DR definitions
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SchemaDrift {
+    pub table_name: String,
+    pub differences: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CountEstimateDrift {
+    pub table_name: String,
+    pub describe_table_count: usize,
+    pub scan_count: usize,
+    // scan_count - describe_table_count; positive means the estimate is behind
+    pub drift: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DrLatency {
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub max_ms: u64,
+}
+
+// Freshness of one table's newest backup against its own SLA
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BackupSla {
+    pub table_name: String,
+    pub max_age_hours: f64,
+    // None when the table has no completed backup at all
+    pub newest_backup_age_hours: Option<f64>,
+    pub pass: bool,
+}
+
+#[derive(Serialize)]
+pub struct BackupStatus {
+    pub last_backup_age_hours: Option<f64>,
+    pub backup_count: usize,
+    pub oldest_backup_days: Option<f64>,
+    // The metadata scan failed, so backup_count 0 means "unknown", not "none"
+    pub backup_check_failed: bool,
+}
+
+impl BackupStatus {
+    pub fn check_failed() -> Self {
+        Self {
+            last_backup_age_hours: None,
+            backup_count: 0,
+            oldest_backup_days: None,
+            backup_check_failed: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TableValidation {
+    pub table_name: String,
+    pub primary_count: usize,
+    // Item count of the compare target (DR table or latest backup)
+    pub dr_count: usize,
+    pub sample_mismatches: Vec<Mismatch>,
+}
+
+// Sampled backup objects whose copy in the DR bucket is missing or differs
+#[derive(Serialize, Debug, Clone)]
+pub struct ObjectChecksumReport {
+    pub objects_checked: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Mismatch {
+    pub key: String,
+    pub reason: String,
+}
+
+// One line of the JSONL audit object written per run when AUDIT_BUCKET is set
+#[derive(Serialize, Debug, Clone)]
+pub struct AuditRecord {
+    pub table_name: String,
+    // None for table-level mismatches such as differing item counts
+    pub key: Option<String>,
+    pub reason: String,
+    pub timestamp: String,
+}
+
+// Summary of one run, kept under reports/ in AUDIT_BUCKET for smoothing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ValidationReport {
+    pub timestamp: String,
+    pub tables_validated: usize,
+    pub consistency_score: f64,
+}
+
+pub struct DataValidatorService {
+    pub primary_dynamo: DynamoClient,
+    pub dr_dynamo: DynamoClient,
+    pub s3_client: S3Client,
+    pub backup_bucket: String,
+    // DR_BACKUP_BUCKET (default "<BACKUP_BUCKET>-dr"), the replica of
+    // backup_bucket read through dr_s3_client
+    pub dr_backup_bucket: String,
+    pub dr_s3_client: S3Client,
+    pub audit_bucket: Option<String>,
+    pub smoothing_window: usize,
+    pub metric_filter: MetricFilter,
+    pub cloudwatch_client: CloudWatchClient,
+    pub events: EventEmitter,
+    pub source_region: String,
+    pub target_region: String,
+    pub dr_latency_threshold_ms: u64,
+    // SENTINEL_TABLE: lag checks, latency probes and the sync sentinel use it
+    pub sentinel_table: String,
+    // Opened by DR_CIRCUIT_BREAKER_THRESHOLD consecutive failed DR calls
+    pub dr_breaker: CircuitBreaker,
+    // Shared by every retried call this service makes
+    pub retry_budget: RetryBudget,
+    // Random per service, so sentinel test records from concurrent
+    // invocations never share an id
+    pub probe_salt: String,
+    // TTL_ATTRIBUTE: epoch-seconds expiry attribute. Expired items may already
+    // be gone from one side, so they are left out of comparisons and syncs.
+    pub ttl_attribute: Option<String>,
+    // Source of the regions and environment-backed settings above
+    pub config_trace: ConfigTrace,
+}
+
+impl DataValidatorService {
+    // Region configs come from a cache shared across a batch, so requests
+    // against the same regions don't each reload them
+    pub async fn new(
+        source_region: Option<String>,
+        target_region: Option<String>,
+        region_configs: &RegionCache<SdkConfig>,
+    ) -> Result<Self, Error> {
+        let mut config_trace = ConfigTrace::new();
+        config_trace.record(
+            "source_region",
+            ConfigSource::of_request(&source_region, ConfigSource::Default),
+        );
+        config_trace.record(
+            "target_region",
+            ConfigSource::of_request(&target_region, ConfigSource::Default),
+        );
+        config_trace.record_env(&[
+            "DR_LATENCY_THRESHOLD_MS",
+            "DR_CIRCUIT_BREAKER_THRESHOLD",
+            "METRIC_REGION",
+            "SENTINEL_TABLE",
+            "BACKUP_BUCKET",
+            "DR_BACKUP_BUCKET",
+            "AUDIT_BUCKET",
+            "SMOOTHING_WINDOW",
+            "TTL_ATTRIBUTE",
+            "RESPONSE_FORMAT",
+        ]);
+
+        let source_region = source_region.unwrap_or_else(|| "us-east-1".to_string());
+        let target_region = target_region.unwrap_or_else(|| "us-west-2".to_string());
+
+        // Configure clients for both regions
+        let primary_config = region_configs
+            .get_or_load(&source_region, load_region_config)
+            .await;
+        let dr_config = region_configs
+            .get_or_load(&target_region, load_region_config)
+            .await;
+        // METRIC_REGION keeps every run's metrics in one monitoring region
+        // instead of whichever region is primary for this request
+        let metric_config = match std::env::var("METRIC_REGION")
+            .ok()
+            .filter(|region| !region.is_empty())
+        {
+            Some(region) => {
+                region_configs
+                    .get_or_load(&region, load_region_config)
+                    .await
+            }
+            None => primary_config.clone(),
+        };
+
+        let dr_latency_threshold_ms = std::env::var("DR_LATENCY_THRESHOLD_MS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_DR_LATENCY_THRESHOLD_MS);
+        let dr_circuit_breaker_threshold = std::env::var("DR_CIRCUIT_BREAKER_THRESHOLD")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_DR_CIRCUIT_BREAKER_THRESHOLD);
+        let backup_bucket = std::env::var("BACKUP_BUCKET")
+            .unwrap_or_else(|_| "dr-demo-backup-bucket-primary".to_string());
+        let dr_backup_bucket =
+            std::env::var("DR_BACKUP_BUCKET").unwrap_or_else(|_| format!("{}-dr", backup_bucket));
+        let audit_bucket = std::env::var("AUDIT_BUCKET").ok();
+        let smoothing_window = std::env::var("SMOOTHING_WINDOW")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_SMOOTHING_WINDOW)
+            .max(1);
+        let ttl_attribute = std::env::var("TTL_ATTRIBUTE")
+            .ok()
+            .filter(|value| !value.is_empty());
+
+        Ok(Self {
+            primary_dynamo: DynamoClient::new(&primary_config),
+            dr_dynamo: DynamoClient::new(&dr_config),
+            s3_client: S3Client::new(&primary_config),
+            backup_bucket,
+            dr_backup_bucket,
+            dr_s3_client: S3Client::new(&dr_config),
+            audit_bucket,
+            smoothing_window,
+            metric_filter: MetricFilter::all(),
+            cloudwatch_client: CloudWatchClient::new(&metric_config),
+            events: EventEmitter::new(&primary_config, env!("CARGO_PKG_NAME")),
+            source_region,
+            target_region,
+            dr_latency_threshold_ms,
+            sentinel_table: sentinel_table(),
+            dr_breaker: CircuitBreaker::new(dr_circuit_breaker_threshold),
+            retry_budget: RetryBudget::from_env(),
+            probe_salt: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            ttl_attribute,
+            config_trace,
+        })
+    }
+
+    // Rebuilds the DR client with credentials from assuming `role_arn`
+    pub async fn with_dr_role(
+        mut self,
+        role_arn: Option<String>,
+        region_configs: &RegionCache<SdkConfig>,
+    ) -> Self {
+        let role_arn = match role_arn.filter(|role_arn| !role_arn.is_empty()) {
+            Some(role_arn) => role_arn,
+            None => return self,
+        };
+
+        let target_region = self.target_region.clone();
+        let dr_config = region_configs
+            .get_or_load(&format!("{}#{}", target_region, role_arn), |_| {
+                load_assumed_role_config(target_region, role_arn)
+            })
+            .await;
+        self.dr_dynamo = DynamoClient::new(&dr_config);
+        self.dr_s3_client = S3Client::new(&dr_config);
+        self.config_trace.record("role_arn", ConfigSource::Request);
+        self
+    }
+
+    pub fn with_metric_filter(mut self, metric_filter: MetricFilter) -> Self {
+        self.metric_filter = metric_filter;
+        self
+    }
+
+    pub async fn get_table_item_count(
+        &self,
+        client: &DynamoClient,
+        table_name: &str,
+    ) -> Result<usize> {
+        let result = client
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:DescribeTable", table_name)))?;
+
+        if let Some(table) = result.table {
+            Ok(table.item_count.unwrap_or(0) as usize)
+        } else {
+            Ok(0)
+        }
+    }
+
+    pub async fn get_scan_item_count(
+        &self,
+        client: &DynamoClient,
+        table_name: &str,
+    ) -> Result<usize> {
+        let mut total = 0usize;
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = client
+                .scan()
+                .table_name(table_name)
+                .select(Select::Count)
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:Scan", table_name)))?;
+
+            total += result.count.max(0) as usize;
+
+            // The scan is complete once DynamoDB stops returning a continuation key
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(total)
+    }
+
+    pub async fn describe_table(
+        &self,
+        client: &DynamoClient,
+        table_name: &str,
+    ) -> Result<TableDescription> {
+        client
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:DescribeTable", table_name)))?
+            .table
+            .ok_or_else(|| anyhow!("describe_table returned no description for {}", table_name))
+    }
+
+    // Key schema, attribute definitions and GSIs that differ between regions
+    pub async fn compare_schema(&self, table_name: &str) -> Result<Vec<String>> {
+        let primary = self
+            .describe_table(&self.primary_dynamo, table_name)
+            .await?;
+        let dr = self.describe_table(&self.dr_dynamo, table_name).await?;
+
+        Ok(diff_table_schemas(&primary, &dr))
+    }
+
+    pub async fn compare_throughput(&self, table_name: &str) -> Result<ThroughputComparison> {
+        let primary = self
+            .describe_table(&self.primary_dynamo, table_name)
+            .await?;
+        let dr = self.describe_table(&self.dr_dynamo, table_name).await?;
+
+        let primary = throughput_snapshot(&primary);
+        let dr = throughput_snapshot(&dr);
+
+        Ok(ThroughputComparison {
+            table_name: table_name.to_string(),
+            dr_under_provisioned: dr_under_provisioned(&primary, &dr),
+            primary,
+            dr,
+        })
+    }
+
+    // Diagnostic: how far describe_table's cached item_count is from a real count
+    pub async fn compute_count_drift(&self, table_name: &str) -> Result<CountEstimateDrift> {
+        let describe_table_count = self
+            .get_table_item_count(&self.primary_dynamo, table_name)
+            .await?;
+        let scan_count = self
+            .get_scan_item_count(&self.primary_dynamo, table_name)
+            .await?;
+
+        Ok(CountEstimateDrift {
+            table_name: table_name.to_string(),
+            describe_table_count,
+            scan_count,
+            drift: scan_count as i64 - describe_table_count as i64,
+        })
+    }
+
+    // Item count and order-independent content hash of a whole table
+    pub async fn table_fingerprint(
+        &self,
+        client: &DynamoClient,
+        table_name: &str,
+    ) -> Result<(usize, String)> {
+        let now = Utc::now().timestamp();
+        let mut count = 0usize;
+        let mut hash = 0u64;
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = client
+                .scan()
+                .table_name(table_name)
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:Scan", table_name)))?;
+            warn_on_missing_scan_items(&result, table_name);
+
+            for item in result
+                .items()
+                .iter()
+                .filter(|item| !self.is_expired(item, now))
+            {
+                count += 1;
+                hash = hash.wrapping_add(item_hash(item));
+            }
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok((count, format!("{:016x}", hash)))
+    }
+
+    // Content hash of every unexpired item, keyed by its "id"
+    pub async fn scan_item_hashes(
+        &self,
+        client: &DynamoClient,
+        table_name: &str,
+    ) -> Result<HashMap<String, u64>> {
+        let now = Utc::now().timestamp();
+        let mut hashes = HashMap::new();
+        let mut last_evaluated_key = None;
+
+        loop {
+            let request = client
+                .scan()
+                .table_name(table_name)
+                .set_exclusive_start_key(last_evaluated_key);
+            let result = with_retry(&self.retry_budget, SDK_RETRY_ATTEMPTS, || {
+                request.clone().send()
+            })
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:Scan", table_name)))?;
+            warn_on_missing_scan_items(&result, table_name);
+
+            for item in result
+                .items()
+                .iter()
+                .filter(|item| !self.is_expired(item, now))
+            {
+                if let Some(Ok(id)) = item.get("id").map(|id| id.as_s()) {
+                    hashes.insert(id.clone(), item_hash(item));
+                }
+            }
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(hashes)
+    }
+
+    pub async fn deep_validate_table(&self, table_name: &str) -> Result<TableValidation> {
+        let primary = self
+            .scan_item_hashes(&self.primary_dynamo, table_name)
+            .await?;
+        let dr = self.scan_item_hashes(&self.dr_dynamo, table_name).await?;
+        let sample_mismatches = deep_mismatches(&primary, &dr);
+
+        info!(
+            "Table {}: deep comparison found {} mismatched items",
+            table_name,
+            sample_mismatches.len()
+        );
+
+        Ok(TableValidation {
+            table_name: table_name.to_string(),
+            primary_count: primary.len(),
+            dr_count: dr.len(),
+            sample_mismatches,
+        })
+    }
+
+    pub async fn snapshot_table(&self, table_name: &str) -> Result<TableBaseline> {
+        let (primary_count, primary_hash) = self
+            .table_fingerprint(&self.primary_dynamo, table_name)
+            .await?;
+        let (dr_count, dr_hash) = self.table_fingerprint(&self.dr_dynamo, table_name).await?;
+
+        Ok(TableBaseline {
+            table_name: table_name.to_string(),
+            primary_count,
+            dr_count,
+            primary_hash,
+            dr_hash,
+            captured_at: Utc::now().to_rfc3339(),
+        })
+    }
+
+    pub async fn capture_baseline(&self, table_name: &str) -> Result<TableBaseline> {
+        let baseline = self.snapshot_table(table_name).await?;
+
+        self.primary_dynamo
+            .put_item()
+            .table_name("dr-backup-metadata")
+            .set_item(Some(baseline_to_item(&baseline)))
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:PutItem", "dr-backup-metadata")))?;
+
+        info!(
+            "Captured baseline for {}: primary {} items ({}), DR {} items ({})",
+            table_name,
+            baseline.primary_count,
+            baseline.primary_hash,
+            baseline.dr_count,
+            baseline.dr_hash
+        );
+        Ok(baseline)
+    }
+
+    pub async fn load_baseline(&self, table_name: &str) -> Result<Option<TableBaseline>> {
+        let result = self
+            .primary_dynamo
+            .get_item()
+            .table_name("dr-backup-metadata")
+            .key(
+                "backup_id",
+                AttributeValue::S(format!("{}{}", BASELINE_ID_PREFIX, table_name)),
+            )
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:GetItem", "dr-backup-metadata")))?;
+
+        Ok(result.item.as_ref().and_then(baseline_from_item))
+    }
+
+    fn checkpoint_id(&self) -> String {
+        format!("{}{}", CHECKPOINT_ID_PREFIX, self.target_region)
+    }
+
+    pub async fn load_checkpoint(&self) -> Result<Option<ValidationCheckpoint>> {
+        let result = self
+            .primary_dynamo
+            .get_item()
+            .table_name("dr-backup-metadata")
+            .key("backup_id", AttributeValue::S(self.checkpoint_id()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:GetItem", "dr-backup-metadata")))?;
+
+        Ok(result.item.as_ref().and_then(checkpoint_from_item))
+    }
+
+    pub async fn save_checkpoint(&self, checkpoint: &ValidationCheckpoint) -> Result<()> {
+        self.primary_dynamo
+            .put_item()
+            .table_name("dr-backup-metadata")
+            .set_item(Some(checkpoint_to_item(&self.checkpoint_id(), checkpoint)))
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:PutItem", "dr-backup-metadata")))?;
+        Ok(())
+    }
+
+    // None when no baseline has been captured for the table
+    pub async fn compare_with_baseline(&self, table_name: &str) -> Result<Option<BaselineDrift>> {
+        let baseline = match self.load_baseline(table_name).await? {
+            Some(baseline) => baseline,
+            None => return Ok(None),
+        };
+        let current = self.snapshot_table(table_name).await?;
+        Ok(Some(baseline_drift(&baseline, &current)))
+    }
+
+    // Ids of every item in the newest backup written by the backup manager
+    pub async fn load_latest_backup_ids(&self, table_name: &str) -> Result<HashSet<String>> {
+        let prefix = format!("backups/{}/", table_name);
+        let mut latest: Option<(aws_sdk_s3::primitives::DateTime, String)> = None;
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let result = self
+                .s3_client
+                .list_objects_v2()
+                .bucket(&self.backup_bucket)
+                .prefix(&prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|e| anyhow!(map_sdk_error(e, "s3:ListBucket", &self.backup_bucket)))?;
+
+            for object in result.contents.unwrap_or_default() {
+                if let (Some(key), Some(modified)) = (object.key, object.last_modified) {
+                    // backup-manager's listing of the table's backups, not a backup
+                    if key.ends_with("/index.json") {
+                        continue;
+                    }
+                    if latest.as_ref().is_none_or(|(newest, _)| modified > *newest) {
+                        latest = Some((modified, key.clone()));
+                    }
+                    keys.push(key);
+                }
+            }
+
+            match result.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        let latest_key = match latest {
+            Some((_, key)) => key,
+            None => return Err(anyhow!("No backups found for table {}", table_name)),
+        };
+
+        // A partitioned backup is one <backup_id>.json.gz (.json for older
+        // backups) per partition directory
+        let file_name = latest_key.rsplit('/').next().unwrap_or(&latest_key);
+        let backup_keys: Vec<&String> = keys
+            .iter()
+            .filter(|key| key.rsplit('/').next() == Some(file_name))
+            .collect();
+
+        info!(
+            "Comparing {} against backup {} ({} objects)",
+            table_name,
+            file_name,
+            backup_keys.len()
+        );
+
+        let mut items: Vec<HashMap<String, serde_json::Value>> = Vec::new();
+        for key in backup_keys {
+            let object = self
+                .s3_client
+                .get_object()
+                .bucket(&self.backup_bucket)
+                .key(key)
+                .send()
+                .await
+                .map_err(|e| anyhow!(map_sdk_error(e, "s3:GetObject", key)))?;
+            let body = object.body.collect().await?.into_bytes();
+            let object_items: Vec<HashMap<String, serde_json::Value>> = if key.ends_with(".gz") {
+                serde_json::from_reader(GzDecoder::new(body.as_ref()))?
+            } else {
+                serde_json::from_slice(&body)?
+            };
+            items.extend(object_items);
+        }
+
+        Ok(items
+            .iter()
+            // Plain backups hold "id": "x", DynamoDB JSON ones "id": {"S": "x"}
+            .filter_map(|item| {
+                let id = item.get("id")?;
+                id.as_str().or_else(|| id["S"].as_str()).map(str::to_string)
+            })
+            .collect())
+    }
+
+    // Reads every data file listed in the export's manifest-files.json
+    pub async fn load_export(&self, uri: &str) -> Result<ExportData> {
+        let (bucket, prefix) =
+            parse_s3_uri(uri).ok_or_else(|| anyhow!("Invalid export location {}", uri))?;
+        let manifest_key = format!("{}/manifest-files.json", prefix);
+        let manifest = self.get_s3_object(&bucket, &manifest_key).await?;
+
+        let mut data_keys = Vec::new();
+        for line in String::from_utf8(manifest)?.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: serde_json::Value = serde_json::from_str(line)?;
+            match entry["dataFileS3Key"].as_str() {
+                Some(key) => data_keys.push(key.to_string()),
+                None => return Err(anyhow!("Malformed manifest entry in {}", manifest_key)),
+            }
+        }
+
+        let now = Utc::now().timestamp();
+        let mut export = ExportData::default();
+        for key in data_keys {
+            let mut contents = String::new();
+            GzDecoder::new(self.get_s3_object(&bucket, &key).await?.as_slice())
+                .read_to_string(&mut contents)?;
+
+            for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                let mut record: serde_json::Value = serde_json::from_str(line)?;
+                let item = record["Item"].take();
+                let expired = self
+                    .ttl_attribute
+                    .as_deref()
+                    .is_some_and(|attribute| export_item_expired(&item, attribute, now));
+                if expired {
+                    continue;
+                }
+
+                export.count += 1;
+                if let Some(id) = item["id"]["S"].as_str() {
+                    export.items.insert(id.to_string(), item);
+                }
+            }
+        }
+
+        info!("Loaded {} items from export {}", export.count, uri);
+        Ok(export)
+    }
+
+    pub async fn get_s3_object(&self, bucket: &str, key: &str) -> Result<Vec<u8>> {
+        let object = self
+            .s3_client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "s3:GetObject", key)))?;
+        Ok(object.body.collect().await?.into_bytes().to_vec())
+    }
+
+    pub async fn validate_table_exports(
+        &self,
+        table_name: &str,
+        exports: &ExportSources,
+    ) -> Result<TableValidation> {
+        info!(
+            "Validating table {} from exports {} and {}",
+            table_name, exports.primary, exports.dr
+        );
+
+        let primary = self.load_export(&exports.primary).await?;
+        let dr = self.load_export(&exports.dr).await?;
+        Ok(compare_exports(table_name, &primary, &dr))
+    }
+
+    pub async fn compare_object_checksums(
+        &self,
+        sample_size: usize,
+    ) -> Result<ObjectChecksumReport> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let result = self
+                .s3_client
+                .list_objects_v2()
+                .bucket(&self.backup_bucket)
+                .prefix("backups/")
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|e| anyhow!(map_sdk_error(e, "s3:ListBucket", &self.backup_bucket)))?;
+
+            for object in result.contents.unwrap_or_default() {
+                if let (Some(key), Some(modified)) = (object.key, object.last_modified) {
+                    objects.push((modified, key));
+                }
+            }
+
+            match result.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        objects.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        let keys: Vec<String> = objects
+            .into_iter()
+            .map(|(_, key)| key)
+            .take(sample_size)
+            .collect();
+
+        let mut mismatches = Vec::new();
+        for key in &keys {
+            let primary = self
+                .object_checksum(&self.s3_client, &self.backup_bucket, key)
+                .await?;
+            let dr = self
+                .object_checksum(&self.dr_s3_client, &self.dr_backup_bucket, key)
+                .await?;
+            if let Some(reason) = checksum_mismatch(primary.as_deref(), dr.as_deref()) {
+                mismatches.push(Mismatch {
+                    key: key.clone(),
+                    reason: reason.to_string(),
+                });
+            }
+        }
+
+        info!(
+            "Compared checksums of {} backup objects with {}: {} mismatched",
+            keys.len(),
+            self.dr_backup_bucket,
+            mismatches.len()
+        );
+
+        Ok(ObjectChecksumReport {
+            objects_checked: keys.len(),
+            mismatches,
+        })
+    }
+
+    // None when the object doesn't exist
+    async fn object_checksum(
+        &self,
+        client: &S3Client,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<String>> {
+        match client
+            .get_object_attributes()
+            .bucket(bucket)
+            .key(key)
+            .object_attributes(ObjectAttributes::Checksum)
+            .object_attributes(ObjectAttributes::Etag)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(Some(object_fingerprint(&output))),
+            Err(e)
+                if matches!(
+                    e.as_service_error(),
+                    Some(GetObjectAttributesError::NoSuchKey(_))
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(anyhow!(map_sdk_error(
+                e,
+                "s3:GetObjectAttributes",
+                &format!("{}/{}", bucket, key)
+            ))),
+        }
+    }
+
+    pub fn is_expired(&self, item: &Item, now: i64) -> bool {
+        self.ttl_attribute
+            .as_deref()
+            .is_some_and(|attribute| item_expired(item, attribute, now))
+    }
+
+    pub async fn validate_table_data(
+        &self,
+        table_name: &str,
+        compare_target: CompareTarget,
+        deep: bool,
+    ) -> Result<TableValidation> {
+        info!(
+            "Validating table: {} against {:?}",
+            table_name, compare_target
+        );
+
+        if deep && compare_target == CompareTarget::DrTable {
+            return self.deep_validate_table(table_name).await;
+        }
+
+        // Get item counts
+        let primary_count = self
+            .get_table_item_count(&self.primary_dynamo, table_name)
+            .await?;
+
+        let backup_ids = match compare_target {
+            CompareTarget::DrTable => None,
+            CompareTarget::LatestBackup => Some(self.load_latest_backup_ids(table_name).await?),
+        };
+
+        let dr_count = match &backup_ids {
+            Some(ids) => ids.len(),
+            None => {
+                self.get_table_item_count(&self.dr_dynamo, table_name)
+                    .await?
+            }
+        };
+
+        let mut sample_mismatches = Vec::new();
+
+        // Sample validation - check a few random items
+        let scan_result = with_retry(&self.retry_budget, SDK_RETRY_ATTEMPTS, || {
+            self.primary_dynamo
+                .scan()
+                .table_name(table_name)
+                .limit(10)
+                .send()
+        })
+        .await
+        .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:Scan", table_name)))?;
+        warn_on_missing_scan_items(&scan_result, table_name);
+
+        if let Some(items) = scan_result.items {
+            let now = Utc::now().timestamp();
+            for item in items.iter().filter(|item| !self.is_expired(item, now)) {
+                if let Some(id_attr) = item.get("id") {
+                    if let Ok(id) = id_attr.as_s() {
+                        if let Some(ids) = &backup_ids {
+                            if !ids.contains(id) {
+                                sample_mismatches.push(Mismatch {
+                                    key: id.clone(),
+                                    reason: "not found in latest backup".to_string(),
+                                });
+                            }
+                            continue;
+                        }
+
+                        if self.dr_breaker.is_open() {
+                            break;
+                        }
+
+                        // Check if item exists in DR
+                        let dr_result = with_retry(&self.retry_budget, SDK_RETRY_ATTEMPTS, || {
+                            self.dr_dynamo
+                                .get_item()
+                                .table_name(table_name)
+                                .key("id", AttributeValue::S(id.to_string()))
+                                .send()
+                        })
+                        .await;
+
+                        match dr_result {
+                            Ok(response) => {
+                                self.dr_breaker.record_success();
+                                if response.item.is_none() {
+                                    sample_mismatches.push(Mismatch {
+                                        key: id.clone(),
+                                        reason: "not found in DR".to_string(),
+                                    });
+                                }
+                            }
+                            Err(e) => {
+                                warn!("Error checking item {} in DR: {}", id, e);
+                                self.record_dr_failure();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(TableValidation {
+            table_name: table_name.to_string(),
+            primary_count,
+            dr_count,
+            sample_mismatches,
+        })
+    }
+
+    pub async fn check_replication_lag(&self) -> Result<Option<i64>> {
+        // Write a timestamp to primary
+        let test_id = probe_record_id("lag-test", &self.probe_salt);
+        let timestamp = Utc::now().timestamp();
+
+        self.primary_dynamo
+            .put_item()
+            .table_name(&self.sentinel_table)
+            .item("id", AttributeValue::S(test_id.clone()))
+            .item(
+                lag_timestamp_attr(),
+                AttributeValue::N(timestamp.to_string()),
+            )
+            .item("source", AttributeValue::S("validator".to_string()))
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:PutItem", &self.sentinel_table)))?;
+
+        // Wait a bit for replication
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+
+        // Try to read from DR
+        let start_time = Utc::now();
+        let mut lag = None;
+
+        for _ in 0..10 {
+            let result = self
+                .dr_dynamo
+                .get_item()
+                .table_name(&self.sentinel_table)
+                .key("id", AttributeValue::S(test_id.clone()))
+                .send()
+                .await;
+
+            if let Ok(response) = result {
+                if response.item.is_some() {
+                    lag = Some((Utc::now() - start_time).num_seconds());
+                    break;
+                }
+            }
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        }
+
+        // Clean up exactly this run's test record
+        let _ = self
+            .primary_dynamo
+            .delete_item()
+            .table_name(&self.sentinel_table)
+            .key("id", AttributeValue::S(test_id))
+            .send()
+            .await;
+
+        Ok(lag)
+    }
+
+    pub async fn monitor_replication_lag(
+        &self,
+        window: Duration,
+        interval: Duration,
+        publish_metrics: bool,
+    ) -> Vec<LagSample> {
+        info!(
+            "Monitoring replication lag for {}s, sampling every {}s",
+            window.as_secs(),
+            interval.as_secs()
+        );
+
+        let started = Instant::now();
+        let mut samples = Vec::new();
+
+        loop {
+            let sampled_at = Utc::now();
+            let lag = self.check_replication_lag().await.unwrap_or_else(|e| {
+                warn!("Failed to sample replication lag: {}", e);
+                None
+            });
+
+            if let (Some(lag), true) = (lag, publish_metrics) {
+                if let Err(e) = self.publish_lag_sample(lag, sampled_at.into()).await {
+                    error!("Failed to publish replication lag sample: {}", e);
+                }
+            }
+
+            samples.push(LagSample {
+                timestamp: sampled_at.to_rfc3339(),
+                lag_seconds: lag,
+            });
+
+            if self.dr_breaker.is_open() || started.elapsed() + interval >= window {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+
+        samples
+    }
+
+    pub async fn publish_lag_sample(
+        &self,
+        lag_seconds: i64,
+        sampled_at: std::time::SystemTime,
+    ) -> Result<(), Error> {
+        if !self.metric_filter.allows("ReplicationLagSeconds") {
+            return Ok(());
+        }
+
+        let metric = MetricDatum::builder()
+            .metric_name("ReplicationLagSeconds")
+            .value(lag_seconds as f64)
+            .unit(StandardUnit::Seconds)
+            .timestamp(aws_sdk_cloudwatch::primitives::DateTime::from(sampled_at))
+            .build();
+
+        self.cloudwatch_client
+            .put_metric_data()
+            .namespace("DisasterRecovery")
+            .metric_data(metric)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    // Time a few put/get round-trips in DR to confirm it can actually serve traffic
+    pub fn record_dr_failure(&self) {
+        if self.dr_breaker.record_failure() {
+            error!(
+                "DR region {} failed repeatedly; skipping its remaining checks this invocation",
+                self.target_region
+            );
+        }
+    }
+
+    pub async fn check_dr_latency(&self) -> Result<Option<DrLatency>> {
+        let probe_id = probe_record_id("latency-probe", &self.probe_salt);
+        let mut samples = Vec::with_capacity(DR_LATENCY_PROBES * 2);
+
+        for attempt in 0..DR_LATENCY_PROBES {
+            if self.dr_breaker.is_open() {
+                break;
+            }
+
+            let start = std::time::Instant::now();
+            let put_result = self
+                .dr_dynamo
+                .put_item()
+                .table_name(&self.sentinel_table)
+                .item("id", AttributeValue::S(probe_id.clone()))
+                .item("attempt", AttributeValue::N(attempt.to_string()))
+                .item("source", AttributeValue::S("validator".to_string()))
+                .send()
+                .await;
+
+            match put_result {
+                Ok(_) => {
+                    self.dr_breaker.record_success();
+                    samples.push(start.elapsed().as_millis() as u64);
+                }
+                Err(e) => {
+                    warn!("DR latency probe put failed: {}", e);
+                    self.record_dr_failure();
+                }
+            }
+
+            let start = std::time::Instant::now();
+            let get_result = self
+                .dr_dynamo
+                .get_item()
+                .table_name(&self.sentinel_table)
+                .key("id", AttributeValue::S(probe_id.clone()))
+                .send()
+                .await;
+
+            match get_result {
+                Ok(_) => {
+                    self.dr_breaker.record_success();
+                    samples.push(start.elapsed().as_millis() as u64);
+                }
+                Err(e) => {
+                    warn!("DR latency probe get failed: {}", e);
+                    self.record_dr_failure();
+                }
+            }
+        }
+
+        // Clean up probe record
+        let _ = self
+            .dr_dynamo
+            .delete_item()
+            .table_name(&self.sentinel_table)
+            .key("id", AttributeValue::S(probe_id))
+            .send()
+            .await;
+
+        Ok(summarize_latencies(samples))
+    }
+
+    pub async fn validate_backups(&self) -> Result<BackupStatus> {
+        let _bucket_name = std::env::var("BACKUP_BUCKET")
+            .unwrap_or_else(|_| "dr-demo-backup-bucket-primary".to_string());
+
+        // Check backup metadata
+        let scan_result = self
+            .primary_dynamo
+            .scan()
+            .table_name("dr-backup-metadata")
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:Scan", "dr-backup-metadata")))?;
+
+        let mut last_backup_timestamp = 0i64;
+        let mut oldest_backup_timestamp = i64::MAX;
+        let backup_count = scan_result
+            .items
+            .as_ref()
+            .map(|items| items.len())
+            .unwrap_or(0);
+
+        if let Some(items) = scan_result.items {
+            for item in items {
+                if let Some(timestamp_attr) = item.get("timestamp") {
+                    if let Ok(timestamp_str) = timestamp_attr.as_n() {
+                        if let Ok(timestamp) = timestamp_str.parse::<i64>() {
+                            last_backup_timestamp = last_backup_timestamp.max(timestamp);
+                            oldest_backup_timestamp = oldest_backup_timestamp.min(timestamp);
+                        }
+                    }
+                }
+            }
+        }
+
+        let current_time = Utc::now().timestamp();
+        let last_backup_age_hours = if last_backup_timestamp > 0 {
+            Some((current_time - last_backup_timestamp) as f64 / 3600.0)
+        } else {
+            None
+        };
+
+        let oldest_backup_days = if oldest_backup_timestamp < i64::MAX {
+            Some((current_time - oldest_backup_timestamp) as f64 / 86400.0)
+        } else {
+            None
+        };
+
+        Ok(BackupStatus {
+            last_backup_age_hours,
+            backup_count,
+            oldest_backup_days,
+            backup_check_failed: false,
+        })
+    }
+
+    // Passes when the table's newest completed backup is at most max_age_hours
+    // old. Publishes BackupSlaBreached (1 or 0) per table for alarms.
+    pub async fn check_backup_sla(
+        &self,
+        table_name: &str,
+        max_age_hours: f64,
+    ) -> Result<BackupSla> {
+        let mut newest: Option<i64> = None;
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = self
+                .primary_dynamo
+                .scan()
+                .table_name("dr-backup-metadata")
+                .filter_expression("table_name = :table AND #status = :completed")
+                .expression_attribute_names("#status", "status")
+                .expression_attribute_values(":table", AttributeValue::S(table_name.to_string()))
+                .expression_attribute_values(
+                    ":completed",
+                    AttributeValue::S("completed".to_string()),
+                )
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:Scan", "dr-backup-metadata")))?;
+
+            for item in result.items.unwrap_or_default() {
+                if let Some(timestamp) = metadata_timestamp(&item) {
+                    newest = Some(newest.map_or(timestamp, |newest| newest.max(timestamp)));
+                }
+            }
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        let sla = backup_sla(table_name, max_age_hours, newest, Utc::now().timestamp());
+        if !sla.pass {
+            warn!(
+                "Backup SLA breached for {}: newest backup age {:?} hours, limit {} hours",
+                table_name, sla.newest_backup_age_hours, max_age_hours
+            );
+        }
+
+        if let Err(e) = self
+            .publish_table_metric(
+                "DisasterRecovery",
+                "BackupSlaBreached",
+                table_name,
+                if sla.pass { 0.0 } else { 1.0 },
+                StandardUnit::Count,
+            )
+            .await
+        {
+            error!("Failed to publish backup SLA metric: {}", e);
+        }
+
+        Ok(sla)
+    }
+
+    // Items in the primary table whose `id` has no match in DR
+    pub async fn find_missing_items(
+        &self,
+        table_name: &str,
+        concurrency: usize,
+    ) -> Result<Vec<Item>> {
+        let mut primary_items = Vec::new();
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = self
+                .primary_dynamo
+                .scan()
+                .table_name(table_name)
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:Scan", table_name)))?;
+
+            warn_on_missing_scan_items(&result, table_name);
+
+            let now = Utc::now().timestamp();
+            primary_items.extend(
+                result
+                    .items
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|item| !self.is_expired(item, now)),
+            );
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        let missing = stream::iter(primary_items)
+            .map(|item| async move {
+                if self.dr_breaker.is_open() {
+                    return None;
+                }
+
+                let id = item.get("id")?.as_s().ok()?.clone();
+                let result = self
+                    .dr_dynamo
+                    .get_item()
+                    .table_name(table_name)
+                    .key("id", AttributeValue::S(id.clone()))
+                    .send()
+                    .await;
+
+                match result {
+                    Ok(response) => {
+                        self.dr_breaker.record_success();
+                        response.item.is_none().then_some(item)
+                    }
+                    Err(e) => {
+                        warn!("Error checking item {} in DR: {}", id, e);
+                        self.record_dr_failure();
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(concurrency)
+            .filter_map(|item| async move { item })
+            .collect()
+            .await;
+
+        // Unchecked items are unknown, not present, so don't act on a partial list
+        if self.dr_breaker.is_open() {
+            return Err(anyhow!(
+                "DR region {} is unreachable; stopped checking {} for missing items",
+                self.target_region,
+                table_name
+            ));
+        }
+
+        Ok(missing)
+    }
+
+    pub async fn sync_missing_items(
+        &self,
+        table_name: &str,
+        validation: &TableValidation,
+        options: &SyncOptions<'_>,
+    ) -> Result<TableSync> {
+        let SyncOptions {
+            order_by,
+            concurrency,
+            transactional,
+            version_attribute,
+            max_items,
+        } = *options;
+
+        info!(
+            "Syncing table {} (primary {} items, DR {} items)",
+            table_name, validation.primary_count, validation.dr_count
+        );
+
+        let missing = self.find_missing_items(table_name, concurrency).await?;
+        let (missing, deferred) = cap_sync_items(missing, max_items);
+        let mut sync = TableSync {
+            table_name: table_name.to_string(),
+            items_deferred: deferred,
+            ..TableSync::default()
+        };
+
+        if deferred > 0 {
+            warn!(
+                "Syncing {} of the items missing from DR table {}, {} left for a later sync",
+                max_items, table_name, deferred
+            );
+        }
+
+        // Batches run one after another; writes within a batch run concurrently
+        for batch in plan_sync_batches(missing, order_by) {
+            if transactional {
+                self.transact_put_items(table_name, batch, version_attribute, &mut sync)
+                    .await?;
+                continue;
+            }
+
+            // DR may have written the item since find_missing_items looked
+            let results: Vec<_> = stream::iter(batch)
+                .map(|item| {
+                    let condition = sync_write_condition(&item, version_attribute);
+                    self.dr_dynamo
+                        .put_item()
+                        .table_name(table_name)
+                        .set_item(Some(item))
+                        .condition_expression(condition.expression)
+                        .set_expression_attribute_names(Some(condition.names))
+                        .set_expression_attribute_values(condition.values)
+                        .send()
+                })
+                .buffer_unordered(concurrency)
+                .collect()
+                .await;
+
+            for result in results {
+                match result {
+                    Ok(_) => sync.items_synced += 1,
+                    Err(e)
+                        if matches!(
+                            e.as_service_error(),
+                            Some(PutItemError::ConditionalCheckFailedException(_))
+                        ) =>
+                    {
+                        sync.items_skipped_newer += 1
+                    }
+                    Err(e) => error!("Failed to sync item to DR table {}: {}", table_name, e),
+                }
+            }
+        }
+
+        if sync.items_skipped_newer > 0 {
+            warn!(
+                "Left {} items in DR table {} that are newer than primary's",
+                sync.items_skipped_newer, table_name
+            );
+        }
+
+        Ok(sync)
+    }
+
+    pub async fn sentinel_present(&self, sentinel_id: &str) -> Result<bool> {
+        let result = self
+            .primary_dynamo
+            .get_item()
+            .table_name(&self.sentinel_table)
+            .key("id", AttributeValue::S(sentinel_id.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "dynamodb:GetItem", &self.sentinel_table)))?;
+
+        Ok(result.item.is_some())
+    }
+
+    // Why primary isn't fit to sync from, or None when every check passes
+    pub async fn check_sync_preconditions(
+        &self,
+        preconditions: &SyncPreconditions,
+        validation: &TableValidation,
+    ) -> Option<String> {
+        if let Some(reason) =
+            count_precondition_failure(preconditions, validation.primary_count, validation.dr_count)
+        {
+            return Some(reason);
+        }
+
+        let sentinel_id = preconditions.required_sentinel_id.as_deref()?;
+        match self.sentinel_present(sentinel_id).await {
+            Ok(true) => None,
+            Ok(false) => Some(format!("sentinel {} is missing from primary", sentinel_id)),
+            Err(e) => Some(format!("could not read sentinel {}: {}", sentinel_id, e)),
+        }
+    }
+
+    // A failed transaction writes nothing, so its items count as not synced. An
+    // item newer in DR cancels its whole transaction and counts as skipped.
+    pub async fn transact_put_items(
+        &self,
+        table_name: &str,
+        items: Vec<Item>,
+        version_attribute: Option<&str>,
+        sync: &mut TableSync,
+    ) -> Result<()> {
+        for chunk in items.chunks(TRANSACT_WRITE_MAX_ITEMS) {
+            let transact_items = chunk
+                .iter()
+                .map(|item| {
+                    let condition = sync_write_condition(item, version_attribute);
+                    let put = Put::builder()
+                        .table_name(table_name)
+                        .set_item(Some(item.clone()))
+                        .condition_expression(condition.expression)
+                        .set_expression_attribute_names(Some(condition.names))
+                        .set_expression_attribute_values(condition.values)
+                        .build()?;
+                    Ok(TransactWriteItem::builder().put(put).build())
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            match self
+                .dr_dynamo
+                .transact_write_items()
+                .set_transact_items(Some(transact_items))
+                .send()
+                .await
+            {
+                Ok(_) => sync.items_synced += chunk.len(),
+                Err(e) => {
+                    if let Some(TransactWriteItemsError::TransactionCanceledException(canceled)) =
+                        e.as_service_error()
+                    {
+                        sync.items_skipped_newer += canceled
+                            .cancellation_reasons()
+                            .iter()
+                            .filter(|reason| reason.code() == Some("ConditionalCheckFailed"))
+                            .count();
+                    }
+                    error!(
+                        "Transaction of {} items to DR table {} rolled back: {}",
+                        chunk.len(),
+                        table_name,
+                        map_sdk_error(e, "dynamodb:TransactWriteItems", table_name)
+                    )
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn publish_init_duration(&self, init: InitTiming) -> Result<(), Error> {
+        if !self.metric_filter.allows("InitDuration") {
+            return Ok(());
+        }
+
+        publish_init_duration(&self.cloudwatch_client, env!("CARGO_PKG_NAME"), init).await
+    }
+
+    pub async fn publish_single_metric(
+        &self,
+        namespace: &str,
+        metric_name: &str,
+        value: f64,
+        unit: StandardUnit,
+    ) -> Result<(), Error> {
+        if !self.metric_filter.allows(metric_name) {
+            return Ok(());
+        }
+
+        let timestamp = std::time::SystemTime::now();
+
+        // Create the metric
+        let metric = MetricDatum::builder()
+            .metric_name(metric_name)
+            .value(value)
+            .unit(unit)
+            .timestamp(aws_sdk_cloudwatch::primitives::DateTime::from(timestamp))
+            .build();
+
+        // Send the metric
+        match self
+            .cloudwatch_client
+            .put_metric_data()
+            .namespace(namespace)
+            .metric_data(metric)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Failed to publish metric {}: {}", metric_name, e);
+                Err(Error::from(e))
+            }
+        }
+    }
+
+    pub async fn publish_table_metric(
+        &self,
+        namespace: &str,
+        metric_name: &str,
+        table_name: &str,
+        value: f64,
+        unit: StandardUnit,
+    ) -> Result<(), Error> {
+        if !self.metric_filter.allows(metric_name) {
+            return Ok(());
+        }
+
+        let metric = MetricDatum::builder()
+            .metric_name(metric_name)
+            .dimensions(
+                Dimension::builder()
+                    .name("TableName")
+                    .value(table_name)
+                    .build(),
+            )
+            .value(value)
+            .unit(unit)
+            .timestamp(aws_sdk_cloudwatch::primitives::DateTime::from(
+                std::time::SystemTime::now(),
+            ))
+            .build();
+
+        match self
+            .cloudwatch_client
+            .put_metric_data()
+            .namespace(namespace)
+            .metric_data(metric)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!(
+                    "Failed to publish metric {} for table {}: {}",
+                    metric_name, table_name, e
+                );
+                Err(Error::from(e))
+            }
+        }
+    }
+
+    pub async fn publish_validation_metrics(&self, results: &ValidationResults) -> Result<()> {
+        let namespace = "DisasterRecovery";
+
+        // Publish consistency score metric
+        match self
+            .publish_single_metric(
+                namespace,
+                "ValidationConsistencyScore",
+                results.consistency_score,
+                StandardUnit::Percent,
+            )
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => error!("Failed to publish consistency score metric: {}", e),
+        }
+
+        // Publish mismatches metric
+        match self
+            .publish_single_metric(
+                namespace,
+                "ValidationMismatches",
+                results.mismatches_found as f64,
+                StandardUnit::Count,
+            )
+            .await
+        {
+            Ok(_) => (),
+            Err(e) => error!("Failed to publish mismatches metric: {}", e),
+        }
+
+        if let Err(e) = self
+            .publish_single_metric(
+                namespace,
+                "TablesSkipped",
+                results.tables_skipped as f64,
+                StandardUnit::Count,
+            )
+            .await
+        {
+            error!("Failed to publish tables skipped metric: {}", e);
+        }
+
+        if let Some(smoothed) = results.smoothed_consistency {
+            if let Err(e) = self
+                .publish_single_metric(
+                    namespace,
+                    "SmoothedConsistency",
+                    smoothed,
+                    StandardUnit::Percent,
+                )
+                .await
+            {
+                error!("Failed to publish smoothed consistency metric: {}", e);
+            }
+        }
+
+        // Publish per-table count estimate drift, when it was measured
+        for drift in &results.count_estimate_drift {
+            if let Err(e) = self
+                .publish_table_metric(
+                    namespace,
+                    "CountEstimateDrift",
+                    &drift.table_name,
+                    drift.drift as f64,
+                    StandardUnit::Count,
+                )
+                .await
+            {
+                error!("Failed to publish count estimate drift metric: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Publish a single set of metrics covering every response in a batch
+    pub async fn publish_batch_metrics(&self, responses: &[ValidationResponse]) -> Result<()> {
+        let total_records: usize = responses.iter().map(|r| r.results.records_checked).sum();
+        let total_mismatches: usize = responses.iter().map(|r| r.results.mismatches_found).sum();
+        let total_skipped: usize = responses.iter().map(|r| r.results.tables_skipped).sum();
+
+        let consistency_score = if total_records > 0 {
+            (total_records.saturating_sub(total_mismatches) as f64 / total_records as f64) * 100.0
+        } else {
+            100.0
+        };
+
+        let namespace = "DisasterRecovery";
+
+        if let Err(e) = self
+            .publish_single_metric(
+                namespace,
+                "ValidationConsistencyScore",
+                consistency_score,
+                StandardUnit::Percent,
+            )
+            .await
+        {
+            error!("Failed to publish batch consistency score metric: {}", e);
+        }
+
+        if let Err(e) = self
+            .publish_single_metric(
+                namespace,
+                "ValidationMismatches",
+                total_mismatches as f64,
+                StandardUnit::Count,
+            )
+            .await
+        {
+            error!("Failed to publish batch mismatches metric: {}", e);
+        }
+
+        if let Err(e) = self
+            .publish_single_metric(
+                namespace,
+                "TablesSkipped",
+                total_skipped as f64,
+                StandardUnit::Count,
+            )
+            .await
+        {
+            error!("Failed to publish batch tables skipped metric: {}", e);
+        }
+
+        Ok(())
+    }
+
+    pub fn generate_recommendations(
+        &self,
+        results: &ValidationResults,
+        consistency_threshold: f64,
+    ) -> Vec<String> {
+        let mut recommendations = Vec::new();
+
+        // Check consistency score
+        if results.consistency_score < consistency_threshold {
+            recommendations.push(format!(
+                "Data consistency is below {}% ({:.1}%). Investigate mismatches immediately.",
+                consistency_threshold, results.consistency_score
+            ));
+        }
+
+        for sync in &results.sync_results {
+            if sync.items_skipped_newer > 0 {
+                recommendations.push(format!(
+                    "Sync left {} items in DR table {} that DR wrote more recently than primary. Reconcile them before failing back.",
+                    sync.items_skipped_newer, sync.table_name
+                ));
+            }
+        }
+
+        for sla in results.backup_sla.iter().filter(|sla| !sla.pass) {
+            recommendations.push(match sla.newest_backup_age_hours {
+                Some(age) => format!(
+                    "Newest backup of {} is {:.1} hours old, over its {} hour SLA. Check the backup schedule.",
+                    sla.table_name, age, sla.max_age_hours
+                ),
+                None => format!(
+                    "{} has no completed backup. Check the backup schedule.",
+                    sla.table_name
+                ),
+            });
+        }
+
+        if let Some(report) = results
+            .object_checksums
+            .as_ref()
+            .filter(|report| !report.mismatches.is_empty())
+        {
+            recommendations.push(format!(
+                "{} of {} sampled backup objects are missing or differ in {}. Check S3 replication before restoring from DR.",
+                report.mismatches.len(),
+                report.objects_checked,
+                self.dr_backup_bucket
+            ));
+        }
+
+        for refusal in &results.sync_refused {
+            recommendations.push(format!(
+                "Sync of {} was refused: {}. Verify primary's data before syncing it into DR.",
+                refusal.table_name, refusal.reason
+            ));
+        }
+
+        // Check replication lag
+        if let Some(lag) = results.replication_lag_seconds {
+            if lag > 60 {
+                recommendations.push(format!(
+                    "Replication lag is {} seconds. Consider investigating DynamoDB Global Tables health.",
+                    lag
+                ));
+            }
+        }
+
+        // Check DR latency
+        if let Some(latency) = &results.dr_latency {
+            if latency.p50_ms > self.dr_latency_threshold_ms {
+                recommendations.push(format!(
+                    "DR read/write latency is {}ms (p50, max {}ms), above the {}ms threshold. DR may be too slow to serve production traffic.",
+                    latency.p50_ms, latency.max_ms, self.dr_latency_threshold_ms
+                ));
+            }
+        }
+
+        // Check DR capacity
+        for comparison in results
+            .throughput
+            .iter()
+            .filter(|comparison| comparison.dr_under_provisioned)
+        {
+            let recommendation = match (
+                comparison.primary.read_capacity_units,
+                comparison.primary.write_capacity_units,
+            ) {
+                (Some(read), Some(write)) => format!(
+                    "DR table {} has less capacity than primary. Run update_table with ProvisionedThroughput ReadCapacityUnits={} WriteCapacityUnits={} (or switch it to PAY_PER_REQUEST) before failing over.",
+                    comparison.table_name, read, write
+                ),
+                _ => format!(
+                    "DR table {} is provisioned while primary is on-demand. Run update_table with BillingMode=PAY_PER_REQUEST before failing over.",
+                    comparison.table_name
+                ),
+            };
+            recommendations.push(recommendation);
+        }
+
+        if results.backup_status.backup_check_failed {
+            recommendations.push(
+                "Backup metadata could not be read, so backup age and count are unknown. Check access to dr-backup-metadata.".to_string(),
+            );
+        }
+
+        // Check backup age
+        if let Some(age_hours) = results.backup_status.last_backup_age_hours {
+            if age_hours > 24.0 {
+                recommendations.push(format!(
+                    "Last backup is {:.1} hours old. Consider running a manual backup.",
+                    age_hours
+                ));
+            }
+        }
+
+        // Check backup retention
+        if let Some(oldest_days) = results.backup_status.oldest_backup_days {
+            if oldest_days > 30.0 {
+                recommendations.push(format!(
+                    "Oldest backup is {:.0} days old. Consider reviewing retention policy.",
+                    oldest_days
+                ));
+            }
+        }
+
+        if results.tables_skipped > 0 {
+            let mut skipped: Vec<&String> = results.skipped_reasons.keys().collect();
+            skipped.sort();
+            recommendations.push(format!(
+                "{} of {} tables were skipped ({}); see skipped_reasons. Coverage of this validation is incomplete.",
+                results.tables_skipped,
+                results.tables_skipped + results.tables_validated,
+                skipped.into_iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        if results.dr_unreachable {
+            recommendations.push(format!(
+                "DR region {} stopped responding during validation and its remaining checks were skipped. Check the region's health before relying on it.",
+                self.target_region
+            ));
+        }
+
+        // An empty scan scores 100%, so a table below its expected size is suspect
+        if !results.tables_below_min_items.is_empty() {
+            recommendations.push(format!(
+                "Tables {} returned fewer items than their configured minimum. Check scan permissions and table contents before trusting this validation.",
+                results.tables_below_min_items.join(", ")
+            ));
+        }
+
+        for drift in &results.schema_drift {
+            recommendations.push(format!(
+                "Table {} schema differs between primary and DR: {}. Align the table definitions before relying on DR.",
+                drift.table_name,
+                drift.differences.join("; ")
+            ));
+        }
+
+        for drift in results.baseline_drift.iter().filter(|drift| drift.diverged) {
+            recommendations.push(format!(
+                "Table {} has diverged from DR since the baseline captured at {} (primary-DR skew {} -> {}). Check changes made since then.",
+                drift.table_name, drift.baseline_captured_at, drift.skew_at_baseline, drift.skew_now
+            ));
+        }
+
+        if recommendations.is_empty() {
+            recommendations.push("All validation checks passed. System is healthy.".to_string());
+        }
+
+        recommendations
+    }
+
+    // Durable per-run record of every mismatch, as JSONL under audit/ in AUDIT_BUCKET
+    pub async fn write_audit_log(&self, validations: &[TableValidation]) -> Result<()> {
+        let audit_bucket = match &self.audit_bucket {
+            Some(bucket) => bucket,
+            None => return Ok(()),
+        };
+
+        let now = Utc::now();
+        let records = audit_records(validations, &now.to_rfc3339());
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let key = format!(
+            "audit/{}/validation-{}.jsonl",
+            now.format("%Y/%m/%d"),
+            now.timestamp_millis()
+        );
+
+        self.s3_client
+            .put_object()
+            .bucket(audit_bucket)
+            .key(&key)
+            .content_type("application/x-ndjson")
+            .body(to_jsonl(&records)?.into_bytes().into())
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "s3:PutObject", audit_bucket)))?;
+
+        info!(
+            "Wrote {} mismatches to s3://{}/{}",
+            records.len(),
+            audit_bucket,
+            key
+        );
+        Ok(())
+    }
+
+    pub async fn write_validation_report(&self, results: &ValidationResults) -> Result<()> {
+        let audit_bucket = match &self.audit_bucket {
+            Some(bucket) => bucket,
+            None => return Ok(()),
+        };
+
+        let now = Utc::now();
+        let report = ValidationReport {
+            timestamp: now.to_rfc3339(),
+            tables_validated: results.tables_validated,
+            consistency_score: results.consistency_score,
+        };
+
+        self.s3_client
+            .put_object()
+            .bucket(audit_bucket)
+            .key(report_key(now.timestamp_millis()))
+            .content_type("application/json")
+            .body(serde_json::to_vec(&report)?.into())
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "s3:PutObject", audit_bucket)))?;
+        Ok(())
+    }
+
+    // Consistency scores of the most recent runs, newest first
+    pub async fn recent_consistency_scores(&self, limit: usize) -> Result<Vec<f64>> {
+        let audit_bucket = match &self.audit_bucket {
+            Some(bucket) => bucket,
+            None => return Ok(Vec::new()),
+        };
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Report keys sort newest first, so the first page is all that's needed
+        let result = self
+            .s3_client
+            .list_objects_v2()
+            .bucket(audit_bucket)
+            .prefix("reports/")
+            .max_keys(limit as i32)
+            .send()
+            .await
+            .map_err(|e| anyhow!(map_sdk_error(e, "s3:ListBucket", audit_bucket)))?;
+
+        let mut scores = Vec::new();
+        for key in result
+            .contents
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|object| object.key)
+        {
+            let body = self.get_s3_object(audit_bucket, &key).await?;
+            match serde_json::from_slice::<ValidationReport>(&body) {
+                Ok(report) => scores.push(report.consistency_score),
+                Err(e) => warn!("Ignoring unreadable validation report {}: {}", key, e),
+            }
+        }
+        Ok(scores)
+    }
+
+    pub async fn smoothed_consistency(&self, consistency_score: f64) -> Option<f64> {
+        self.audit_bucket.as_ref()?;
+
+        let mut scores = vec![consistency_score];
+        match self
+            .recent_consistency_scores(self.smoothing_window - 1)
+            .await
+        {
+            Ok(previous) => scores.extend(previous),
+            Err(e) => warn!("Failed to read previous validation reports: {}", e),
+        }
+        weighted_moving_average(&scores)
+    }
+
+    pub async fn run_validation(
+        &self,
+        request: &ValidationRequest,
+        publish_metrics: bool,
+        default_tables: &[String],
+    ) -> Result<ValidationResponse, Error> {
+        let validation_type = request
+            .validation_type
+            .clone()
+            .unwrap_or_else(|| "incremental".to_string());
+        let action = request.action.as_deref().unwrap_or("validate");
+        let min_expected_items = request.min_expected_items.clone().unwrap_or_default();
+        let exclude_from_score = request.exclude_from_score.clone().unwrap_or_default();
+        let sync_concurrency = request
+            .sync_concurrency
+            .unwrap_or(DEFAULT_SYNC_CONCURRENCY)
+            .max(1);
+        let sync_options = SyncOptions {
+            order_by: request.sync_order_by.as_deref(),
+            concurrency: sync_concurrency,
+            transactional: request.sync_transactional,
+            version_attribute: request.sync_version_attribute.as_deref(),
+            max_items: request.max_sync_items.unwrap_or(DEFAULT_MAX_SYNC_ITEMS),
+        };
+        let consistency_threshold = match consistency_threshold(request.consistency_threshold) {
+            Ok(threshold) => threshold,
+            Err(reason) => {
+                warn!("Rejecting validation request: {}", reason);
+                return Ok(rejected_response(validation_type, reason));
+            }
+        };
+
+        // An export holds a single table, so export mode needs it named
+        if request.exports.is_some() && request.table_name.is_none() {
+            return Err(Error::from("Validating from exports requires table_name"));
+        }
+
+        // Determine which tables to validate
+        let all_tables = if let Some(table_name) = &request.table_name {
+            vec![table_name.clone()]
+        } else {
+            default_tables.to_vec()
+        };
+
+        let (tables_to_validate, progress) = if request.resume {
+            let checkpoint = match self.load_checkpoint().await {
+                Ok(checkpoint) => checkpoint,
+                Err(e) => {
+                    warn!("Failed to load validation checkpoint, starting over: {}", e);
+                    None
+                }
+            };
+            let tables_per_run = request
+                .tables_per_run
+                .unwrap_or(DEFAULT_TABLES_PER_RUN)
+                .max(1);
+            let (start, end) = checkpoint_window(&all_tables, checkpoint.as_ref(), tables_per_run);
+            info!(
+                "Resuming validation at table {} of {}, validating {}",
+                start,
+                all_tables.len(),
+                end - start
+            );
+
+            let progress = ValidationProgress {
+                tables_done: end,
+                tables_total: all_tables.len(),
+                complete: end == all_tables.len(),
+            };
+            (all_tables[start..end].to_vec(), Some(progress))
+        } else {
+            (all_tables.clone(), None)
+        };
+
+        // Perform validation
+        let mut total_mismatches = 0;
+        let mut total_records = 0;
+        let mut validations = Vec::new();
+        let mut tables_below_min_items = Vec::new();
+        let mut excluded_from_score = Vec::new();
+        let mut sync_refused = Vec::new();
+        let mut sync_results = Vec::new();
+        let mut skipped_reasons = HashMap::new();
+
+        if action == "capture_baseline" {
+            for table_name in &tables_to_validate {
+                if let Err(e) = self.capture_baseline(table_name).await {
+                    error!("Failed to capture baseline for {}: {}", table_name, e);
+                }
+            }
+        }
+
+        for table_name in &tables_to_validate {
+            let validation = match &request.exports {
+                Some(exports) => self.validate_table_exports(table_name, exports).await,
+                None => {
+                    self.validate_table_data(table_name, request.compare_target, request.deep)
+                        .await
+                }
+            };
+
+            match validation {
+                Ok(validation) => {
+                    if let Some(&min_items) = min_expected_items.get(table_name) {
+                        if validation.primary_count < min_items {
+                            warn!(
+                                "Table {} has {} items, below the expected minimum of {}",
+                                table_name, validation.primary_count, min_items
+                            );
+                            tables_below_min_items.push(table_name.clone());
+                        }
+                    }
+
+                    let mismatches = validation.primary_count.abs_diff(validation.dr_count)
+                        + validation.sample_mismatches.len();
+
+                    if exclude_from_score.contains(table_name) {
+                        info!(
+                            "Table {} has {} mismatches, excluded from the consistency score",
+                            table_name, mismatches
+                        );
+                        excluded_from_score.push(ExcludedTable {
+                            table_name: table_name.clone(),
+                            records_checked: validation.primary_count,
+                            mismatches_found: mismatches,
+                        });
+                    } else {
+                        total_records += validation.primary_count;
+                        total_mismatches += mismatches;
+                    }
+
+                    // Sync only repairs DR from the live tables; backup and
+                    // export comparisons are report-only
+                    if action == "sync"
+                        && mismatches > 0
+                        && request.compare_target == CompareTarget::DrTable
+                        && request.exports.is_none()
+                    {
+                        if let Some(reason) = self
+                            .check_sync_preconditions(&request.sync_preconditions, &validation)
+                            .await
+                        {
+                            warn!("Refusing to sync table {}: {}", table_name, reason);
+                            sync_refused.push(SyncRefusal {
+                                table_name: table_name.clone(),
+                                reason,
+                            });
+                        } else {
+                            let synced = self
+                                .sync_missing_items(table_name, &validation, &sync_options)
+                                .await;
+                            if let Ok(synced) = synced {
+                                info!(
+                                    "Synced {} items for table {} ({} skipped as newer in DR)",
+                                    synced.items_synced, table_name, synced.items_skipped_newer
+                                );
+                                sync_results.push(synced);
+                            }
+                        }
+                    }
+
+                    validations.push(validation);
+                }
+                Err(e) => {
+                    error!("Failed to validate table {}: {}", table_name, e);
+                    skipped_reasons.insert(table_name.clone(), format!("validation failed: {}", e));
+                }
+            }
+        }
+
+        // A finished pass starts the next one from the first table
+        if let Some(progress) = &progress {
+            let checkpoint = ValidationCheckpoint {
+                tables: all_tables.clone(),
+                next_index: if progress.complete {
+                    0
+                } else {
+                    progress.tables_done
+                },
+            };
+            if let Err(e) = self.save_checkpoint(&checkpoint).await {
+                error!("Failed to save validation checkpoint: {}", e);
+            }
+        }
+
+        let mut count_estimate_drift = Vec::new();
+        if request.check_count_drift.unwrap_or(false) {
+            for table_name in &tables_to_validate {
+                match self.compute_count_drift(table_name).await {
+                    Ok(drift) => {
+                        info!(
+                            "Table {}: describe_table count {}, scan count {}, drift {}",
+                            table_name, drift.describe_table_count, drift.scan_count, drift.drift
+                        );
+                        count_estimate_drift.push(drift);
+                    }
+                    Err(e) => error!("Failed to compute count drift for {}: {}", table_name, e),
+                }
+            }
+        }
+
+        let mut backup_sla = Vec::new();
+        for (table_name, &max_age_hours) in request.backup_sla_hours.iter().flatten() {
+            match self.check_backup_sla(table_name, max_age_hours).await {
+                Ok(sla) => backup_sla.push(sla),
+                Err(e) => error!("Failed to check backup SLA for {}: {}", table_name, e),
+            }
+        }
+
+        let mut schema_drift = Vec::new();
+        for table_name in &tables_to_validate {
+            match self.compare_schema(table_name).await {
+                Ok(differences) if !differences.is_empty() => {
+                    warn!("Schema drift in {}: {:?}", table_name, differences);
+                    schema_drift.push(SchemaDrift {
+                        table_name: table_name.clone(),
+                        differences,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => error!("Failed to compare schema for {}: {}", table_name, e),
+            }
+        }
+
+        let mut baseline_drift = Vec::new();
+        if request.compare_baseline {
+            for table_name in &tables_to_validate {
+                match self.compare_with_baseline(table_name).await {
+                    Ok(Some(drift)) => {
+                        if drift.diverged {
+                            warn!(
+                                "Table {} diverged since baseline {}: skew {} -> {}",
+                                table_name,
+                                drift.baseline_captured_at,
+                                drift.skew_at_baseline,
+                                drift.skew_now
+                            );
+                        }
+                        baseline_drift.push(drift);
+                    }
+                    Ok(None) => info!("No baseline captured for {}", table_name),
+                    Err(e) => error!("Failed to compare {} with its baseline: {}", table_name, e),
+                }
+            }
+        }
+
+        let mut throughput = Vec::new();
+        for table_name in &tables_to_validate {
+            match self.compare_throughput(table_name).await {
+                Ok(comparison) => {
+                    if comparison.dr_under_provisioned {
+                        warn!(
+                            "DR table {} is under-provisioned: {:?} vs primary {:?}",
+                            table_name, comparison.dr, comparison.primary
+                        );
+                    }
+                    throughput.push(comparison);
+                }
+                Err(e) => error!("Failed to compare throughput for {}: {}", table_name, e),
+            }
+        }
+
+        // Check replication lag, once or over the monitoring window
+        let lag_samples = match request.lag_monitor_seconds.filter(|seconds| *seconds > 0) {
+            Some(seconds) => {
+                let window = lag_monitor_window(
+                    seconds,
+                    Utc::now().timestamp_millis() as u64,
+                    request.deadline_ms,
+                );
+                let interval = request
+                    .lag_sample_interval_seconds
+                    .unwrap_or(DEFAULT_LAG_SAMPLE_INTERVAL_SECONDS)
+                    .max(1);
+                self.monitor_replication_lag(window, Duration::from_secs(interval), publish_metrics)
+                    .await
+            }
+            None => Vec::new(),
+        };
+        let replication_lag = match lag_samples.last() {
+            Some(sample) => sample.lag_seconds,
+            None => self.check_replication_lag().await.unwrap_or(None),
+        };
+
+        // Check DR read/write latency
+        let dr_latency = self.check_dr_latency().await.unwrap_or(None);
+
+        // Validate backups
+        let backup_status = self.validate_backups().await.unwrap_or_else(|e| {
+            error!("Failed to validate backups: {}", e);
+            BackupStatus::check_failed()
+        });
+
+        let object_checksums = if request.verify_object_checksums {
+            let sample_size = request
+                .checksum_sample_size
+                .unwrap_or(DEFAULT_CHECKSUM_SAMPLE_SIZE)
+                .max(1);
+            match self.compare_object_checksums(sample_size).await {
+                Ok(report) => Some(report),
+                Err(e) => {
+                    error!("Failed to compare backup object checksums: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Calculate consistency score
+        let consistency_score = if total_records > 0 {
+            ((total_records - total_mismatches) as f64 / total_records as f64) * 100.0
+        } else {
+            100.0
+        };
+        let smoothed_consistency = self.smoothed_consistency(consistency_score).await;
+
+        let results = ValidationResults {
+            tables_validated: validations.len(),
+            tables_skipped: skipped_reasons.len(),
+            skipped_reasons,
+            records_checked: total_records,
+            mismatches_found: total_mismatches,
+            replication_lag_seconds: replication_lag,
+            lag_samples,
+            dr_latency,
+            backup_status,
+            backup_sla,
+            consistency_score,
+            smoothed_consistency,
+            tables_below_min_items,
+            count_estimate_drift,
+            schema_drift,
+            excluded_from_score,
+            throughput,
+            sync_refused,
+            sync_results,
+            dr_unreachable: self.dr_breaker.is_open(),
+            baseline_drift,
+            object_checksums,
+            progress,
+            table_details: request.include_table_details.then(|| {
+                validations
+                    .iter()
+                    .map(|validation| TableDetail {
+                        table_name: validation.table_name.clone(),
+                        primary_count: validation.primary_count,
+                        dr_count: validation.dr_count,
+                        sample_mismatches: validation.sample_mismatches.clone(),
+                    })
+                    .collect()
+            }),
+        };
+
+        // Publish metrics (batches publish once for all entries instead)
+        if publish_metrics {
+            if let Err(e) = self.publish_validation_metrics(&results).await {
+                error!("Failed to publish metrics: {}", e);
+            }
+        }
+
+        // Generate recommendations
+        let recommendations = self.generate_recommendations(&results, consistency_threshold);
+
+        // Log validation summary
+        info!(
+            "Validation complete: {} tables ({} skipped), {} records, {:.1}% consistency",
+            results.tables_validated,
+            results.tables_skipped,
+            results.records_checked,
+            results.consistency_score
+        );
+
+        for validation in &validations {
+            if !validation.sample_mismatches.is_empty() {
+                warn!(
+                    "Table {} has mismatches: {:?}",
+                    validation.table_name, validation.sample_mismatches
+                );
+            }
+        }
+
+        if let Err(e) = self.write_audit_log(&validations).await {
+            error!("Failed to write mismatch audit log: {}", e);
+        }
+
+        // Batch entries validate different table sets, so only standalone runs
+        // feed the moving average
+        if publish_metrics {
+            if let Err(e) = self.write_validation_report(&results).await {
+                error!("Failed to write validation report: {}", e);
+            }
+        }
+
+        let response = ValidationResponse {
+            version: version(),
+            init: None,
+            status: validation_status(
+                results.consistency_score,
+                request.failed_threshold.unwrap_or(DEFAULT_FAILED_THRESHOLD),
+                consistency_threshold,
+                !results.tables_below_min_items.is_empty() || results.dr_unreachable,
+            ),
+            validation_type,
+            timestamp: Utc::now().to_rfc3339(),
+            results,
+            recommendations,
+            config_trace: request.debug.then(|| {
+                let mut config_trace = self.config_trace.clone();
+                config_trace.record(
+                    "table_name",
+                    if request.table_name.is_some() {
+                        ConfigSource::Request
+                    } else {
+                        ConfigSource::of_env("CONFIG_TABLE")
+                    },
+                );
+                config_trace.record(
+                    "action",
+                    ConfigSource::of_request(&request.action, ConfigSource::Default),
+                );
+                config_trace.into_map()
+            }),
+        };
+
+        if response.status != ValidationStatus::Healthy {
+            let detail_type = match response.status {
+                ValidationStatus::Failed => "Validation Failed",
+                _ => "Validation Degraded",
+            };
+            self.events
+                .emit_event(
+                    detail_type,
+                    &serde_json::json!({
+                        "source_region": self.source_region,
+                        "target_region": self.target_region,
+                        "consistency_score": response.results.consistency_score,
+                        "mismatches_found": response.results.mismatches_found,
+                        "tables_below_min_items": response.results.tables_below_min_items,
+                        "timestamp": response.timestamp,
+                    }),
+                )
+                .await;
+        }
+
+        Ok(response)
+    }
+}
+
+pub fn count_precondition_failure(
+    preconditions: &SyncPreconditions,
+    primary_count: usize,
+    dr_count: usize,
+) -> Option<String> {
+    if let Some(min_items) = preconditions.min_primary_items {
+        if primary_count < min_items {
+            return Some(format!(
+                "primary has {} items, below the minimum of {}",
+                primary_count, min_items
+            ));
+        }
+    }
+
+    if let Some(min_ratio) = preconditions.min_primary_to_dr_ratio {
+        if dr_count > 0 && (primary_count as f64) < dr_count as f64 * min_ratio {
+            return Some(format!(
+                "primary has {} items against {} in DR, below the minimum ratio of {}",
+                primary_count, dr_count, min_ratio
+            ));
+        }
+    }
+
+    None
+}
+
+pub fn key_schema_summary(key_schema: &[KeySchemaElement]) -> Vec<String> {
+    let mut keys: Vec<String> = key_schema
+        .iter()
+        .map(|key| format!("{}:{}", key.attribute_name(), key.key_type().as_str()))
+        .collect();
+    keys.sort();
+    keys
+}
+
+// Tables created before on-demand existed report no billing mode summary
+pub fn throughput_snapshot(table: &TableDescription) -> ThroughputSnapshot {
+    let billing_mode = table
+        .billing_mode_summary()
+        .and_then(|summary| summary.billing_mode())
+        .cloned()
+        .unwrap_or(BillingMode::Provisioned);
+    let throughput = table
+        .provisioned_throughput()
+        .filter(|_| billing_mode == BillingMode::Provisioned);
+
+    ThroughputSnapshot {
+        billing_mode: billing_mode.as_str().to_string(),
+        read_capacity_units: throughput.and_then(|t| t.read_capacity_units()),
+        write_capacity_units: throughput.and_then(|t| t.write_capacity_units()),
+    }
+}
+
+// On-demand DR can absorb anything; provisioned DR must at least match primary,
+// and can never be assumed to match an on-demand primary
+pub fn dr_under_provisioned(primary: &ThroughputSnapshot, dr: &ThroughputSnapshot) -> bool {
+    if dr.billing_mode == BillingMode::PayPerRequest.as_str() {
+        return false;
+    }
+    if primary.billing_mode == BillingMode::PayPerRequest.as_str() {
+        return true;
+    }
+
+    dr.read_capacity_units < primary.read_capacity_units
+        || dr.write_capacity_units < primary.write_capacity_units
+}
+
+pub fn diff_table_schemas(primary: &TableDescription, dr: &TableDescription) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    let primary_keys = key_schema_summary(primary.key_schema());
+    let dr_keys = key_schema_summary(dr.key_schema());
+    if primary_keys != dr_keys {
+        differences.push(format!(
+            "key schema differs (primary {:?}, DR {:?})",
+            primary_keys, dr_keys
+        ));
+    }
+
+    let attributes = |table: &TableDescription| {
+        let mut attributes: Vec<String> = table
+            .attribute_definitions()
+            .iter()
+            .map(|a| format!("{}:{}", a.attribute_name(), a.attribute_type().as_str()))
+            .collect();
+        attributes.sort();
+        attributes
+    };
+    let (primary_attributes, dr_attributes) = (attributes(primary), attributes(dr));
+    if primary_attributes != dr_attributes {
+        differences.push(format!(
+            "attribute definitions differ (primary {:?}, DR {:?})",
+            primary_attributes, dr_attributes
+        ));
+    }
+
+    // GSI name -> (sorted key schema, projection type)
+    let indexes = |table: &TableDescription| {
+        table
+            .global_secondary_indexes()
+            .iter()
+            .filter_map(|index| {
+                let projection = index
+                    .projection()
+                    .and_then(|p| p.projection_type())
+                    .map(|p| p.as_str().to_string());
+                Some((
+                    index.index_name()?.to_string(),
+                    (key_schema_summary(index.key_schema()), projection),
+                ))
+            })
+            .collect::<std::collections::BTreeMap<_, _>>()
+    };
+    let (primary_indexes, dr_indexes) = (indexes(primary), indexes(dr));
+
+    for (name, definition) in &primary_indexes {
+        match dr_indexes.get(name) {
+            None => differences.push(format!("GSI {} exists in primary but not in DR", name)),
+            Some(dr_definition) if dr_definition != definition => differences.push(format!(
+                "GSI {} differs (primary {:?}, DR {:?})",
+                name, definition, dr_definition
+            )),
+            Some(_) => {}
+        }
+    }
+    for name in dr_indexes.keys() {
+        if !primary_indexes.contains_key(name) {
+            differences.push(format!("GSI {} exists in DR but not in primary", name));
+        }
+    }
+
+    differences
+}
+
+// TTL attributes hold epoch seconds; items without one never expire
+pub fn item_expired(item: &Item, ttl_attribute: &str, now: i64) -> bool {
+    item.get(ttl_attribute)
+        .and_then(|value| value.as_n().ok())
+        .and_then(|value| value.parse::<f64>().ok())
+        .is_some_and(|expires_at| expires_at < now as f64)
+}
+
+// Stable text form of an attribute value: map keys and set members sorted
+// Backup metadata stores epoch seconds as a string; older rows used a number
+pub fn metadata_timestamp(item: &Item) -> Option<i64> {
+    match item.get("timestamp")? {
+        AttributeValue::S(value) | AttributeValue::N(value) => value.parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn backup_sla(
+    table_name: &str,
+    max_age_hours: f64,
+    newest_backup_timestamp: Option<i64>,
+    now: i64,
+) -> BackupSla {
+    let newest_backup_age_hours =
+        newest_backup_timestamp.map(|timestamp| (now - timestamp) as f64 / 3600.0);
+
+    BackupSla {
+        table_name: table_name.to_string(),
+        max_age_hours,
+        newest_backup_age_hours,
+        pass: newest_backup_age_hours.is_some_and(|age| age <= max_age_hours),
+    }
+}
+
+pub fn canonical_value(value: &AttributeValue) -> String {
+    let sorted = |mut values: Vec<String>| {
+        values.sort();
+        values.join(",")
+    };
+
+    match value {
+        AttributeValue::S(s) => format!("S:{:?}", s),
+        AttributeValue::N(n) => format!("N:{}", n),
+        AttributeValue::B(b) => format!("B:{:?}", b.as_ref()),
+        AttributeValue::Bool(b) => format!("BOOL:{}", b),
+        AttributeValue::Null(_) => "NULL".to_string(),
+        AttributeValue::Ss(values) => format!("SS:[{}]", sorted(values.clone())),
+        AttributeValue::Ns(values) => format!("NS:[{}]", sorted(values.clone())),
+        AttributeValue::Bs(values) => format!(
+            "BS:[{}]",
+            sorted(values.iter().map(|b| format!("{:?}", b.as_ref())).collect())
+        ),
+        AttributeValue::L(values) => format!(
+            "L:[{}]",
+            values
+                .iter()
+                .map(canonical_value)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        AttributeValue::M(map) => format!("M:{}", canonical_item(map)),
+        other => format!("{:?}", other),
+    }
+}
+
+pub fn canonical_item(item: &Item) -> String {
+    let mut attributes: Vec<_> = item
+        .iter()
+        .map(|(name, value)| format!("{:?}={}", name, canonical_value(value)))
+        .collect();
+    attributes.sort();
+    format!("{{{}}}", attributes.join(","))
+}
+
+// FNV-1a of the canonical form. Table hashes add these up so that scan order
+// doesn't matter.
+pub fn item_hash(item: &Item) -> u64 {
+    canonical_item(item)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+pub fn baseline_to_item(baseline: &TableBaseline) -> Item {
+    HashMap::from([
+        (
+            "backup_id".to_string(),
+            AttributeValue::S(format!("{}{}", BASELINE_ID_PREFIX, baseline.table_name)),
+        ),
+        (
+            "table_name".to_string(),
+            AttributeValue::S(baseline.table_name.clone()),
+        ),
+        (
+            "primary_count".to_string(),
+            AttributeValue::N(baseline.primary_count.to_string()),
+        ),
+        (
+            "dr_count".to_string(),
+            AttributeValue::N(baseline.dr_count.to_string()),
+        ),
+        (
+            "primary_hash".to_string(),
+            AttributeValue::S(baseline.primary_hash.clone()),
+        ),
+        (
+            "dr_hash".to_string(),
+            AttributeValue::S(baseline.dr_hash.clone()),
+        ),
+        (
+            "captured_at".to_string(),
+            AttributeValue::S(baseline.captured_at.clone()),
+        ),
+    ])
+}
+
+pub fn baseline_from_item(item: &Item) -> Option<TableBaseline> {
+    let string = |name: &str| item.get(name)?.as_s().ok().cloned();
+    let count = |name: &str| item.get(name)?.as_n().ok()?.parse().ok();
+
+    Some(TableBaseline {
+        table_name: string("table_name")?,
+        primary_count: count("primary_count")?,
+        dr_count: count("dr_count")?,
+        primary_hash: string("primary_hash")?,
+        dr_hash: string("dr_hash")?,
+        captured_at: string("captured_at")?,
+    })
+}
+
+// Sentinel test record ids: `<kind>-<salt>-<uuid>`. Millisecond timestamps
+// collided between concurrent runs, letting one read the other's record.
+pub fn probe_record_id(kind: &str, salt: &str) -> String {
+    format!("{}-{}-{}", kind, salt, Uuid::new_v4().simple())
+}
+
+pub fn checkpoint_to_item(checkpoint_id: &str, checkpoint: &ValidationCheckpoint) -> Item {
+    HashMap::from([
+        (
+            "backup_id".to_string(),
+            AttributeValue::S(checkpoint_id.to_string()),
+        ),
+        (
+            "tables".to_string(),
+            AttributeValue::L(
+                checkpoint
+                    .tables
+                    .iter()
+                    .map(|table| AttributeValue::S(table.clone()))
+                    .collect(),
+            ),
+        ),
+        (
+            "next_index".to_string(),
+            AttributeValue::N(checkpoint.next_index.to_string()),
+        ),
+        (
+            "updated_at".to_string(),
+            AttributeValue::S(Utc::now().to_rfc3339()),
+        ),
+    ])
+}
+
+pub fn checkpoint_from_item(item: &Item) -> Option<ValidationCheckpoint> {
+    let tables = item
+        .get("tables")?
+        .as_l()
+        .ok()?
+        .iter()
+        .map(|table| table.as_s().ok().cloned())
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(ValidationCheckpoint {
+        tables,
+        next_index: item.get("next_index")?.as_n().ok()?.parse().ok()?,
+    })
+}
+
+// The [start, end) slice of `tables` this run validates: from the checkpoint
+// when it was taken over the same tables, otherwise from the beginning
+pub fn checkpoint_window(
+    tables: &[String],
+    checkpoint: Option<&ValidationCheckpoint>,
+    tables_per_run: usize,
+) -> (usize, usize) {
+    let start = checkpoint
+        .filter(|checkpoint| checkpoint.tables == tables && checkpoint.next_index < tables.len())
+        .map_or(0, |checkpoint| checkpoint.next_index);
+    (start, (start + tables_per_run).min(tables.len()))
+}
+
+// Diverged when the count skew moved, or when the two sides held identical
+// content at the baseline but no longer do
+pub fn baseline_drift(baseline: &TableBaseline, current: &TableBaseline) -> BaselineDrift {
+    let skew = |snapshot: &TableBaseline| snapshot.primary_count as i64 - snapshot.dr_count as i64;
+    let skew_at_baseline = skew(baseline);
+    let skew_now = skew(current);
+    let matched_at_baseline = baseline.primary_hash == baseline.dr_hash;
+    let matches_now = current.primary_hash == current.dr_hash;
+
+    BaselineDrift {
+        table_name: current.table_name.clone(),
+        baseline_captured_at: baseline.captured_at.clone(),
+        primary_count_change: current.primary_count as i64 - baseline.primary_count as i64,
+        dr_count_change: current.dr_count as i64 - baseline.dr_count as i64,
+        skew_at_baseline,
+        skew_now,
+        diverged: skew_now != skew_at_baseline || (matched_at_baseline && !matches_now),
+    }
+}
+
+// The requested monitoring time, cut short so the run still finishes before
+// the invocation's deadline
+pub fn lag_monitor_window(
+    requested_seconds: u64,
+    now_ms: u64,
+    deadline_ms: Option<u64>,
+) -> Duration {
+    let requested = Duration::from_secs(requested_seconds);
+    match deadline_ms {
+        Some(deadline_ms) => {
+            let remaining = Duration::from_millis(deadline_ms.saturating_sub(now_ms));
+            requested.min(remaining.saturating_sub(LAG_MONITOR_RESERVE))
+        }
+        None => requested,
+    }
+}
+
+// The object's checksum prefixed with its algorithm, or its ETag when it was
+// uploaded without one
+pub fn object_fingerprint(output: &GetObjectAttributesOutput) -> String {
+    let checksum = output.checksum().and_then(|checksum| {
+        [
+            ("crc32", checksum.checksum_crc32()),
+            ("crc32c", checksum.checksum_crc32_c()),
+            ("crc64nvme", checksum.checksum_crc64_nvme()),
+            ("sha1", checksum.checksum_sha1()),
+            ("sha256", checksum.checksum_sha256()),
+        ]
+        .into_iter()
+        .find_map(|(algorithm, value)| Some(format!("{}:{}", algorithm, value?)))
+    });
+
+    checksum.unwrap_or_else(|| format!("etag:{}", output.e_tag().unwrap_or_default()))
+}
+
+// Why a backup object's DR copy doesn't match, given each side's fingerprint
+// (None when the object doesn't exist there)
+pub fn checksum_mismatch(primary: Option<&str>, dr: Option<&str>) -> Option<&'static str> {
+    match (primary, dr) {
+        (None, _) => Some("deleted from primary bucket during the check"),
+        (Some(_), None) => Some("missing from DR bucket"),
+        (Some(primary), Some(dr)) if primary != dr => Some("checksum differs in DR bucket"),
+        _ => None,
+    }
+}
+
+// "s3://bucket/prefix" as (bucket, prefix without a trailing slash)
+pub fn parse_s3_uri(uri: &str) -> Option<(String, String)> {
+    let (bucket, prefix) = uri.strip_prefix("s3://")?.split_once('/')?;
+    let prefix = prefix.trim_end_matches('/');
+    if bucket.is_empty() || prefix.is_empty() {
+        return None;
+    }
+    Some((bucket.to_string(), prefix.to_string()))
+}
+
+// item_expired for an item in DynamoDB JSON, as found in exports
+pub fn export_item_expired(item: &serde_json::Value, ttl_attribute: &str, now: i64) -> bool {
+    item[ttl_attribute]["N"]
+        .as_str()
+        .and_then(|value| value.parse::<f64>().ok())
+        .is_some_and(|expires_at| expires_at < now as f64)
+}
+
+// Every item whose hash is missing on one side or differs, in key order
+pub fn deep_mismatches(primary: &HashMap<String, u64>, dr: &HashMap<String, u64>) -> Vec<Mismatch> {
+    let mut keys: Vec<&String> = primary.keys().chain(dr.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let reason = match (primary.get(key), dr.get(key)) {
+                (Some(_), None) => "not found in DR",
+                (None, Some(_)) => "not found in primary",
+                (Some(a), Some(b)) if a != b => "content differs",
+                _ => return None,
+            };
+            Some(Mismatch {
+                key: key.clone(),
+                reason: reason.to_string(),
+            })
+        })
+        .collect()
+}
+
+// Scored like a live run: the count difference plus a sample of the items
+// missing from or differing in DR, taken in key order
+pub fn compare_exports(table_name: &str, primary: &ExportData, dr: &ExportData) -> TableValidation {
+    let mut keys: Vec<&String> = primary.items.keys().collect();
+    keys.sort();
+
+    let mismatches: Vec<Mismatch> = keys
+        .into_iter()
+        .filter_map(|key| {
+            let reason = match dr.items.get(key) {
+                None => "not found in DR export",
+                Some(item) if *item != primary.items[key] => "differs in DR export",
+                Some(_) => return None,
+            };
+            Some(Mismatch {
+                key: key.clone(),
+                reason: reason.to_string(),
+            })
+        })
+        .collect();
+
+    if mismatches.len() > EXPORT_MISMATCH_SAMPLE {
+        info!(
+            "Table {}: {} items missing or different in the DR export, reporting the first {}",
+            table_name,
+            mismatches.len(),
+            EXPORT_MISMATCH_SAMPLE
+        );
+    }
+
+    TableValidation {
+        table_name: table_name.to_string(),
+        primary_count: primary.count,
+        dr_count: dr.count,
+        sample_mismatches: mismatches
+            .into_iter()
+            .take(EXPORT_MISMATCH_SAMPLE)
+            .collect(),
+    }
+}
+
+// Inverted millis, so an ascending S3 listing returns the newest report first
+pub fn report_key(timestamp_millis: i64) -> String {
+    format!("reports/{:019}.json", i64::MAX - timestamp_millis)
+}
+
+// Linearly weighted: with n scores, newest first, the newest counts n times and
+// the oldest once
+pub fn weighted_moving_average(scores: &[f64]) -> Option<f64> {
+    if scores.is_empty() {
+        return None;
+    }
+
+    let n = scores.len();
+    let weighted: f64 = scores
+        .iter()
+        .enumerate()
+        .map(|(i, score)| score * (n - i) as f64)
+        .sum();
+    let total_weight = (n * (n + 1) / 2) as f64;
+    Some(weighted / total_weight)
+}
+
+pub fn audit_records(validations: &[TableValidation], timestamp: &str) -> Vec<AuditRecord> {
+    let mut records = Vec::new();
+
+    for validation in validations {
+        if validation.primary_count != validation.dr_count {
+            records.push(AuditRecord {
+                table_name: validation.table_name.clone(),
+                key: None,
+                reason: format!(
+                    "item count differs: primary {}, target {}",
+                    validation.primary_count, validation.dr_count
+                ),
+                timestamp: timestamp.to_string(),
+            });
+        }
+
+        for mismatch in &validation.sample_mismatches {
+            records.push(AuditRecord {
+                table_name: validation.table_name.clone(),
+                key: Some(mismatch.key.clone()),
+                reason: mismatch.reason.clone(),
+                timestamp: timestamp.to_string(),
+            });
+        }
+    }
+
+    records
+}
+
+pub fn to_jsonl(records: &[AuditRecord]) -> Result<String> {
+    let mut output = String::new();
+    for record in records {
+        output.push_str(&serde_json::to_string(record)?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+pub fn summarize_latencies(mut samples: Vec<u64>) -> Option<DrLatency> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    samples.sort_unstable();
+
+    Some(DrLatency {
+        samples: samples.len(),
+        p50_ms: samples[(samples.len() - 1) / 2],
+        max_ms: samples[samples.len() - 1],
+    })
+}
+
+// Config table rows look like {"table_name": S, "validate": BOOL, "backup": BOOL};
+// a missing flag means the table is included
+pub fn sync_order_key(item: &Item, attribute: &str) -> SyncOrderKey {
+    match item.get(attribute) {
+        Some(AttributeValue::N(n)) => n
+            .parse()
+            .map(SyncOrderKey::Number)
+            .unwrap_or_else(|_| SyncOrderKey::Text(n.clone())),
+        Some(AttributeValue::S(s)) => SyncOrderKey::Text(s.clone()),
+        _ => SyncOrderKey::Missing,
+    }
+}
+
+// Without an ordering hint everything goes in one unordered batch. With one,
+// items are sorted by the attribute and grouped by equal values, so each group
+// is written only after the groups before it.
+// Sync writes only where DR has no item with this id, or, given a version
+// attribute, where DR's value is older than primary's. DR items lacking the
+// attribute are left alone.
+pub fn sync_write_condition(item: &Item, version_attribute: Option<&str>) -> WriteCondition {
+    let mut names = HashMap::from([("#id".to_string(), "id".to_string())]);
+
+    match version_attribute.and_then(|attribute| Some((attribute, item.get(attribute)?))) {
+        Some((attribute, version)) => {
+            names.insert("#version".to_string(), attribute.to_string());
+            WriteCondition {
+                expression: "attribute_not_exists(#id) OR #version < :version".to_string(),
+                names,
+                values: Some(HashMap::from([(":version".to_string(), version.clone())])),
+            }
+        }
+        None => WriteCondition {
+            expression: "attribute_not_exists(#id)".to_string(),
+            names,
+            values: None,
+        },
+    }
+}
+
+// The first max items in id order, and how many were left out
+pub fn cap_sync_items(mut items: Vec<Item>, max: usize) -> (Vec<Item>, usize) {
+    if items.len() <= max {
+        return (items, 0);
+    }
+
+    items.sort_by(|a, b| item_id(a).cmp(&item_id(b)));
+    let deferred = items.len() - max;
+    items.truncate(max);
+    (items, deferred)
+}
+
+fn item_id(item: &Item) -> Option<&str> {
+    item.get("id")?.as_s().ok().map(String::as_str)
+}
+
+pub fn plan_sync_batches(items: Vec<Item>, order_by: Option<&str>) -> Vec<Vec<Item>> {
+    let attribute = match order_by {
+        Some(attribute) => attribute,
+        None if items.is_empty() => return Vec::new(),
+        None => return vec![items],
+    };
+
+    let mut keyed: Vec<(SyncOrderKey, Item)> = items
+        .into_iter()
+        .map(|item| (sync_order_key(&item, attribute), item))
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut batches: Vec<Vec<Item>> = Vec::new();
+    let mut current_key = None;
+
+    for (key, item) in keyed {
+        if current_key.as_ref() == Some(&key) {
+            if let Some(batch) = batches.last_mut() {
+                batch.push(item);
+            }
+        } else {
+            batches.push(vec![item]);
+            current_key = Some(key);
+        }
+    }
+
+    batches
+}
+
+pub async fn load_validation_tables() -> Result<Vec<String>, Error> {
+    let config_table = match std::env::var("CONFIG_TABLE") {
+        Ok(config_table) => config_table,
+        Err(_) => return Ok(DEFAULT_TABLES.iter().map(|t| t.to_string()).collect()),
+    };
+
+    let config = aws_config_loader().load().await;
+    let client = DynamoClient::new(&config);
+
+    let mut tables = Vec::new();
+    let mut last_evaluated_key = None;
+
+    loop {
+        let result = client
+            .scan()
+            .table_name(&config_table)
+            .set_exclusive_start_key(last_evaluated_key)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:Scan", &config_table)?;
+
+        for item in result.items.unwrap_or_default() {
+            let enabled = item
+                .get("validate")
+                .and_then(|value| value.as_bool().ok())
+                .copied()
+                .unwrap_or(true);
+
+            if let Some(Ok(table_name)) = item.get("table_name").map(|value| value.as_s()) {
+                if enabled {
+                    tables.push(table_name.clone());
+                }
+            }
+        }
+
+        match result.last_evaluated_key {
+            Some(key) => last_evaluated_key = Some(key),
+            None => break,
+        }
+    }
+
+    info!("Loaded {} tables from {}", tables.len(), config_table);
+    Ok(tables)
+}
+
+pub async fn load_region_config(region: String) -> SdkConfig {
+    aws_config_loader()
+        .region(aws_config::Region::new(region))
+        .load()
+        .await
+}
+
+// Credentials for another account's DR region, refreshed as they expire
+pub async fn load_assumed_role_config(region: String, role_arn: String) -> SdkConfig {
+    let base_config = load_region_config(region.clone()).await;
+    let credentials = AssumeRoleProvider::builder(role_arn)
+        .session_name("aegis-data-validator")
+        .region(aws_config::Region::new(region.clone()))
+        .configure(&base_config)
+        .build()
+        .await;
+
+    aws_config_loader()
+        .region(aws_config::Region::new(region))
+        .credentials_provider(credentials)
+        .load()
+        .await
+}
+
+// One request per table_targets entry, in table name order
+pub fn split_table_targets(
+    request: &ValidationRequest,
+    table_targets: &HashMap<String, TableTarget>,
+) -> Vec<ValidationRequest> {
+    let mut table_names: Vec<&String> = table_targets.keys().collect();
+    table_names.sort();
+
+    table_names
+        .into_iter()
+        .map(|table_name| {
+            let target = &table_targets[table_name];
+            let mut table_request = request.clone();
+            table_request.table_name = Some(table_name.clone());
+            table_request.source_region = target
+                .source_region
+                .clone()
+                .or_else(|| request.source_region.clone());
+            table_request.target_region = target
+                .target_region
+                .clone()
+                .or_else(|| request.target_region.clone());
+            table_request.role_arn = target.role_arn.clone().or_else(|| request.role_arn.clone());
+            table_request.table_targets = None;
+            table_request
+        })
+        .collect()
+}
+
+pub async fn validate_single(
+    request: ValidationRequest,
+    publish_metrics: bool,
+    default_tables: &[String],
+    region_configs: &RegionCache<SdkConfig>,
+) -> Result<ValidationResponse, Error> {
+    let init_started = Instant::now();
+    let service = DataValidatorService::new(
+        request.source_region.clone(),
+        request.target_region.clone(),
+        region_configs,
+    )
+    .await?
+    .with_dr_role(request.role_arn.clone(), region_configs)
+    .await
+    .with_metric_filter(MetricFilter::new(
+        request.publish_metrics,
+        request.metric_allowlist.clone(),
+    ));
+
+    let init = InitTiming::measure(init_started);
+
+    if publish_metrics {
+        if let Err(e) = service.publish_init_duration(init).await {
+            error!("Failed to publish init duration metric: {}", e);
+        }
+    }
+
+    let mut response = service
+        .run_validation(&request, publish_metrics, default_tables)
+        .await?;
+    response.init = Some(init);
+    Ok(response)
+}
+
+pub async fn validate_multi_region(
+    request: ValidationRequest,
+    target_regions: Vec<String>,
+    publish_metrics: bool,
+    default_tables: &[String],
+    region_configs: &RegionCache<SdkConfig>,
+) -> Result<MultiRegionValidation, Error> {
+    info!(
+        "Validating primary against {} DR regions: {:?}",
+        target_regions.len(),
+        target_regions
+    );
+
+    let request = &request;
+    let responses =
+        futures::future::try_join_all(target_regions.iter().map(|target_region| async move {
+            DataValidatorService::new(
+                request.source_region.clone(),
+                Some(target_region.clone()),
+                region_configs,
+            )
+            .await?
+            .with_dr_role(request.role_arn.clone(), region_configs)
+            .await
+            .with_metric_filter(MetricFilter::new(
+                request.publish_metrics,
+                request.metric_allowlist.clone(),
+            ))
+            .run_validation(request, false, default_tables)
+            .await
+        }))
+        .await?;
+
+    // Per-region runs don't publish; the combined score goes out once
+    if publish_metrics {
+        let metrics_service =
+            DataValidatorService::new(request.source_region.clone(), None, region_configs)
+                .await?
+                .with_metric_filter(MetricFilter::new(
+                    request.publish_metrics,
+                    request.metric_allowlist.clone(),
+                ));
+        if let Err(e) = metrics_service.publish_batch_metrics(&responses).await {
+            error!("Failed to publish multi-region metrics: {}", e);
+        }
+    }
+
+    let status = overall_status(responses.iter().map(|response| response.status));
+    for (target_region, response) in target_regions.iter().zip(&responses) {
+        if response.status != ValidationStatus::Healthy {
+            warn!(
+                "DR region {} is {:?}: {:.1}% consistency",
+                target_region, response.status, response.results.consistency_score
+            );
+        }
+    }
+
+    Ok(MultiRegionValidation {
+        version: version(),
+        status,
+        regions: target_regions
+            .into_iter()
+            .zip(responses)
+            .map(|(target_region, response)| RegionValidation {
+                target_region,
+                response,
+            })
+            .collect(),
+        timestamp: Utc::now().to_rfc3339(),
+    })
+}
+
+pub fn overall_status(statuses: impl IntoIterator<Item = ValidationStatus>) -> ValidationStatus {
+    statuses
+        .into_iter()
+        .max()
+        .unwrap_or(ValidationStatus::Healthy)
+}
+
+// Failed below `failed_threshold`, Degraded below `consistency_threshold` or
+// when a table is under its minimum item count, otherwise Healthy
+pub fn validation_status(
+    consistency_score: f64,
+    failed_threshold: f64,
+    consistency_threshold: f64,
+    // The score can't be trusted, e.g. tables below their minimum size
+    suspect: bool,
+) -> ValidationStatus {
+    if consistency_score < failed_threshold {
+        ValidationStatus::Failed
+    } else if consistency_score < consistency_threshold || suspect {
+        ValidationStatus::Degraded
+    } else {
+        ValidationStatus::Healthy
+    }
+}
+
+// The request's consistency_threshold, or the default; an error naming the
+// value when it isn't a percentage
+pub fn consistency_threshold(requested: Option<f64>) -> Result<f64, String> {
+    match requested {
+        None => Ok(DEFAULT_CONSISTENCY_THRESHOLD),
+        Some(threshold) if (0.0..=100.0).contains(&threshold) => Ok(threshold),
+        Some(threshold) => Err(format!(
+            "consistency_threshold must be between 0 and 100, got {}. Fix the request; nothing was validated.",
+            threshold
+        )),
+    }
+}
+
+pub async fn validate_batch(
+    batch: BatchValidationRequest,
+    default_tables: &[String],
+    region_configs: &RegionCache<SdkConfig>,
+) -> Result<Vec<ValidationResponse>, Error> {
+    if batch
+        .requests
+        .iter()
+        .any(|request| request.target_regions.is_some())
+    {
+        return Err(Error::from(
+            "target_regions is not supported in batch requests; use one entry per target_region",
+        ));
+    }
+
+    if batch
+        .requests
+        .iter()
+        .any(|request| request.table_targets.is_some())
+    {
+        return Err(Error::from(
+            "table_targets is not supported in batch requests; use one entry per table",
+        ));
+    }
+
+    let concurrency = batch
+        .max_concurrency
+        .unwrap_or(DEFAULT_BATCH_CONCURRENCY)
+        .max(1);
+
+    info!(
+        "Running batch validation of {} requests with concurrency {}",
+        batch.requests.len(),
+        concurrency
+    );
+
+    // `buffered` keeps responses in the same order as the requests
+    let responses: Vec<ValidationResponse> = stream::iter(batch.requests)
+        .map(|request| validate_single(request, false, default_tables, region_configs))
+        .buffered(concurrency)
+        .try_collect()
+        .await?;
+
+    let metrics_service = DataValidatorService::new(None, None, region_configs).await?;
+    if let Err(e) = metrics_service.publish_batch_metrics(&responses).await {
+        error!("Failed to publish batch metrics: {}", e);
+    }
+
+    Ok(responses)
+}
+
+// Package version plus the git hash of the build, reported in every response
+pub fn version() -> String {
+    build_version(env!("CARGO_PKG_VERSION"))
+}
+
+pub fn self_test_response() -> ValidationResponse {
+    ValidationResponse {
+        version: version(),
+        init: None,
+        status: ValidationStatus::Healthy,
+        validation_type: "self_test".to_string(),
+        timestamp: Utc::now().to_rfc3339(),
+        results: ValidationResults {
+            tables_validated: 0,
+            tables_skipped: 0,
+            skipped_reasons: HashMap::new(),
+            records_checked: 0,
+            mismatches_found: 0,
+            replication_lag_seconds: None,
+            lag_samples: Vec::new(),
+            dr_latency: None,
+            backup_status: BackupStatus {
+                last_backup_age_hours: None,
+                backup_count: 0,
+                oldest_backup_days: None,
+                backup_check_failed: false,
+            },
+            backup_sla: Vec::new(),
+            consistency_score: 100.0,
+            smoothed_consistency: None,
+            tables_below_min_items: Vec::new(),
+            count_estimate_drift: Vec::new(),
+            schema_drift: Vec::new(),
+            excluded_from_score: Vec::new(),
+            throughput: Vec::new(),
+            sync_refused: Vec::new(),
+            sync_results: Vec::new(),
+            dr_unreachable: false,
+            baseline_drift: Vec::new(),
+            object_checksums: None,
+            progress: None,
+            table_details: None,
+        },
+        recommendations: vec!["Self test passed".to_string()],
+        config_trace: None,
+    }
+}
+
+// Failed without validating anything, because the request itself is invalid
+pub fn rejected_response(validation_type: String, reason: String) -> ValidationResponse {
+    ValidationResponse {
+        status: ValidationStatus::Failed,
+        validation_type,
+        recommendations: vec![reason],
+        ..self_test_response()
+    }
+}
+
+#[tracing::instrument(skip_all, fields(version = %version()))]
+pub async fn function_handler(
+    event: LambdaEvent<ValidationEvent>,
+) -> Result<serde_json::Value, Error> {
+    encode_response(&handle_event(event).await?, ResponseFormat::from_env())
+}
+
+pub async fn handle_event(event: LambdaEvent<ValidationEvent>) -> Result<ValidationOutput, Error> {
+    if let ValidationEvent::Single(request) = &event.payload {
+        if request.self_test {
+            return Ok(ValidationOutput::Single(Box::new(self_test_response())));
+        }
+    }
+
+    // Read once per invocation and shared by every request in a batch
+    let default_tables = load_validation_tables().await?;
+    let region_configs = RegionCache::new();
+    let deadline_ms = Some(event.context.deadline);
+
+    match event.payload {
+        ValidationEvent::Batch(mut batch) => {
+            for request in &mut batch.requests {
+                request.deadline_ms = deadline_ms;
+            }
+            validate_batch(batch, &default_tables, &region_configs)
+                .await
+                .map(ValidationOutput::Batch)
+        }
+        ValidationEvent::Single(mut request) => {
+            request.deadline_ms = deadline_ms;
+            if let Some(table_targets) = request
+                .table_targets
+                .take()
+                .filter(|targets| !targets.is_empty())
+            {
+                if request.target_regions.is_some() {
+                    return Err(Error::from(
+                        "table_targets and target_regions can't be combined; set target_region per table",
+                    ));
+                }
+
+                let batch = BatchValidationRequest {
+                    requests: split_table_targets(&request, &table_targets),
+                    max_concurrency: None,
+                };
+                return validate_batch(batch, &default_tables, &region_configs)
+                    .await
+                    .map(ValidationOutput::Batch);
+            }
+
+            match request
+                .target_regions
+                .take()
+                .filter(|regions| !regions.is_empty())
+            {
+                Some(target_regions) => validate_multi_region(
+                    *request,
+                    target_regions,
+                    true,
+                    &default_tables,
+                    &region_configs,
+                )
+                .await
+                .map(ValidationOutput::MultiRegion),
+                None => validate_single(*request, true, &default_tables, &region_configs)
+                    .await
+                    .map(|response| ValidationOutput::Single(Box::new(response))),
+            }
+        }
+    }
+}
@@ -1,9 +1,15 @@
+use aegis_common::{RegionCache, DEFAULT_SENTINEL_TABLE};
+use data_validator::{
+    backup_sla, cap_sync_items, checkpoint_from_item, checkpoint_to_item, checkpoint_window,
+    checksum_mismatch, consistency_threshold, deep_mismatches, function_handler,
+    lag_monitor_window, metadata_timestamp, object_fingerprint, overall_status, probe_record_id,
+    rejected_response, report_key, self_test_response, split_table_targets, sync_write_condition,
+    validation_status, weighted_moving_average, BackupStatus, CompareTarget, DataValidatorService,
+    ValidationCheckpoint, ValidationEvent, ValidationRequest, ValidationStatus,
+};
 use lambda_runtime::{Context, LambdaEvent};
 use serde_json::json;
 
-// Since data-validator doesn't expose types via lib.rs, we'll test JSON serialization/deserialization
-// and the overall structure of requests and responses
-
 #[test]
 fn test_request_parsing() {
     // Test full request
@@ -370,3 +376,513 @@ mod aws_integration_tests {
         // Test CloudWatch metric publishing
     }
 }
+
+#[cfg(test)]
+mod library_tests {
+    use super::*;
+
+    #[test]
+    fn test_object_checksums() {
+        use aws_sdk_s3::operation::get_object_attributes::GetObjectAttributesOutput;
+        use aws_sdk_s3::types::Checksum;
+
+        let with_checksum = GetObjectAttributesOutput::builder()
+            .checksum(Checksum::builder().checksum_sha256("q1w2e3").build())
+            .e_tag("\"abc\"")
+            .build();
+        assert_eq!(object_fingerprint(&with_checksum), "sha256:q1w2e3");
+
+        let etag_only = GetObjectAttributesOutput::builder()
+            .e_tag("\"abc\"")
+            .build();
+        assert_eq!(object_fingerprint(&etag_only), "etag:\"abc\"");
+
+        assert_eq!(checksum_mismatch(Some("sha256:a"), Some("sha256:a")), None);
+        assert_eq!(
+            checksum_mismatch(Some("sha256:a"), Some("sha256:b")),
+            Some("checksum differs in DR bucket")
+        );
+        assert_eq!(
+            checksum_mismatch(Some("sha256:a"), None),
+            Some("missing from DR bucket")
+        );
+    }
+
+    #[test]
+    fn test_lag_monitor_window_stops_before_deadline() {
+        use std::time::Duration;
+
+        // No deadline: the requested time as is
+        assert_eq!(lag_monitor_window(120, 0, None), Duration::from_secs(120));
+
+        // 5 minutes left: plenty for 2 minutes of sampling
+        let now_ms = 1_700_000_000_000;
+        assert_eq!(
+            lag_monitor_window(120, now_ms, Some(now_ms + 300_000)),
+            Duration::from_secs(120)
+        );
+
+        // 90 seconds left: the last 30 are kept for the rest of the run
+        assert_eq!(
+            lag_monitor_window(120, now_ms, Some(now_ms + 90_000)),
+            Duration::from_secs(60)
+        );
+
+        // Past the deadline: a single sample
+        assert_eq!(
+            lag_monitor_window(120, now_ms, Some(now_ms - 1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_deep_mismatches_reports_every_difference() {
+        let primary: std::collections::HashMap<String, u64> = [
+            ("a".to_string(), 1),
+            ("b".to_string(), 2),
+            ("c".to_string(), 3),
+        ]
+        .into_iter()
+        .collect();
+        let dr: std::collections::HashMap<String, u64> = [
+            ("a".to_string(), 1),
+            ("c".to_string(), 4),
+            ("d".to_string(), 5),
+        ]
+        .into_iter()
+        .collect();
+
+        let mismatches = deep_mismatches(&primary, &dr);
+        let found: Vec<(&str, &str)> = mismatches
+            .iter()
+            .map(|m| (m.key.as_str(), m.reason.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("b", "not found in DR"),
+                ("c", "content differs"),
+                ("d", "not found in primary"),
+            ]
+        );
+        assert!(deep_mismatches(&primary, &primary).is_empty());
+    }
+
+    #[test]
+    fn test_request_deserializes_into_real_type() {
+        let request: ValidationRequest = serde_json::from_value(json!({
+            "validation_type": "full",
+            "table_name": "test-table",
+            "source_region": "us-east-1",
+            "target_region": "us-west-2",
+            "action": "validate",
+            "compare_target": "latest_backup",
+            "include_table_details": true
+        }))
+        .unwrap();
+
+        assert_eq!(request.validation_type.as_deref(), Some("full"));
+        assert_eq!(request.table_name.as_deref(), Some("test-table"));
+        assert_eq!(request.target_region.as_deref(), Some("us-west-2"));
+        assert_eq!(request.action.as_deref(), Some("validate"));
+        assert_eq!(request.compare_target, CompareTarget::LatestBackup);
+        assert!(request.include_table_details);
+        // Defaults for everything left out
+        assert!(request.publish_metrics);
+        assert!(!request.self_test);
+        assert!(!request.sync_transactional);
+    }
+
+    #[test]
+    fn test_table_targets_split_into_per_table_requests() {
+        let request: ValidationRequest = serde_json::from_value(json!({
+            "source_region": "us-east-1",
+            "target_region": "us-west-2",
+            "table_targets": {
+                "payments": {
+                    "target_region": "eu-west-1",
+                    "role_arn": "arn:aws:iam::222222222222:role/dr-validator"
+                },
+                "orders": {}
+            }
+        }))
+        .unwrap();
+        let table_targets = request.table_targets.clone().unwrap();
+
+        let requests = split_table_targets(&request, &table_targets);
+
+        assert_eq!(requests.len(), 2);
+        // Unset fields fall back to the request's regions
+        assert_eq!(requests[0].table_name.as_deref(), Some("orders"));
+        assert_eq!(requests[0].source_region.as_deref(), Some("us-east-1"));
+        assert_eq!(requests[0].target_region.as_deref(), Some("us-west-2"));
+        assert_eq!(requests[0].role_arn, None);
+        assert_eq!(requests[1].table_name.as_deref(), Some("payments"));
+        assert_eq!(requests[1].target_region.as_deref(), Some("eu-west-1"));
+        assert_eq!(
+            requests[1].role_arn.as_deref(),
+            Some("arn:aws:iam::222222222222:role/dr-validator")
+        );
+        assert!(requests.iter().all(|r| r.table_targets.is_none()));
+    }
+
+    #[test]
+    fn test_event_distinguishes_batch_from_single() {
+        let batch: ValidationEvent = serde_json::from_value(json!({
+            "requests": [{"table_name": "a"}, {"table_name": "b"}],
+            "max_concurrency": 2
+        }))
+        .unwrap();
+        match batch {
+            ValidationEvent::Batch(batch) => {
+                assert_eq!(batch.requests.len(), 2);
+                assert_eq!(batch.max_concurrency, Some(2));
+            }
+            ValidationEvent::Single(_) => panic!("expected a batch"),
+        }
+
+        let single: ValidationEvent = serde_json::from_value(json!({"table_name": "a"})).unwrap();
+        assert!(matches!(single, ValidationEvent::Single(_)));
+    }
+
+    #[test]
+    fn test_response_serializes_snake_case_fields() {
+        let json = serde_json::to_value(self_test_response()).unwrap();
+
+        assert_eq!(json["status"], "healthy");
+        assert_eq!(json["validation_type"], "self_test");
+        assert_eq!(json["results"]["tables_validated"], 0);
+        assert_eq!(json["results"]["consistency_score"], 100.0);
+        assert_eq!(json["results"]["backup_status"]["backup_count"], 0);
+        assert_eq!(
+            json["results"]["backup_status"]["backup_check_failed"],
+            false
+        );
+        assert!(json["results"]["replication_lag_seconds"].is_null());
+        assert!(json.get("config_trace").is_none());
+    }
+
+    #[test]
+    fn test_out_of_range_consistency_threshold_fails_the_run() {
+        let reason = consistency_threshold(Some(150.0)).unwrap_err();
+        assert!(reason.contains("between 0 and 100, got 150"));
+        assert!(consistency_threshold(Some(-1.0)).is_err());
+
+        let response = rejected_response("full".to_string(), reason.clone());
+        assert_eq!(response.status, ValidationStatus::Failed);
+        assert_eq!(response.validation_type, "full");
+        assert_eq!(response.recommendations, vec![reason]);
+        assert_eq!(response.results.tables_validated, 0);
+    }
+
+    #[test]
+    fn test_status_thresholds() {
+        assert_eq!(
+            validation_status(100.0, 50.0, 95.0, false),
+            ValidationStatus::Healthy
+        );
+        assert_eq!(
+            validation_status(100.0, 50.0, 95.0, true),
+            ValidationStatus::Degraded
+        );
+        assert_eq!(
+            validation_status(40.0, 50.0, 95.0, false),
+            ValidationStatus::Failed
+        );
+
+        // 93% is healthy against a 90% threshold but degraded against 99%
+        assert_eq!(
+            validation_status(
+                93.0,
+                50.0,
+                consistency_threshold(Some(90.0)).unwrap(),
+                false
+            ),
+            ValidationStatus::Healthy
+        );
+        assert_eq!(
+            validation_status(
+                93.0,
+                50.0,
+                consistency_threshold(Some(99.0)).unwrap(),
+                false
+            ),
+            ValidationStatus::Degraded
+        );
+        assert_eq!(consistency_threshold(None), Ok(95.0));
+
+        let statuses = [ValidationStatus::Healthy, ValidationStatus::Failed];
+        assert_eq!(overall_status(statuses), ValidationStatus::Failed);
+        assert_eq!(overall_status([]), ValidationStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_self_test_through_handler() {
+        let payload: ValidationEvent = serde_json::from_value(json!({"self_test": true})).unwrap();
+        let event = LambdaEvent {
+            payload,
+            context: Context::default(),
+        };
+
+        let response = function_handler(event).await.unwrap();
+        assert_eq!(response["status"], "healthy");
+        assert_eq!(response["recommendations"], json!(["Self test passed"]));
+    }
+}
+
+#[cfg(test)]
+mod smoothing_tests {
+    use super::*;
+
+    #[test]
+    fn test_weighted_moving_average() {
+        assert_eq!(weighted_moving_average(&[]), None);
+        assert_eq!(weighted_moving_average(&[90.0]), Some(90.0));
+
+        // Newest first: (3 * 100 + 2 * 70 + 1 * 100) / 6
+        assert_eq!(weighted_moving_average(&[100.0, 70.0, 100.0]), Some(90.0));
+
+        // A single dip is damped rather than reported outright
+        let smoothed = weighted_moving_average(&[40.0, 100.0, 100.0, 100.0, 100.0]).unwrap();
+        assert!(smoothed > 75.0 && smoothed < 100.0);
+    }
+
+    #[test]
+    fn test_report_keys_sort_newest_first() {
+        let older = report_key(1_700_000_000_000);
+        let newer = report_key(1_700_000_000_001);
+
+        assert!(newer < older);
+        assert!(newer.starts_with("reports/"));
+        assert_eq!(newer.len(), older.len());
+    }
+}
+
+#[cfg(test)]
+mod sync_condition_tests {
+    use super::*;
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use std::collections::HashMap;
+
+    fn item(updated_at: Option<&str>) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::from([("id".to_string(), AttributeValue::S("order-1".into()))]);
+        if let Some(updated_at) = updated_at {
+            item.insert(
+                "updated_at".to_string(),
+                AttributeValue::S(updated_at.into()),
+            );
+        }
+        item
+    }
+
+    #[test]
+    fn test_sync_only_writes_absent_items_by_default() {
+        let condition = sync_write_condition(&item(Some("2025-01-06T12:00:00Z")), None);
+
+        assert_eq!(condition.expression, "attribute_not_exists(#id)");
+        assert_eq!(condition.names["#id"], "id");
+        assert_eq!(condition.values, None);
+    }
+
+    #[test]
+    fn test_sync_replaces_older_versions() {
+        let condition =
+            sync_write_condition(&item(Some("2025-01-06T12:00:00Z")), Some("updated_at"));
+
+        assert_eq!(
+            condition.expression,
+            "attribute_not_exists(#id) OR #version < :version"
+        );
+        assert_eq!(condition.names["#version"], "updated_at");
+        assert_eq!(
+            condition.values.unwrap()[":version"],
+            AttributeValue::S("2025-01-06T12:00:00Z".into())
+        );
+
+        // Without a version on primary's item there is nothing to compare
+        let condition = sync_write_condition(&item(None), Some("updated_at"));
+        assert_eq!(condition.expression, "attribute_not_exists(#id)");
+    }
+
+    #[test]
+    fn test_sync_cap_keeps_lowest_ids() {
+        let items: Vec<_> = ["order-3", "order-1", "order-4", "order-2"]
+            .into_iter()
+            .map(|id| HashMap::from([("id".to_string(), AttributeValue::S(id.into()))]))
+            .collect();
+
+        let (kept, deferred) = cap_sync_items(items.clone(), 2);
+        let ids: Vec<_> = kept.iter().map(|item| item["id"].as_s().unwrap()).collect();
+        assert_eq!(ids, vec!["order-1", "order-2"]);
+        assert_eq!(deferred, 2);
+
+        let (kept, deferred) = cap_sync_items(items, 1000);
+        assert_eq!(kept.len(), 4);
+        assert_eq!(deferred, 0);
+    }
+}
+
+#[cfg(test)]
+mod service_config_tests {
+    use super::*;
+
+    // The only test in this binary touching SENTINEL_TABLE, so it can't race
+    #[tokio::test]
+    async fn test_sentinel_table_from_env() {
+        let region_configs = RegionCache::new();
+        let new_service = || {
+            DataValidatorService::new(
+                Some("us-east-1".to_string()),
+                Some("us-west-2".to_string()),
+                &region_configs,
+            )
+        };
+
+        std::env::remove_var("SENTINEL_TABLE");
+        let service = new_service().await.unwrap();
+        assert_eq!(service.sentinel_table, DEFAULT_SENTINEL_TABLE);
+
+        std::env::set_var("SENTINEL_TABLE", "payments-sentinel");
+        let service = new_service().await.unwrap();
+        std::env::remove_var("SENTINEL_TABLE");
+        assert_eq!(service.sentinel_table, "payments-sentinel");
+    }
+}
+
+#[cfg(test)]
+mod backup_sla_tests {
+    use super::*;
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_backup_sla() {
+        let now = 1_704_556_800;
+
+        let fresh = backup_sla("orders", 24.0, Some(now - 6 * 3600), now);
+        assert!(fresh.pass);
+        assert_eq!(fresh.newest_backup_age_hours, Some(6.0));
+
+        let stale = backup_sla("orders", 24.0, Some(now - 30 * 3600), now);
+        assert!(!stale.pass);
+        assert_eq!(stale.newest_backup_age_hours, Some(30.0));
+
+        let never = backup_sla("orders", 24.0, None, now);
+        assert!(!never.pass);
+        assert_eq!(never.newest_backup_age_hours, None);
+    }
+
+    #[test]
+    fn test_metadata_timestamp_accepts_string_or_number() {
+        let item = |value| HashMap::from([("timestamp".to_string(), value)]);
+
+        assert_eq!(
+            metadata_timestamp(&item(AttributeValue::S("1704556800".into()))),
+            Some(1_704_556_800)
+        );
+        assert_eq!(
+            metadata_timestamp(&item(AttributeValue::N("1704556800".into()))),
+            Some(1_704_556_800)
+        );
+        assert_eq!(
+            metadata_timestamp(&item(AttributeValue::S("yesterday".into()))),
+            None
+        );
+        assert_eq!(metadata_timestamp(&HashMap::new()), None);
+    }
+}
+
+#[cfg(test)]
+mod checkpoint_tests {
+    use super::*;
+
+    fn tables(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_checkpoint_window() {
+        let all = tables(&["a", "b", "c", "d", "e"]);
+
+        // No checkpoint: start from the first table
+        assert_eq!(checkpoint_window(&all, None, 2), (0, 2));
+
+        // Continue where the last run stopped, clamped to the end
+        let checkpoint = ValidationCheckpoint {
+            tables: all.clone(),
+            next_index: 4,
+        };
+        assert_eq!(checkpoint_window(&all, Some(&checkpoint), 2), (4, 5));
+
+        // The table list changed since the checkpoint: start a new pass
+        let stale = ValidationCheckpoint {
+            tables: tables(&["a", "b"]),
+            next_index: 1,
+        };
+        assert_eq!(checkpoint_window(&all, Some(&stale), 2), (0, 2));
+
+        // Nothing to validate
+        assert_eq!(checkpoint_window(&[], None, 2), (0, 0));
+    }
+
+    #[test]
+    fn test_checkpoint_item_round_trip() {
+        let checkpoint = ValidationCheckpoint {
+            tables: tables(&["orders", "users"]),
+            next_index: 1,
+        };
+
+        let item = checkpoint_to_item("validation_checkpoint#us-west-2", &checkpoint);
+        assert_eq!(
+            item["backup_id"].as_s().unwrap(),
+            "validation_checkpoint#us-west-2"
+        );
+        assert_eq!(checkpoint_from_item(&item), Some(checkpoint));
+    }
+}
+
+#[cfg(test)]
+mod probe_id_tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_probe_record_id_format() {
+        let id = probe_record_id("lag-test", "a1b2c3d4");
+        assert!(id.starts_with("lag-test-a1b2c3d4-"));
+        assert_eq!(id.len(), "lag-test-a1b2c3d4-".len() + 32);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_runs_get_distinct_ids() {
+        // Two invocations starting in the same millisecond
+        let run = |salt: &'static str| {
+            tokio::spawn(async move {
+                (0..500)
+                    .map(|_| probe_record_id("lag-test", salt))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let (first, second) = tokio::join!(run("aaaaaaaa"), run("bbbbbbbb"));
+        let (first, second) = (first.unwrap(), second.unwrap());
+
+        let ids: HashSet<&String> = first.iter().chain(&second).collect();
+        assert_eq!(ids.len(), first.len() + second.len());
+        assert!(first.iter().all(|id| id.starts_with("lag-test-aaaaaaaa-")));
+        assert!(second.iter().all(|id| id.starts_with("lag-test-bbbbbbbb-")));
+    }
+}
+
+#[cfg(test)]
+mod backup_status_tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_check_is_not_an_empty_history() {
+        let json = serde_json::to_value(BackupStatus::check_failed()).unwrap();
+
+        assert_eq!(json["backup_count"], 0);
+        assert_eq!(json["backup_check_failed"], true);
+        assert!(json["last_backup_age_hours"].is_null());
+    }
+}
@@ -0,0 +1,496 @@
+use aegis_common::{aws_config_loader, build_version, sentinel_table, SdkResultExt};
+use aws_sdk_dynamodb::{types::AttributeValue, Client as DynamoClient};
+use chrono::Utc;
+use failover_controller::{FailoverService, FailoverStatus};
+use health_check::HealthCheckService;
+use lambda_runtime::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+// Tables compared when the request doesn't name any
+const DEFAULT_TABLES: [&str; 2] = ["dr-application-table", "dr-sentinel-table"];
+
+// Oldest newest-backup a drill accepts before calling DR not ready
+const DEFAULT_DRILL_MAX_RPO_SECONDS: i64 = 86_400;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Request {
+    pub tables: Option<Vec<String>>,
+    // "overview" (default) or "drill"
+    pub action: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum DrOutput {
+    Overview(Box<DrOverview>),
+    Drill(DrillReport),
+}
+
+// A dry run of the DR runbook: each step only reads, apart from a drill record
+// written to and removed from the DR sentinel table. Ready when every step passed.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DrillReport {
+    pub ready: bool,
+    pub steps: Vec<DrillStep>,
+    pub timestamp: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DrillStep {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+// One payload summarizing the whole DR posture. A sub-check that fails leaves
+// its section empty and adds an entry to `errors` instead of failing the call.
+#[derive(Serialize, Debug, Clone)]
+pub struct DrOverview {
+    pub health: Option<health_check::Response>,
+    pub validation_summary: Option<ValidationSummary>,
+    pub backup_rpo: Option<BackupRpo>,
+    pub failover_state: Option<FailoverStatus>,
+    pub errors: Vec<String>,
+    pub timestamp: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ValidationSummary {
+    pub tables: Vec<TableCounts>,
+    pub in_sync: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TableCounts {
+    pub table_name: String,
+    pub primary_count: i64,
+    pub dr_count: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BackupRpo {
+    pub last_backup_timestamp: Option<i64>,
+    // Seconds of data that would be lost restoring from the newest backup now
+    pub rpo_seconds: Option<i64>,
+}
+
+pub struct DrOverviewService {
+    primary_dynamo: DynamoClient,
+    dr_dynamo: DynamoClient,
+    dr_region: String,
+    metadata_table: String,
+    tables: Vec<String>,
+    // DRILL_MAX_RPO_SECONDS
+    drill_max_rpo_seconds: i64,
+}
+
+impl DrOverviewService {
+    pub async fn new(tables: Option<Vec<String>>) -> Result<Self, Error> {
+        let primary_config = aws_config_loader().load().await;
+
+        let dr_region = std::env::var("DR_REGION").unwrap_or_else(|_| "us-west-2".to_string());
+        let dr_config = aws_config_loader()
+            .region(aws_config::Region::new(dr_region.clone()))
+            .load()
+            .await;
+
+        let metadata_table =
+            std::env::var("METADATA_TABLE").unwrap_or_else(|_| "dr-backup-metadata".to_string());
+        let drill_max_rpo_seconds = std::env::var("DRILL_MAX_RPO_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_DRILL_MAX_RPO_SECONDS);
+
+        Ok(Self {
+            primary_dynamo: DynamoClient::new(&primary_config),
+            dr_dynamo: DynamoClient::new(&dr_config),
+            dr_region,
+            metadata_table,
+            tables: tables
+                .unwrap_or_else(|| DEFAULT_TABLES.iter().map(|t| t.to_string()).collect()),
+            drill_max_rpo_seconds,
+        })
+    }
+
+    async fn check_health(&self) -> Result<health_check::Response, Error> {
+        Ok(HealthCheckService::new(None)
+            .await?
+            .run_health_check()
+            .await?)
+    }
+
+    async fn item_count(&self, client: &DynamoClient, table_name: &str) -> Result<i64, Error> {
+        let result = client
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:DescribeTable", table_name)?;
+
+        Ok(result.table.and_then(|table| table.item_count).unwrap_or(0))
+    }
+
+    async fn summarize_validation(&self) -> Result<ValidationSummary, Error> {
+        let mut tables = Vec::with_capacity(self.tables.len());
+
+        for table_name in &self.tables {
+            let (primary_count, dr_count) = tokio::try_join!(
+                self.item_count(&self.primary_dynamo, table_name),
+                self.item_count(&self.dr_dynamo, table_name)
+            )?;
+
+            tables.push(TableCounts {
+                table_name: table_name.clone(),
+                primary_count,
+                dr_count,
+            });
+        }
+
+        Ok(summarize_counts(tables))
+    }
+
+    async fn check_backup_rpo(&self) -> Result<BackupRpo, Error> {
+        let mut items = Vec::new();
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = self
+                .primary_dynamo
+                .scan()
+                .table_name(&self.metadata_table)
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:Scan", &self.metadata_table)?;
+
+            items.extend(result.items.unwrap_or_default());
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        let last_backup_timestamp = newest_backup_timestamp(&items);
+
+        Ok(BackupRpo {
+            last_backup_timestamp,
+            rpo_seconds: last_backup_timestamp.map(|ts| Utc::now().timestamp() - ts),
+        })
+    }
+
+    async fn check_failover_state(&self) -> Result<Option<FailoverStatus>, Error> {
+        FailoverService::new().await?.current_status().await
+    }
+
+    // The same health check a failover runs before switching to DR
+    async fn check_target_health(&self) -> Result<(bool, String), Error> {
+        let healthy = FailoverService::new()
+            .await?
+            .check_health(&self.dr_region)
+            .await?;
+        Ok(evaluate_target_health(&self.dr_region, healthy))
+    }
+
+    // Writes a drill record to the DR sentinel table, reads it back and
+    // deletes it again; nothing else in DR is touched
+    async fn exercise_dr_read_write(&self) -> Result<(bool, String), Error> {
+        let table_name = sentinel_table();
+        let drill_id = format!("dr-drill-{}", Utc::now().timestamp_millis());
+
+        self.dr_dynamo
+            .put_item()
+            .table_name(&table_name)
+            .item("id", AttributeValue::S(drill_id.clone()))
+            .item("source", AttributeValue::S("dr-drill".to_string()))
+            .send()
+            .await
+            .map_sdk_err("dynamodb:PutItem", &table_name)?;
+
+        let read = self
+            .dr_dynamo
+            .get_item()
+            .table_name(&table_name)
+            .key("id", AttributeValue::S(drill_id.clone()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:GetItem", &table_name);
+
+        if let Err(e) = self
+            .dr_dynamo
+            .delete_item()
+            .table_name(&table_name)
+            .key("id", AttributeValue::S(drill_id.clone()))
+            .send()
+            .await
+        {
+            warn!("Failed to remove drill record {}: {}", drill_id, e);
+        }
+
+        Ok(match read?.item {
+            Some(_) => (
+                true,
+                format!("Wrote and read back a record in {}", table_name),
+            ),
+            None => (
+                false,
+                format!("Record written to {} could not be read back", table_name),
+            ),
+        })
+    }
+
+    // Runs the runbook's checks one after another, as an operator would, and
+    // keeps going past a failed step so the report covers all of them
+    pub async fn run_drill(&self) -> DrillReport {
+        let mut steps = Vec::new();
+
+        let started = Instant::now();
+        let result = self
+            .summarize_validation()
+            .await
+            .map(|summary| evaluate_consistency(&summary));
+        steps.push(drill_step("validate_consistency", started, result));
+
+        let started = Instant::now();
+        let result = self
+            .check_backup_rpo()
+            .await
+            .map(|rpo| evaluate_backup_rpo(&rpo, self.drill_max_rpo_seconds));
+        steps.push(drill_step("confirm_backups", started, result));
+
+        let started = Instant::now();
+        let result = self.check_target_health().await;
+        steps.push(drill_step("target_health", started, result));
+
+        let started = Instant::now();
+        let result = self.exercise_dr_read_write().await;
+        steps.push(drill_step("dr_read_write", started, result));
+
+        let report = drill_report(steps);
+        info!(
+            "DR drill finished: {}",
+            if report.ready { "ready" } else { "not ready" }
+        );
+        report
+    }
+
+    // The sub-checks are independent, so they run concurrently
+    pub async fn run_overview(&self) -> DrOverview {
+        let (health, validation_summary, backup_rpo, failover_state) = tokio::join!(
+            self.check_health(),
+            self.summarize_validation(),
+            self.check_backup_rpo(),
+            self.check_failover_state()
+        );
+
+        let mut errors = Vec::new();
+        let mut record = |check: &str, e: Error| {
+            error!("DR overview {} check failed: {}", check, e);
+            errors.push(format!("{}: {}", check, e));
+        };
+
+        let health = health.map_err(|e| record("health", e)).ok();
+        let validation_summary = validation_summary.map_err(|e| record("validation", e)).ok();
+        let backup_rpo = backup_rpo.map_err(|e| record("backup", e)).ok();
+        let failover_state = failover_state
+            .map_err(|e| record("failover", e))
+            .ok()
+            .flatten();
+
+        info!("DR overview completed with {} failed checks", errors.len());
+
+        DrOverview {
+            health,
+            validation_summary,
+            backup_rpo,
+            failover_state,
+            errors,
+            timestamp: Utc::now().to_rfc3339(),
+            version: version(),
+        }
+    }
+}
+
+// Package version plus the git hash of the build, reported in every response
+pub fn version() -> String {
+    build_version(env!("CARGO_PKG_VERSION"))
+}
+
+// A step that couldn't run counts as failed, with the error as its detail
+pub fn drill_step(
+    name: &str,
+    started: Instant,
+    result: Result<(bool, String), Error>,
+) -> DrillStep {
+    let (passed, detail) = result.unwrap_or_else(|e| {
+        error!("DR drill step {} failed to run: {}", name, e);
+        (false, format!("Step failed to run: {}", e))
+    });
+
+    DrillStep {
+        name: name.to_string(),
+        passed,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+pub fn drill_report(steps: Vec<DrillStep>) -> DrillReport {
+    DrillReport {
+        ready: !steps.is_empty() && steps.iter().all(|step| step.passed),
+        steps,
+        timestamp: Utc::now().to_rfc3339(),
+        version: version(),
+    }
+}
+
+pub fn evaluate_consistency(summary: &ValidationSummary) -> (bool, String) {
+    let out_of_sync: Vec<&str> = summary
+        .tables
+        .iter()
+        .filter(|table| table.primary_count != table.dr_count)
+        .map(|table| table.table_name.as_str())
+        .collect();
+
+    match out_of_sync.is_empty() {
+        true => (
+            true,
+            format!("{} tables match between regions", summary.tables.len()),
+        ),
+        false => (
+            false,
+            format!("Item counts differ for {}", out_of_sync.join(", ")),
+        ),
+    }
+}
+
+pub fn evaluate_backup_rpo(rpo: &BackupRpo, max_rpo_seconds: i64) -> (bool, String) {
+    match rpo.rpo_seconds {
+        Some(seconds) if seconds <= max_rpo_seconds => {
+            (true, format!("Newest backup is {}s old", seconds))
+        }
+        Some(seconds) => (
+            false,
+            format!(
+                "Newest backup is {}s old, over the {}s limit",
+                seconds, max_rpo_seconds
+            ),
+        ),
+        None => (false, "No backups recorded".to_string()),
+    }
+}
+
+pub fn evaluate_target_health(region: &str, healthy: bool) -> (bool, String) {
+    match healthy {
+        true => (true, format!("{} passed the failover health check", region)),
+        false => (
+            false,
+            format!("{} failed the failover health check", region),
+        ),
+    }
+}
+
+pub fn summarize_counts(tables: Vec<TableCounts>) -> ValidationSummary {
+    let in_sync = tables
+        .iter()
+        .all(|table| table.primary_count == table.dr_count);
+
+    ValidationSummary { tables, in_sync }
+}
+
+// Backup metadata rows carry a table_name; the failover status and lock rows
+// sharing the table don't, so they are skipped
+pub fn newest_backup_timestamp(items: &[HashMap<String, AttributeValue>]) -> Option<i64> {
+    items
+        .iter()
+        .filter(|item| item.contains_key("table_name"))
+        .filter_map(|item| match item.get("timestamp")? {
+            AttributeValue::S(value) | AttributeValue::N(value) => value.parse().ok(),
+            _ => None,
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata_row(table_name: Option<&str>, timestamp: &str) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::new();
+        if let Some(table_name) = table_name {
+            item.insert(
+                "table_name".to_string(),
+                AttributeValue::S(table_name.to_string()),
+            );
+        }
+        item.insert(
+            "timestamp".to_string(),
+            AttributeValue::S(timestamp.to_string()),
+        );
+        item
+    }
+
+    #[test]
+    fn test_newest_backup_timestamp() {
+        let items = vec![
+            metadata_row(Some("orders"), "1704556800"),
+            metadata_row(Some("customers"), "1704643200"),
+            metadata_row(None, "1804643200"), // failover status row
+            metadata_row(Some("orders"), "not-a-number"),
+        ];
+
+        assert_eq!(newest_backup_timestamp(&items), Some(1704643200));
+        assert_eq!(newest_backup_timestamp(&[]), None);
+    }
+
+    #[test]
+    fn test_drill_readiness() {
+        let rpo = |rpo_seconds| BackupRpo {
+            last_backup_timestamp: Some(1704556800),
+            rpo_seconds,
+        };
+        assert!(evaluate_backup_rpo(&rpo(Some(3600)), 86_400).0);
+        assert!(!evaluate_backup_rpo(&rpo(Some(90_000)), 86_400).0);
+        assert!(!evaluate_backup_rpo(&rpo(None), 86_400).0);
+
+        let summary = summarize_counts(vec![TableCounts {
+            table_name: "orders".to_string(),
+            primary_count: 10,
+            dr_count: 9,
+        }]);
+        assert_eq!(
+            evaluate_consistency(&summary),
+            (false, "Item counts differ for orders".to_string())
+        );
+
+        let started = Instant::now();
+        let passed = drill_step("confirm_backups", started, Ok((true, "ok".to_string())));
+        let errored = drill_step("dr_read_write", started, Err(Error::from("access denied")));
+        assert!(!errored.passed);
+        assert_eq!(errored.detail, "Step failed to run: access denied");
+
+        assert!(drill_report(vec![passed.clone()]).ready);
+        assert!(!drill_report(vec![passed, errored]).ready);
+        assert!(!drill_report(Vec::new()).ready);
+    }
+
+    #[test]
+    fn test_summarize_counts() {
+        let counts = |primary_count, dr_count| TableCounts {
+            table_name: "orders".to_string(),
+            primary_count,
+            dr_count,
+        };
+
+        assert!(summarize_counts(vec![counts(10, 10)]).in_sync);
+        assert!(!summarize_counts(vec![counts(10, 10), counts(10, 9)]).in_sync);
+        assert!(summarize_counts(Vec::new()).in_sync);
+    }
+}
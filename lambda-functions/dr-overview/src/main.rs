@@ -0,0 +1,24 @@
+use dr_overview::{version, DrOutput, DrOverviewService, Request};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+
+#[tracing::instrument(skip_all, fields(version = %version()))]
+async fn function_handler(event: LambdaEvent<Request>) -> Result<DrOutput, Error> {
+    let request = event.payload;
+    let service = DrOverviewService::new(request.tables).await?;
+
+    match request.action.as_deref().unwrap_or("overview") {
+        "overview" => Ok(DrOutput::Overview(Box::new(service.run_overview().await))),
+        "drill" => Ok(DrOutput::Drill(service.run_drill().await)),
+        action => Err(Error::from(format!("Invalid action: {}", action))),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    run(service_fn(function_handler)).await
+}
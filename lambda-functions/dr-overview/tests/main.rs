@@ -0,0 +1,48 @@
+use dr_overview::{BackupRpo, DrOverview, Request, TableCounts, ValidationSummary};
+use serde_json::json;
+
+#[test]
+fn test_request_parsing() {
+    let request: Request = serde_json::from_value(json!({})).unwrap();
+    assert_eq!(request.tables, None);
+
+    let request: Request = serde_json::from_value(json!({
+        "tables": ["orders", "customers"]
+    }))
+    .unwrap();
+    assert_eq!(
+        request.tables,
+        Some(vec!["orders".to_string(), "customers".to_string()])
+    );
+}
+
+#[test]
+fn test_overview_serialization() {
+    let overview = DrOverview {
+        health: None,
+        validation_summary: Some(ValidationSummary {
+            tables: vec![TableCounts {
+                table_name: "orders".to_string(),
+                primary_count: 100,
+                dr_count: 98,
+            }],
+            in_sync: false,
+        }),
+        backup_rpo: Some(BackupRpo {
+            last_backup_timestamp: Some(1704556800),
+            rpo_seconds: Some(3600),
+        }),
+        failover_state: None,
+        errors: vec!["health: DynamoDB unreachable".to_string()],
+        timestamp: "2025-01-06T12:00:00Z".to_string(),
+        version: "0.1.0+test".to_string(),
+    };
+
+    let json = serde_json::to_value(&overview).unwrap();
+
+    assert!(json["health"].is_null());
+    assert_eq!(json["validation_summary"]["in_sync"], false);
+    assert_eq!(json["validation_summary"]["tables"][0]["dr_count"], 98);
+    assert_eq!(json["backup_rpo"]["rpo_seconds"], 3600);
+    assert_eq!(json["errors"][0], "health: DynamoDB unreachable");
+}
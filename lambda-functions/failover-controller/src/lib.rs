@@ -1,23 +1,143 @@
-use aws_config::BehaviorVersion;
+use aegis_common::{
+    access_denied, aws_config_loader, build_version, lag_timestamp_attr, map_sdk_error, parse_flag,
+    sentinel_table, ConfigSource, ConfigTrace, EventEmitter, InitTiming, RegionCache, SdkResultExt,
+};
+use aws_lambda_events::apigw::ApiGatewayV2httpRequest;
+use aws_lambda_events::query_map::QueryMap;
+use aws_sdk_cloudwatch::{
+    types::{Dimension, MetricDatum, StandardUnit},
+    Client as CloudWatchClient,
+};
+use aws_sdk_dynamodb::operation::put_item::PutItemError;
+use aws_sdk_dynamodb::types::AttributeValue;
 use aws_sdk_dynamodb::Client as DynamoClient;
+use aws_sdk_sns::{error::BuildError, operation::publish::PublishInput, Client as SnsClient};
+use aws_sdk_ssm::Client as SsmClient;
 use chrono::Utc;
+use futures::StreamExt;
 use lambda_runtime::Error;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tracing::{error, info, warn};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Request {
-    pub action: String,        // "failover" or "failback"
+    // Both may be omitted for a self test; otherwise they are validated
+    #[serde(default)]
+    pub action: String, // "failover" or "failback"
+    #[serde(default)]
     pub target_region: String, // Region to failover/failback to
-    pub force: Option<bool>,   // Force failover even if health checks fail
+    pub force: Option<bool>, // Shorthand for setting every force flag
+    pub force_flags: Option<ForceFlags>,
+    // A failover into the region we already run in is almost always an operator
+    // error, so it is refused unless this is set. Not implied by `force`.
+    pub allow_same_region: Option<bool>,
+    // Smoke-test mode: return a canned response without calling AWS
+    #[serde(default)]
+    pub self_test: bool,
+    // Report where each config value came from in `config_trace`
+    #[serde(default)]
+    pub debug: bool,
 }
 
+// Function URL invocations wrap the call in an HTTP event, so the parameters
+// come from the query string rather than the payload itself
+pub fn parse_event(event: serde_json::Value) -> Result<Request, serde_json::Error> {
+    if event.get("requestContext").is_some() {
+        let http: ApiGatewayV2httpRequest = serde_json::from_value(event)?;
+        return Ok(request_from_query(&http.query_string_parameters));
+    }
+    serde_json::from_value(event)
+}
+
+pub fn request_from_query(params: &QueryMap) -> Request {
+    let text = |name: &str| params.first(name).unwrap_or_default().to_string();
+    let flag = |name: &str| params.first(name).map(parse_flag);
+    let force_flags = ["skip_health", "skip_lag", "skip_lock"]
+        .iter()
+        .any(|name| params.first(name).is_some())
+        .then(|| ForceFlags {
+            skip_health: flag("skip_health").unwrap_or(false),
+            skip_lag: flag("skip_lag").unwrap_or(false),
+            skip_lock: flag("skip_lock").unwrap_or(false),
+        });
+
+    Request {
+        action: text("action"),
+        target_region: text("target_region"),
+        force: flag("force"),
+        force_flags,
+        allow_same_region: flag("allow_same_region"),
+        self_test: flag("self_test").unwrap_or(false),
+        debug: flag("debug").unwrap_or(false),
+    }
+}
+
+// Individual emergency overrides; `force: true` sets all of them
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct ForceFlags {
+    pub skip_health: bool, // Proceed even if the target region is unhealthy
+    pub skip_lag: bool,    // Fail back even if replication lag exceeds the limit
+    pub skip_lock: bool,   // Proceed even if another operation holds the lock
+}
+
+impl ForceFlags {
+    pub fn all() -> Self {
+        Self {
+            skip_health: true,
+            skip_lag: true,
+            skip_lock: true,
+        }
+    }
+
+    pub fn resolve(force: Option<bool>, flags: Option<ForceFlags>) -> Self {
+        if force.unwrap_or(false) {
+            Self::all()
+        } else {
+            flags.unwrap_or_default()
+        }
+    }
+}
+
+const FAILOVER_LOCK_ID: &str = "failover_lock";
+const DEFAULT_LOCK_TTL_SECONDS: i64 = 300;
+const DEFAULT_MAX_FAILBACK_LAG_SECONDS: i64 = 60;
+// Bound on the health check plus status update of a failover, kept under the
+// 60s Lambda timeout so the controller always gets to respond
+const DEFAULT_FAILOVER_TIMEOUT_SECONDS: u64 = 45;
+// auto_failover only fails over after this many unhealthy probes in a row...
+const DEFAULT_CONSECUTIVE_UNHEALTHY_REQUIRED: u32 = 3;
+// ...all within this window of the first one
+const DEFAULT_UNHEALTHY_WINDOW_SECONDS: i64 = 300;
+const HEALTH_PROBE_STATE_ID: &str = "health_probe_state";
+// Operator kill switch for auto_failover, e.g. during planned maintenance
+const AUTOMATION_SWITCH_ID: &str = "failover_automation";
+// Standby health checks in flight at once; each new region resolves
+// credentials and builds a client, so an unbounded fan-out storms STS
+const DEFAULT_STANDBY_HEALTH_CONCURRENCY: usize = 3;
+// check_health probes a region at most this many times, deciding once enough
+// consecutive probes agree
+const DEFAULT_HEALTH_CHECK_ATTEMPTS: u32 = 5;
+const DEFAULT_HEALTH_CHECK_CONSECUTIVE: u32 = 2;
+const DEFAULT_HEALTH_CHECK_DELAY_MS: u64 = 200;
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Response {
     pub status: String,
     pub message: String,
     pub action: String,
     pub timestamp: String,
+    pub version: String,
+    // Set by auto_failover: how many health probes in a row have failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consecutive_unhealthy: Option<u32>,
+    // Where each config value came from, when the request set `debug`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_trace: Option<HashMap<String, String>>,
+    // cold_start and init_duration_ms, set by the handler
+    #[serde(flatten)]
+    pub init: Option<InitTiming>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -30,114 +150,912 @@ pub struct FailoverStatus {
     pub status: String,
 }
 
+const FAILOVER_STATUS_ID: &str = "failover_status";
+// One row per completed failover/failback, keyed "failover_history#<completed_at_ms>"
+const FAILOVER_HISTORY_PREFIX: &str = "failover_history#";
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RtoEstimate {
+    pub samples: usize,
+    pub average_seconds: f64,
+    pub p95_seconds: f64,
+}
+
+// Approved failover plan, stored as JSON in the SSM parameter named by
+// RUNBOOK_PARAMETER. When configured, failovers that don't match are rejected.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Runbook {
+    pub approved_target_region: String,
+    // Whether force flags may bypass the controller's own safety checks
+    #[serde(default)]
+    pub allow_force: bool,
+    // Replication lag into the target region must be measurable and within this
+    pub max_replication_lag_seconds: Option<i64>,
+}
+
+// Run of failed auto_failover health probes, persisted between invocations
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HealthProbeState {
+    pub consecutive_unhealthy: u32,
+    pub first_unhealthy_at: Option<i64>,
+}
+
 pub struct FailoverService {
     pub dynamo_client: DynamoClient,
+    pub cloudwatch_client: CloudWatchClient,
+    pub events: EventEmitter,
     pub current_region: String,
+    // The other region holding a copy of the failover status record
+    pub peer_region: String,
+    // SENTINEL_TABLE, read in each region for replication lag
+    pub sentinel_table: String,
+    // auto_failover targets, highest priority first; defaults to the peer region
+    pub standby_regions: Vec<String>,
+    pub standby_health_concurrency: usize,
+    pub health_check_attempts: u32,
+    pub health_check_consecutive: u32,
+    // Grows linearly with each retry
+    pub health_check_delay: std::time::Duration,
+    pub max_failback_lag_seconds: i64,
+    pub consecutive_unhealthy_required: u32,
+    pub unhealthy_window_seconds: i64,
+    pub failover_timeout: std::time::Duration,
+    pub ssm_client: SsmClient,
+    pub runbook_parameter: Option<String>,
+    pub sns_client: SnsClient,
+    // FAILOVER_TOPIC_ARN: paged with the outcome of every failover and
+    // failback; no notifications when unset
+    pub topic_arn: Option<String>,
+    region_clients: RegionCache<DynamoClient>,
+    // Source of each environment-backed setting above
+    pub config_trace: ConfigTrace,
 }
 
 impl FailoverService {
     pub async fn new() -> Result<Self, Error> {
-        let config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        let config = aws_config_loader().load().await;
 
         let current_region = std::env::var("AWS_REGION")?;
+        let max_failback_lag_seconds = std::env::var("MAX_FAILBACK_LAG_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_FAILBACK_LAG_SECONDS);
+        let consecutive_unhealthy_required = std::env::var("CONSECUTIVE_UNHEALTHY_REQUIRED")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_CONSECUTIVE_UNHEALTHY_REQUIRED)
+            .max(1);
+        let unhealthy_window_seconds = std::env::var("UNHEALTHY_WINDOW_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_UNHEALTHY_WINDOW_SECONDS);
+        let failover_timeout = std::time::Duration::from_secs(
+            std::env::var("FAILOVER_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_FAILOVER_TIMEOUT_SECONDS),
+        );
+
+        let runbook_parameter = std::env::var("RUNBOOK_PARAMETER")
+            .ok()
+            .filter(|value| !value.is_empty());
+        let topic_arn = std::env::var("FAILOVER_TOPIC_ARN")
+            .ok()
+            .filter(|value| !value.is_empty());
+
+        let peer_region = std::env::var("PEER_REGION").unwrap_or_else(|_| {
+            if current_region == "us-east-1" {
+                "us-west-2".to_string()
+            } else {
+                "us-east-1".to_string()
+            }
+        });
+        let standby_regions = std::env::var("STANDBY_REGIONS")
+            .ok()
+            .and_then(|value| parse_standby_regions(&value))
+            .unwrap_or_else(|| vec![peer_region.clone()]);
+        let standby_health_concurrency = std::env::var("STANDBY_HEALTH_CONCURRENCY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_STANDBY_HEALTH_CONCURRENCY)
+            .max(1);
+
+        let health_check_consecutive = std::env::var("HEALTH_CHECK_CONSECUTIVE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_HEALTH_CHECK_CONSECUTIVE)
+            .max(1);
+        let health_check_attempts = std::env::var("HEALTH_CHECK_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_HEALTH_CHECK_ATTEMPTS)
+            .max(health_check_consecutive);
+        let health_check_delay = std::time::Duration::from_millis(
+            std::env::var("HEALTH_CHECK_DELAY_MS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_HEALTH_CHECK_DELAY_MS),
+        );
+
+        let mut config_trace = ConfigTrace::new();
+        config_trace.record_env(&[
+            "AWS_REGION",
+            "PEER_REGION",
+            "STANDBY_REGIONS",
+            "STANDBY_HEALTH_CONCURRENCY",
+            "HEALTH_CHECK_ATTEMPTS",
+            "HEALTH_CHECK_CONSECUTIVE",
+            "HEALTH_CHECK_DELAY_MS",
+            "MAX_FAILBACK_LAG_SECONDS",
+            "CONSECUTIVE_UNHEALTHY_REQUIRED",
+            "UNHEALTHY_WINDOW_SECONDS",
+            "FAILOVER_TIMEOUT_SECONDS",
+            "RUNBOOK_PARAMETER",
+            "SENTINEL_TABLE",
+            "FAILOVER_TOPIC_ARN",
+        ]);
+        if runbook_parameter.is_some() {
+            config_trace.record("runbook", ConfigSource::Ssm);
+        }
 
         Ok(Self {
             dynamo_client: DynamoClient::new(&config),
+            cloudwatch_client: CloudWatchClient::new(&config),
+            events: EventEmitter::new(&config, env!("CARGO_PKG_NAME")),
             current_region,
+            peer_region,
+            sentinel_table: sentinel_table(),
+            standby_regions,
+            standby_health_concurrency,
+            health_check_attempts,
+            health_check_consecutive,
+            health_check_delay,
+            max_failback_lag_seconds,
+            consecutive_unhealthy_required,
+            unhealthy_window_seconds,
+            failover_timeout,
+            ssm_client: SsmClient::new(&config),
+            runbook_parameter,
+            sns_client: SnsClient::new(&config),
+            topic_arn,
+            region_clients: RegionCache::new(),
+            config_trace,
         })
     }
 
+    async fn client_for_region(&self, region: &str) -> DynamoClient {
+        if region == self.current_region {
+            return self.dynamo_client.clone();
+        }
+
+        self.region_clients
+            .get_or_load(region, |region| async move {
+                let config = aws_config_loader()
+                    .region(aws_config::Region::new(region))
+                    .load()
+                    .await;
+                DynamoClient::new(&config)
+            })
+            .await
+    }
+
+    // A configured runbook that can't be read or parsed is an error, so a
+    // broken parameter never silently disables the guardrail
+    pub async fn load_runbook(&self) -> Result<Option<Runbook>, Error> {
+        let name = match &self.runbook_parameter {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+
+        let output = self
+            .ssm_client
+            .get_parameter()
+            .name(name)
+            .with_decryption(true)
+            .send()
+            .await
+            .map_sdk_err("ssm:GetParameter", name)?;
+
+        let value = output
+            .parameter()
+            .and_then(|parameter| parameter.value())
+            .ok_or_else(|| Error::from(format!("Runbook parameter {} has no value", name)))?;
+
+        serde_json::from_str(value)
+            .map(Some)
+            .map_err(|e| Error::from(format!("Invalid runbook in {}: {}", name, e)))
+    }
+
+    // Probes until health_check_consecutive probes in a row agree, so one
+    // transient error during an incident can't block (or allow) a failover
     pub async fn check_health(&self, region: &str) -> Result<bool, Error> {
+        let mut results = Vec::new();
+
+        for attempt in 0..self.health_check_attempts {
+            if attempt > 0 {
+                tokio::time::sleep(self.health_check_delay * attempt).await;
+            }
+
+            results.push(self.probe_health(region).await?);
+            if let Some(healthy) = health_verdict(&results, self.health_check_consecutive) {
+                return Ok(healthy);
+            }
+        }
+
+        warn!(
+            "Health of {} flapped over {} probes ({:?}); treating it as unhealthy",
+            region,
+            results.len(),
+            results
+        );
+        Ok(false)
+    }
+
+    async fn probe_health(&self, region: &str) -> Result<bool, Error> {
         // In a real implementation, you would do more comprehensive health checks
         // This is a simplified version that just checks if we can connect to DynamoDB
 
-        // Use the current client if checking the current region
-        if region == self.current_region {
-            let result = self.dynamo_client.list_tables().limit(1).send().await;
-            return Ok(result.is_ok());
+        let client = self.client_for_region(region).await;
+        let result = client.list_tables().limit(1).send().await;
+
+        // Permissions problems must not look like an unhealthy region
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => match access_denied(&e, "dynamodb:ListTables", region) {
+                Some(denied) => Err(denied.into()),
+                None => {
+                    warn!("Health probe of {} failed: {}", region, e);
+                    Ok(false)
+                }
+            },
         }
+    }
 
-        // Otherwise, create a client for the target region
-        let config = aws_config::defaults(BehaviorVersion::latest())
-            .region(aws_config::Region::new(region.to_string()))
-            .load()
+    // Probes the standbys concurrently and settles on the highest-priority
+    // healthy one without waiting for lower-priority checks to finish
+    pub async fn select_failover_target(&self) -> Result<Option<String>, Error> {
+        let mut results = vec![None; self.standby_regions.len()];
+        let mut checks = futures::stream::iter(self.standby_regions.iter().enumerate())
+            .map(|(index, region)| async move { (index, self.check_health(region).await) })
+            .buffer_unordered(self.standby_health_concurrency);
+
+        while let Some((index, healthy)) = checks.next().await {
+            results[index] = Some(healthy?);
+            if let Some(selected) = preferred_healthy(&results) {
+                return Ok(selected.map(|index| self.standby_regions[index].clone()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Age of the sentinel record in the given region, or None if it can't be read
+    pub async fn check_replication_lag(&self, region: &str) -> Result<Option<i64>, Error> {
+        let client = self.client_for_region(region).await;
+        let result = client
+            .get_item()
+            .table_name(&self.sentinel_table)
+            .key("id", AttributeValue::S("sentinel".to_string()))
+            .send()
             .await;
 
-        let client = DynamoClient::new(&config);
-        let result = client.list_tables().limit(1).send().await;
+        let last_updated = result
+            .ok()
+            .and_then(|response| response.item)
+            .and_then(|item| item.get(&lag_timestamp_attr()).cloned())
+            .and_then(|attr| attr.as_n().ok().and_then(|n| n.parse::<i64>().ok()));
 
-        Ok(result.is_ok())
+        Ok(last_updated.map(|timestamp| Utc::now().timestamp() - timestamp))
     }
 
-    pub async fn update_failover_status(&self, to_region: &str, action: &str) -> Result<(), Error> {
+    // Take the failover lock; returns false if another operation holds it
+    pub async fn acquire_lock(&self, action: &str) -> Result<bool, Error> {
+        let now = Utc::now().timestamp();
+
+        let result = self
+            .dynamo_client
+            .put_item()
+            .table_name("dr-backup-metadata")
+            .item("backup_id", AttributeValue::S(FAILOVER_LOCK_ID.to_string()))
+            .item("action", AttributeValue::S(action.to_string()))
+            .item(
+                "expires_at",
+                AttributeValue::N((now + DEFAULT_LOCK_TTL_SECONDS).to_string()),
+            )
+            // An expired lock left behind by a crashed invocation can be taken over
+            .condition_expression("attribute_not_exists(backup_id) OR expires_at < :now")
+            .expression_attribute_values(":now", AttributeValue::N(now.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => match e.as_service_error() {
+                Some(PutItemError::ConditionalCheckFailedException(_)) => Ok(false),
+                _ => Err(map_sdk_error(e, "dynamodb:PutItem", "dr-backup-metadata")),
+            },
+        }
+    }
+
+    pub async fn release_lock(&self) -> Result<(), Error> {
         self.dynamo_client
+            .delete_item()
+            .table_name("dr-backup-metadata")
+            .key("backup_id", AttributeValue::S(FAILOVER_LOCK_ID.to_string()))
+            .send()
+            .await
+            .map_sdk_err("dynamodb:DeleteItem", "dr-backup-metadata")?;
+
+        Ok(())
+    }
+
+    pub async fn update_failover_status(&self, to_region: &str, action: &str) -> Result<(), Error> {
+        let status = FailoverStatus {
+            id: FAILOVER_STATUS_ID.to_string(),
+            timestamp: Utc::now().timestamp(),
+            action: action.to_string(),
+            source_region: self.current_region.clone(),
+            target_region: to_region.to_string(),
+            status: "completed".to_string(),
+        };
+
+        self.put_failover_status(&self.dynamo_client, &status).await
+    }
+
+    async fn put_failover_status(
+        &self,
+        client: &DynamoClient,
+        status: &FailoverStatus,
+    ) -> Result<(), Error> {
+        client
             .put_item()
             .table_name("dr-backup-metadata")
+            .item("backup_id", AttributeValue::S(status.id.clone()))
+            .item("timestamp", AttributeValue::N(status.timestamp.to_string()))
+            .item("action", AttributeValue::S(status.action.clone()))
             .item(
-                "backup_id",
-                aws_sdk_dynamodb::types::AttributeValue::S("failover_status".to_string()),
+                "source_region",
+                AttributeValue::S(status.source_region.clone()),
             )
             .item(
-                "timestamp",
-                aws_sdk_dynamodb::types::AttributeValue::N(Utc::now().timestamp().to_string()),
+                "target_region",
+                AttributeValue::S(status.target_region.clone()),
             )
+            .item("status", AttributeValue::S(status.status.clone()))
+            .send()
+            .await
+            .map_sdk_err("dynamodb:PutItem", "dr-backup-metadata")?;
+
+        Ok(())
+    }
+
+    async fn record_failover_history(
+        &self,
+        action: &str,
+        target_region: &str,
+        started_at_ms: i64,
+    ) -> Result<(), Error> {
+        let completed_at_ms = Utc::now().timestamp_millis();
+
+        self.dynamo_client
+            .put_item()
+            .table_name("dr-backup-metadata")
             .item(
-                "action",
-                aws_sdk_dynamodb::types::AttributeValue::S(action.to_string()),
+                "backup_id",
+                AttributeValue::S(format!("{}{}", FAILOVER_HISTORY_PREFIX, completed_at_ms)),
             )
+            .item("action", AttributeValue::S(action.to_string()))
             .item(
                 "source_region",
-                aws_sdk_dynamodb::types::AttributeValue::S(self.current_region.clone()),
+                AttributeValue::S(self.current_region.clone()),
             )
             .item(
                 "target_region",
-                aws_sdk_dynamodb::types::AttributeValue::S(to_region.to_string()),
+                AttributeValue::S(target_region.to_string()),
+            )
+            .item(
+                "started_at_ms",
+                AttributeValue::N(started_at_ms.to_string()),
             )
             .item(
-                "status",
-                aws_sdk_dynamodb::types::AttributeValue::S("completed".to_string()),
+                "completed_at_ms",
+                AttributeValue::N(completed_at_ms.to_string()),
             )
             .send()
+            .await
+            .map_sdk_err("dynamodb:PutItem", "dr-backup-metadata")?;
+
+        Ok(())
+    }
+
+    // Average and p95 time from start to completion of past failovers/failbacks
+    pub async fn estimate_rto(&self) -> Result<Option<RtoEstimate>, Error> {
+        let mut durations = Vec::new();
+        let mut last_evaluated_key = None;
+
+        loop {
+            let result = self
+                .dynamo_client
+                .scan()
+                .table_name("dr-backup-metadata")
+                .filter_expression("begins_with(backup_id, :prefix)")
+                .expression_attribute_values(
+                    ":prefix",
+                    AttributeValue::S(FAILOVER_HISTORY_PREFIX.to_string()),
+                )
+                .set_exclusive_start_key(last_evaluated_key)
+                .send()
+                .await
+                .map_sdk_err("dynamodb:Scan", "dr-backup-metadata")?;
+
+            durations.extend(
+                result
+                    .items
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(history_duration_seconds),
+            );
+
+            match result.last_evaluated_key {
+                Some(key) => last_evaluated_key = Some(key),
+                None => break,
+            }
+        }
+
+        let estimate = rto_estimate(&durations);
+
+        if let Some(estimate) = &estimate {
+            info!(
+                "RTO over {} failovers: average {:.1}s, p95 {:.1}s",
+                estimate.samples, estimate.average_seconds, estimate.p95_seconds
+            );
+
+            if let Err(e) = self.publish_rto_metrics(estimate).await {
+                error!("Failed to publish RTO metrics: {}", e);
+            }
+        }
+
+        Ok(estimate)
+    }
+
+    async fn publish_rto_metrics(&self, estimate: &RtoEstimate) -> Result<(), Error> {
+        let timestamp =
+            aws_sdk_cloudwatch::primitives::DateTime::from(std::time::SystemTime::now());
+
+        let metrics = [
+            ("Average", estimate.average_seconds),
+            ("p95", estimate.p95_seconds),
+        ]
+        .into_iter()
+        .map(|(statistic, value)| {
+            MetricDatum::builder()
+                .metric_name("RtoSeconds")
+                .value(value)
+                .unit(StandardUnit::Seconds)
+                .dimensions(
+                    Dimension::builder()
+                        .name("Statistic")
+                        .value(statistic)
+                        .build(),
+                )
+                .timestamp(timestamp)
+                .build()
+        })
+        .collect();
+
+        self.cloudwatch_client
+            .put_metric_data()
+            .namespace("DisasterRecovery")
+            .set_metric_data(Some(metrics))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_health_probe_state(&self) -> Result<HealthProbeState, Error> {
+        let result = self
+            .dynamo_client
+            .get_item()
+            .table_name("dr-backup-metadata")
+            .key(
+                "backup_id",
+                AttributeValue::S(HEALTH_PROBE_STATE_ID.to_string()),
+            )
+            .consistent_read(true)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:GetItem", "dr-backup-metadata")?;
+
+        Ok(result
+            .item
+            .as_ref()
+            .map(health_probe_state_from_item)
+            .unwrap_or_default())
+    }
+
+    async fn put_health_probe_state(&self, state: HealthProbeState) -> Result<(), Error> {
+        let mut request = self
+            .dynamo_client
+            .put_item()
+            .table_name("dr-backup-metadata")
+            .item(
+                "backup_id",
+                AttributeValue::S(HEALTH_PROBE_STATE_ID.to_string()),
+            )
+            .item(
+                "consecutive_unhealthy",
+                AttributeValue::N(state.consecutive_unhealthy.to_string()),
+            );
+
+        if let Some(first_unhealthy_at) = state.first_unhealthy_at {
+            request = request.item(
+                "first_unhealthy_at",
+                AttributeValue::N(first_unhealthy_at.to_string()),
+            );
+        }
+
+        request
+            .send()
+            .await
+            .map_sdk_err("dynamodb:PutItem", "dr-backup-metadata")?;
+
+        Ok(())
+    }
+
+    // Probe this region and fold the result into the stored run of failures
+    pub async fn record_health_probe(&self) -> Result<HealthProbeState, Error> {
+        let healthy = self.check_health(&self.current_region).await?;
+        let previous = self.get_health_probe_state().await?;
+        let state = next_probe_state(
+            previous,
+            healthy,
+            Utc::now().timestamp(),
+            self.unhealthy_window_seconds,
+        );
+
+        self.put_health_probe_state(state).await?;
+        Ok(state)
+    }
+
+    // Automation is enabled unless an operator has explicitly paused it
+    pub async fn is_automation_enabled(&self) -> Result<bool, Error> {
+        let result = self
+            .dynamo_client
+            .get_item()
+            .table_name("dr-backup-metadata")
+            .key(
+                "backup_id",
+                AttributeValue::S(AUTOMATION_SWITCH_ID.to_string()),
+            )
+            .consistent_read(true)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:GetItem", "dr-backup-metadata")?;
+
+        Ok(result
+            .item
+            .as_ref()
+            .and_then(|item| item.get("failover_automation_enabled"))
+            .and_then(|value| value.as_bool().ok())
+            .copied()
+            .unwrap_or(true))
+    }
+
+    pub async fn set_automation_enabled(&self, enabled: bool) -> Result<(), Error> {
+        self.dynamo_client
+            .put_item()
+            .table_name("dr-backup-metadata")
+            .item(
+                "backup_id",
+                AttributeValue::S(AUTOMATION_SWITCH_ID.to_string()),
+            )
+            .item("failover_automation_enabled", AttributeValue::Bool(enabled))
+            .item("updated_at", AttributeValue::S(Utc::now().to_rfc3339()))
+            .send()
+            .await
+            .map_sdk_err("dynamodb:PutItem", "dr-backup-metadata")?;
+
+        info!(
+            "Failover automation {}",
+            if enabled { "resumed" } else { "paused" }
+        );
+        Ok(())
+    }
+
+    // Debounced failover: only once this region has failed
+    // consecutive_unhealthy_required probes in a row does it fail over to the peer
+    async fn auto_failover(&self, force: ForceFlags) -> Result<Response, Error> {
+        // Checked before probing so a paused controller doesn't build up a
+        // run of failures that fires the moment automation is resumed
+        if !self.is_automation_enabled().await? {
+            warn!("Failover automation is paused; skipping auto_failover");
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: None,
+                config_trace: None,
+                status: "paused".to_string(),
+                message: "Failover automation is paused".to_string(),
+                action: "auto_failover".to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+
+        let probe = self.record_health_probe().await?;
+        let count = probe.consecutive_unhealthy;
+
+        if count < self.consecutive_unhealthy_required {
+            let message = if count == 0 {
+                format!("Region {} is healthy", self.current_region)
+            } else {
+                format!(
+                    "Region {} unhealthy for {} of {} required consecutive checks; not failing over yet",
+                    self.current_region, count, self.consecutive_unhealthy_required
+                )
+            };
+
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: Some(count),
+                config_trace: None,
+                status: "success".to_string(),
+                message,
+                action: "auto_failover".to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+
+        let target_region = match self.select_failover_target().await? {
+            Some(region) => region,
+            // run_action checks the target again, so this only goes through
+            // when the caller chose to skip health checks
+            None if force.skip_health => self
+                .standby_regions
+                .first()
+                .cloned()
+                .unwrap_or_else(|| self.peer_region.clone()),
+            None => {
+                warn!(
+                    "Region {} unhealthy but no standby region is healthy",
+                    self.current_region
+                );
+                return Ok(Response {
+                    version: version(),
+                    init: None,
+                    consecutive_unhealthy: Some(count),
+                    config_trace: None,
+                    status: "failed".to_string(),
+                    message: format!(
+                        "No healthy standby region among {}",
+                        self.standby_regions.join(", ")
+                    ),
+                    action: "auto_failover".to_string(),
+                    timestamp: Utc::now().to_rfc3339(),
+                });
+            }
+        };
+
+        warn!(
+            "Region {} unhealthy for {} consecutive checks, failing over to {}",
+            self.current_region, count, target_region
+        );
+
+        let mut response = self
+            .run_action("failover", &target_region, force, false)
+            .await?;
+
+        // Start counting afresh so the next run doesn't fail over again
+        if response.status == "success" {
+            self.put_health_probe_state(HealthProbeState::default())
+                .await?;
+        }
+
+        response.consecutive_unhealthy = Some(count);
+        Ok(response)
+    }
+
+    // The failover status recorded in this region, without reconciling
+    pub async fn current_status(&self) -> Result<Option<FailoverStatus>, Error> {
+        self.get_failover_status(&self.dynamo_client).await
+    }
+
+    async fn get_failover_status(
+        &self,
+        client: &DynamoClient,
+    ) -> Result<Option<FailoverStatus>, Error> {
+        let result = client
+            .get_item()
+            .table_name("dr-backup-metadata")
+            .key(
+                "backup_id",
+                AttributeValue::S(FAILOVER_STATUS_ID.to_string()),
+            )
+            .consistent_read(true)
+            .send()
+            .await
+            .map_sdk_err("dynamodb:GetItem", "dr-backup-metadata")?;
+
+        Ok(result.item.as_ref().and_then(failover_status_from_item))
+    }
+
+    // The status record lives in whichever region ran the last failover. Read it
+    // from both regions, keep the newest, and write it back to both.
+    pub async fn reconcile_status(&self) -> Result<Option<FailoverStatus>, Error> {
+        let peer_client = self.client_for_region(&self.peer_region).await;
+
+        let local = self.get_failover_status(&self.dynamo_client).await?;
+        let peer = self.get_failover_status(&peer_client).await?;
+
+        let reconciled = match latest_status(local, peer) {
+            Some(status) => status,
+            None => return Ok(None),
+        };
+
+        self.put_failover_status(&self.dynamo_client, &reconciled)
             .await?;
+        self.put_failover_status(&peer_client, &reconciled).await?;
+
+        info!(
+            "Reconciled failover status across {} and {}: {} to {} at {}",
+            self.current_region,
+            self.peer_region,
+            reconciled.action,
+            reconciled.target_region,
+            reconciled.timestamp
+        );
+
+        Ok(Some(reconciled))
+    }
+
+    pub async fn notify(&self, subject: &str, message: &str) -> Result<(), Error> {
+        let topic_arn = match &self.topic_arn {
+            Some(topic_arn) => topic_arn,
+            None => return Ok(()),
+        };
+
+        let input = publish_input(topic_arn, subject, message)?;
+        self.sns_client
+            .publish()
+            .set_topic_arn(input.topic_arn)
+            .set_subject(input.subject)
+            .set_message(input.message)
+            .send()
+            .await
+            .map_sdk_err("sns:Publish", topic_arn)?;
 
         Ok(())
     }
 
+    // Pages operators with the outcome, whichever way the action ended
+    async fn notify_outcome(
+        &self,
+        action: &str,
+        target_region: &str,
+        outcome: &Result<Response, Error>,
+    ) {
+        let (subject, message) =
+            outcome_notification(action, &self.current_region, target_region, outcome);
+        if let Err(e) = self.notify(&subject, &message).await {
+            error!("Failed to send {} notification: {}", action, e);
+        }
+    }
+
     pub async fn execute_failover(
         &self,
         target_region: &str,
-        force: bool,
+        force: ForceFlags,
+        allow_same_region: bool,
+    ) -> Result<Response, Error> {
+        let result = self
+            .try_failover(target_region, force, allow_same_region)
+            .await;
+        self.notify_outcome("failover", target_region, &result)
+            .await;
+        result
+    }
+
+    async fn try_failover(
+        &self,
+        target_region: &str,
+        force: ForceFlags,
+        allow_same_region: bool,
     ) -> Result<Response, Error> {
         info!("Executing failover to region: {}", target_region);
+        let started_at_ms = Utc::now().timestamp_millis();
 
-        // Check health of target region
-        if !force {
-            let is_healthy = self.check_health(target_region).await?;
+        if target_region == self.current_region && !allow_same_region {
+            warn!(
+                "Refusing failover: target region {} equals current region. Set allow_same_region to override.",
+                target_region
+            );
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: None,
+                config_trace: None,
+                status: "failed".to_string(),
+                message: format!("Target region {} equals current region", target_region),
+                action: "failover".to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
 
-            if !is_healthy {
-                warn!(
-                    "Target region {} is not healthy. Use force=true to override.",
-                    target_region
+        // Both steps talk to the target region, which may be hanging
+        let steps = async {
+            // Check health of target region
+            if !force.skip_health {
+                let is_healthy = self.check_health(target_region).await?;
+
+                if !is_healthy {
+                    warn!(
+                        "Target region {} is not healthy. Use force=true to override.",
+                        target_region
+                    );
+                    return Ok(Some(Response {
+                        version: version(),
+                        init: None,
+                        consecutive_unhealthy: None,
+                        config_trace: None,
+                        status: "failed".to_string(),
+                        message: format!("Target region {} is not healthy", target_region),
+                        action: "failover".to_string(),
+                        timestamp: Utc::now().to_rfc3339(),
+                    }));
+                }
+            }
+
+            // In a real implementation, you would:
+            // 1. Update DNS to point to the DR region
+            // 2. Promote standby resources to active
+            // 3. Scale up resources as needed
+
+            // Update failover status
+            self.update_failover_status(target_region, "failover")
+                .await?;
+
+            Ok::<_, Error>(None)
+        };
+
+        match tokio::time::timeout(self.failover_timeout, steps).await {
+            Ok(Ok(None)) => {}
+            Ok(Ok(Some(response))) => return Ok(response),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => {
+                error!(
+                    "Failover to {} timed out after {:?}",
+                    target_region, self.failover_timeout
                 );
                 return Ok(Response {
-                    status: "failed".to_string(),
-                    message: format!("Target region {} is not healthy", target_region),
+                    version: version(),
+                    init: None,
+                    consecutive_unhealthy: None,
+                    config_trace: None,
+                    status: "timed_out".to_string(),
+                    message: format!(
+                        "Failover to region {} did not finish within {}s; run reconcile to check the recorded status",
+                        target_region,
+                        self.failover_timeout.as_secs()
+                    ),
                     action: "failover".to_string(),
                     timestamp: Utc::now().to_rfc3339(),
                 });
             }
         }
 
-        // In a real implementation, you would:
-        // 1. Update DNS to point to the DR region
-        // 2. Promote standby resources to active
-        // 3. Scale up resources as needed
-
-        // Update failover status
-        self.update_failover_status(target_region, "failover")
-            .await?;
+        if let Err(e) = self
+            .record_failover_history("failover", target_region, started_at_ms)
+            .await
+        {
+            error!("Failed to record failover history: {}", e);
+        }
 
         Ok(Response {
+            version: version(),
+            init: None,
+            consecutive_unhealthy: None,
+            config_trace: None,
             status: "success".to_string(),
             message: format!("Failover to region {} completed", target_region),
             action: "failover".to_string(),
@@ -148,12 +1066,24 @@ impl FailoverService {
     pub async fn execute_failback(
         &self,
         target_region: &str,
-        force: bool,
+        force: ForceFlags,
+    ) -> Result<Response, Error> {
+        let result = self.try_failback(target_region, force).await;
+        self.notify_outcome("failback", target_region, &result)
+            .await;
+        result
+    }
+
+    async fn try_failback(
+        &self,
+        target_region: &str,
+        force: ForceFlags,
     ) -> Result<Response, Error> {
         info!("Executing failback to region: {}", target_region);
+        let started_at_ms = Utc::now().timestamp_millis();
 
         // Check health of target region
-        if !force {
+        if !force.skip_health {
             let is_healthy = self.check_health(target_region).await?;
 
             if !is_healthy {
@@ -162,6 +1092,10 @@ impl FailoverService {
                     target_region
                 );
                 return Ok(Response {
+                    version: version(),
+                    init: None,
+                    consecutive_unhealthy: None,
+                    config_trace: None,
                     status: "failed".to_string(),
                     message: format!("Target region {} is not healthy", target_region),
                     action: "failback".to_string(),
@@ -170,6 +1104,36 @@ impl FailoverService {
             }
         }
 
+        // Failing back before the primary has caught up would lose DR-side writes
+        if !force.skip_lag {
+            match self.check_replication_lag(target_region).await? {
+                Some(lag) if lag > self.max_failback_lag_seconds => {
+                    warn!(
+                        "Replication lag to {} is {}s (limit {}s). Use force_flags.skip_lag to override.",
+                        target_region, lag, self.max_failback_lag_seconds
+                    );
+                    return Ok(Response {
+                        version: version(),
+                        init: None,
+                        consecutive_unhealthy: None,
+                        config_trace: None,
+                        status: "failed".to_string(),
+                        message: format!(
+                            "Replication lag to region {} is {} seconds, above the {} second limit",
+                            target_region, lag, self.max_failback_lag_seconds
+                        ),
+                        action: "failback".to_string(),
+                        timestamp: Utc::now().to_rfc3339(),
+                    });
+                }
+                Some(_) => {}
+                None => warn!(
+                    "Could not determine replication lag for {}; proceeding with failback",
+                    target_region
+                ),
+            }
+        }
+
         // In a real implementation, you would:
         // 1. Verify data synchronization
         // 2. Update DNS to point back to primary region
@@ -179,7 +1143,18 @@ impl FailoverService {
         self.update_failover_status(target_region, "failback")
             .await?;
 
+        if let Err(e) = self
+            .record_failover_history("failback", target_region, started_at_ms)
+            .await
+        {
+            error!("Failed to record failback history: {}", e);
+        }
+
         Ok(Response {
+            version: version(),
+            init: None,
+            consecutive_unhealthy: None,
+            config_trace: None,
             status: "success".to_string(),
             message: format!("Failback to region {} completed", target_region),
             action: "failback".to_string(),
@@ -191,32 +1166,449 @@ impl FailoverService {
         &self,
         action: &str,
         target_region: &str,
-        force: bool,
+        force: ForceFlags,
+        allow_same_region: bool,
     ) -> Result<Response, Error> {
-        match action {
-            "failover" => self.execute_failover(target_region, force).await,
-            "failback" => self.execute_failback(target_region, force).await,
-            _ => {
-                error!("Invalid action: {}", action);
-                Ok(Response {
-                    status: "failed".to_string(),
-                    message: format!("Invalid action: {}", action),
-                    action: action.to_string(),
-                    timestamp: Utc::now().to_rfc3339(),
-                })
+        if action == "reconcile" {
+            let message = match self.reconcile_status().await? {
+                Some(status) => format!(
+                    "Failover status reconciled: {} from {} to {} at {}",
+                    status.action, status.source_region, status.target_region, status.timestamp
+                ),
+                None => "No failover status recorded in either region".to_string(),
+            };
+
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: None,
+                config_trace: None,
+                status: "success".to_string(),
+                message,
+                action: action.to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+
+        if action == "estimate_rto" {
+            let message = match self.estimate_rto().await? {
+                Some(estimate) => format!(
+                    "RTO over {} failovers: average {:.1}s, p95 {:.1}s",
+                    estimate.samples, estimate.average_seconds, estimate.p95_seconds
+                ),
+                None => "No completed failovers recorded".to_string(),
+            };
+
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: None,
+                config_trace: None,
+                status: "success".to_string(),
+                message,
+                action: action.to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+
+        if action == "auto_failover" {
+            return self.auto_failover(force).await;
+        }
+
+        if action == "pause_automation" || action == "resume_automation" {
+            let enabled = action == "resume_automation";
+            self.set_automation_enabled(enabled).await?;
+
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: None,
+                config_trace: None,
+                status: "success".to_string(),
+                message: format!(
+                    "Failover automation {}",
+                    if enabled { "resumed" } else { "paused" }
+                ),
+                action: action.to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+
+        // Reject bad input before any health check or lock is attempted
+        if let Some(reason) = invalid_request(action, target_region) {
+            error!("{}", reason);
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: None,
+                config_trace: None,
+                status: "failed".to_string(),
+                message: reason,
+                action: action.to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+
+        self.run_action(action, target_region, force, allow_same_region)
+            .await
+    }
+
+    async fn run_action(
+        &self,
+        action: &str,
+        target_region: &str,
+        force: ForceFlags,
+        allow_same_region: bool,
+    ) -> Result<Response, Error> {
+        if action == "failover" {
+            if let Some(runbook) = self.load_runbook().await? {
+                let lag = match runbook.max_replication_lag_seconds {
+                    Some(_) => self.check_replication_lag(target_region).await?,
+                    None => None,
+                };
+
+                if let Err(reason) = check_runbook(&runbook, target_region, force, lag) {
+                    warn!("Runbook rejected failover to {}: {}", target_region, reason);
+                    return Ok(Response {
+                        version: version(),
+                        init: None,
+                        consecutive_unhealthy: None,
+                        config_trace: None,
+                        status: "rejected".to_string(),
+                        message: format!("Runbook rejected failover: {}", reason),
+                        action: action.to_string(),
+                        timestamp: Utc::now().to_rfc3339(),
+                    });
+                }
+            }
+        }
+
+        // Serialize failovers and failbacks so two invocations can't race
+        if !force.skip_lock && !self.acquire_lock(action).await? {
+            warn!("Failover lock is held. Use force_flags.skip_lock to override.");
+            return Ok(Response {
+                version: version(),
+                init: None,
+                consecutive_unhealthy: None,
+                config_trace: None,
+                status: "failed".to_string(),
+                message: "Another failover or failback is already in progress".to_string(),
+                action: action.to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+            });
+        }
+
+        let result = match action {
+            "failover" => {
+                self.execute_failover(target_region, force, allow_same_region)
+                    .await
+            }
+            _ => self.execute_failback(target_region, force).await,
+        };
+
+        if !force.skip_lock {
+            if let Err(e) = self.release_lock().await {
+                error!("Failed to release failover lock: {}", e);
+            }
+        }
+
+        if let Ok(response) = &result {
+            let detail_type = match (action, response.status.as_str()) {
+                ("failover", "success") => "Failover Completed",
+                ("failover", _) => "Failover Failed",
+                (_, "success") => "Failback Completed",
+                _ => "Failback Failed",
+            };
+            self.events.emit_event(detail_type, response).await;
+        }
+
+        result
+    }
+}
+
+// SNS subject and body for a finished failover or failback. An error, e.g. a
+// failed status write, is reported as failed with the error as the reason.
+pub fn outcome_notification(
+    action: &str,
+    source_region: &str,
+    target_region: &str,
+    outcome: &Result<Response, Error>,
+) -> (String, String) {
+    let (status, detail, timestamp) = match outcome {
+        Ok(response) => (
+            response.status.as_str(),
+            response.message.clone(),
+            response.timestamp.clone(),
+        ),
+        Err(e) => ("failed", e.to_string(), Utc::now().to_rfc3339()),
+    };
+
+    let mut verb = action.to_string();
+    if let Some(first) = verb.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    let subject = format!("{} to {} {}", verb, target_region, status);
+    let message = format!(
+        "Action: {}\nStatus: {}\nSource region: {}\nTarget region: {}\nTimestamp: {}\nDetails: {}",
+        action, status, source_region, target_region, timestamp, detail
+    );
+
+    (subject, message)
+}
+
+pub fn publish_input(
+    topic_arn: &str,
+    subject: &str,
+    message: &str,
+) -> Result<PublishInput, BuildError> {
+    PublishInput::builder()
+        .topic_arn(topic_arn)
+        .subject(subject)
+        .message(message)
+        .build()
+}
+
+// Healthy or unhealthy once the last `consecutive` probe results agree;
+// None while they don't yet
+pub fn health_verdict(results: &[bool], consecutive: u32) -> Option<bool> {
+    let consecutive = consecutive.max(1) as usize;
+    let recent = results.get(results.len().checked_sub(consecutive)?..)?;
+    let first = recent[0];
+    recent
+        .iter()
+        .all(|&healthy| healthy == first)
+        .then_some(first)
+}
+
+// STANDBY_REGIONS, comma separated in priority order
+pub fn parse_standby_regions(value: &str) -> Option<Vec<String>> {
+    let regions: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|region| !region.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if regions.is_empty() {
+        None
+    } else {
+        Some(regions)
+    }
+}
+
+// Health results in priority order, None while a check is still running.
+// Decided once every region ahead of the first healthy one has reported;
+// Some(None) means no standby is healthy.
+pub fn preferred_healthy(results: &[Option<bool>]) -> Option<Option<usize>> {
+    for (index, result) in results.iter().enumerate() {
+        match result {
+            None => return None,
+            Some(true) => return Some(Some(index)),
+            Some(false) => {}
+        }
+    }
+    Some(None)
+}
+
+pub fn failover_status_from_item(item: &HashMap<String, AttributeValue>) -> Option<FailoverStatus> {
+    let string = |name: &str| item.get(name)?.as_s().ok().cloned();
+
+    Some(FailoverStatus {
+        id: string("backup_id")?,
+        timestamp: item.get("timestamp")?.as_n().ok()?.parse().ok()?,
+        action: string("action")?,
+        source_region: string("source_region")?,
+        target_region: string("target_region")?,
+        status: string("status")?,
+    })
+}
+
+pub fn health_probe_state_from_item(item: &HashMap<String, AttributeValue>) -> HealthProbeState {
+    let number = |name: &str| item.get(name)?.as_n().ok()?.parse::<i64>().ok();
+
+    HealthProbeState {
+        consecutive_unhealthy: number("consecutive_unhealthy").unwrap_or(0) as u32,
+        first_unhealthy_at: number("first_unhealthy_at"),
+    }
+}
+
+// A healthy probe clears the run. An unhealthy one extends it, or starts a new
+// run if the current one began more than `window_seconds` ago.
+pub fn next_probe_state(
+    previous: HealthProbeState,
+    healthy: bool,
+    now: i64,
+    window_seconds: i64,
+) -> HealthProbeState {
+    if healthy {
+        return HealthProbeState::default();
+    }
+
+    match previous.first_unhealthy_at {
+        Some(first) if previous.consecutive_unhealthy > 0 && now - first <= window_seconds => {
+            HealthProbeState {
+                consecutive_unhealthy: previous.consecutive_unhealthy + 1,
+                first_unhealthy_at: Some(first),
             }
         }
+        _ => HealthProbeState {
+            consecutive_unhealthy: 1,
+            first_unhealthy_at: Some(now),
+        },
+    }
+}
+
+pub fn history_duration_seconds(item: &HashMap<String, AttributeValue>) -> Option<f64> {
+    let millis = |name: &str| item.get(name)?.as_n().ok()?.parse::<i64>().ok();
+    let duration_ms = millis("completed_at_ms")? - millis("started_at_ms")?;
+
+    (duration_ms >= 0).then(|| duration_ms as f64 / 1000.0)
+}
+
+// p95 uses the nearest-rank method
+pub fn rto_estimate(durations: &[f64]) -> Option<RtoEstimate> {
+    if durations.is_empty() {
+        return None;
+    }
+
+    let mut sorted = durations.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let rank = ((sorted.len() as f64) * 0.95).ceil() as usize;
+
+    Some(RtoEstimate {
+        samples: sorted.len(),
+        average_seconds: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        p95_seconds: sorted[rank.max(1) - 1],
+    })
+}
+
+// Latest timestamp wins; ties keep the first argument
+pub fn latest_status(
+    a: Option<FailoverStatus>,
+    b: Option<FailoverStatus>,
+) -> Option<FailoverStatus> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.timestamp > a.timestamp { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+// Package version plus the git hash of the build, reported in every response
+pub fn version() -> String {
+    build_version(env!("CARGO_PKG_VERSION"))
+}
+
+pub fn self_test_response() -> Response {
+    Response {
+        version: version(),
+        init: None,
+        consecutive_unhealthy: None,
+        config_trace: None,
+        status: "success".to_string(),
+        message: "Self test passed".to_string(),
+        action: "self_test".to_string(),
+        timestamp: Utc::now().to_rfc3339(),
     }
 }
 
 // Utility functions for testing
+pub fn check_runbook(
+    runbook: &Runbook,
+    target_region: &str,
+    force: ForceFlags,
+    replication_lag: Option<i64>,
+) -> Result<(), String> {
+    if target_region != runbook.approved_target_region {
+        return Err(format!(
+            "target region {} is not the approved region {}",
+            target_region, runbook.approved_target_region
+        ));
+    }
+
+    if force != ForceFlags::default() && !runbook.allow_force {
+        return Err("force flags are not allowed by the runbook".to_string());
+    }
+
+    if let Some(max_lag) = runbook.max_replication_lag_seconds {
+        match replication_lag {
+            None => return Err("replication lag could not be measured".to_string()),
+            Some(lag) if lag > max_lag => {
+                return Err(format!(
+                    "replication lag {}s exceeds the runbook limit of {}s",
+                    lag, max_lag
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
 pub fn validate_action(action: &str) -> bool {
     matches!(action, "failover" | "failback")
 }
 
+// Why a failover or failback request can't run, or None when it can
+pub fn invalid_request(action: &str, target_region: &str) -> Option<String> {
+    if !validate_action(action) {
+        return Some(format!("Invalid action: {}", action));
+    }
+    if target_region.is_empty() {
+        return Some(format!("target_region is required for {}", action));
+    }
+    if !validate_region(target_region) {
+        return Some(format!("Invalid target region: {}", target_region));
+    }
+    None
+}
+
+// Regions a failover can target: the commercial, GovCloud and China
+// partitions. A name that merely looks like a region, e.g. us-fake-99, is
+// rejected rather than failing later inside an AWS call.
+pub const AWS_REGIONS: &[&str] = &[
+    "af-south-1",
+    "ap-east-1",
+    "ap-east-2",
+    "ap-northeast-1",
+    "ap-northeast-2",
+    "ap-northeast-3",
+    "ap-south-1",
+    "ap-south-2",
+    "ap-southeast-1",
+    "ap-southeast-2",
+    "ap-southeast-3",
+    "ap-southeast-4",
+    "ap-southeast-5",
+    "ap-southeast-7",
+    "ca-central-1",
+    "ca-west-1",
+    "cn-north-1",
+    "cn-northwest-1",
+    "eu-central-1",
+    "eu-central-2",
+    "eu-north-1",
+    "eu-south-1",
+    "eu-south-2",
+    "eu-west-1",
+    "eu-west-2",
+    "eu-west-3",
+    "il-central-1",
+    "me-central-1",
+    "me-south-1",
+    "mx-central-1",
+    "sa-east-1",
+    "us-east-1",
+    "us-east-2",
+    "us-gov-east-1",
+    "us-gov-west-1",
+    "us-west-1",
+    "us-west-2",
+];
+
 pub fn validate_region(region: &str) -> bool {
-    // Basic validation - in production, you'd check against a list of valid AWS regions
-    !region.is_empty() && region.contains('-')
+    AWS_REGIONS.contains(&region)
 }
 
 #[cfg(test)]
@@ -230,11 +1622,53 @@ mod tests {
         assert_eq!(request.action, "failover");
         assert_eq!(request.target_region, "us-west-2");
         assert_eq!(request.force, Some(true));
+        assert_eq!(request.force_flags, None);
+    }
+
+    #[test]
+    fn test_force_flags_resolution() {
+        // The boolean shorthand sets every flag
+        assert_eq!(ForceFlags::resolve(Some(true), None), ForceFlags::all());
+        assert_eq!(
+            ForceFlags::resolve(
+                Some(true),
+                Some(ForceFlags {
+                    skip_health: false,
+                    skip_lag: false,
+                    skip_lock: false,
+                })
+            ),
+            ForceFlags::all()
+        );
+
+        // Individual flags apply on their own
+        let flags = ForceFlags {
+            skip_health: false,
+            skip_lag: true,
+            skip_lock: false,
+        };
+        assert_eq!(ForceFlags::resolve(Some(false), Some(flags)), flags);
+        assert_eq!(ForceFlags::resolve(None, None), ForceFlags::default());
+    }
+
+    #[test]
+    fn test_force_flags_deserialization() {
+        let json = r#"{"action": "failback", "target_region": "us-east-1", "force_flags": {"skip_lag": true}}"#;
+        let request: Request = serde_json::from_str(json).unwrap();
+        let flags = ForceFlags::resolve(request.force, request.force_flags);
+
+        assert!(!flags.skip_health);
+        assert!(flags.skip_lag);
+        assert!(!flags.skip_lock);
     }
 
     #[test]
     fn test_response_serialization() {
         let response = Response {
+            version: version(),
+            init: None,
+            consecutive_unhealthy: None,
+            config_trace: None,
             status: "success".to_string(),
             message: "Failover completed".to_string(),
             action: "failover".to_string(),
@@ -260,6 +1694,14 @@ mod tests {
         assert!(validate_region("eu-west-1"));
         assert!(!validate_region("invalid"));
         assert!(!validate_region(""));
+        assert!(validate_region("us-gov-west-1"));
+        assert!(validate_region("us-gov-east-1"));
+        assert!(!validate_region("eu-gov-west-1"));
+        assert!(!validate_region("us-west"));
+        assert!(!validate_region("us-upward-1"));
+        assert!(!validate_region("us-fake-99"));
+        assert!(!validate_region("US-EAST-1"));
+        assert!(!validate_region(" us-east-1"));
     }
 
     #[test]
@@ -276,4 +1718,327 @@ mod tests {
         assert_eq!(status.id, "failover_status");
         assert_eq!(status.action, "failover");
     }
+
+    fn status_at(timestamp: i64, target_region: &str) -> FailoverStatus {
+        FailoverStatus {
+            id: "failover_status".to_string(),
+            timestamp,
+            action: "failover".to_string(),
+            source_region: "us-east-1".to_string(),
+            target_region: target_region.to_string(),
+            status: "completed".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_latest_status_wins() {
+        let older = status_at(100, "us-west-2");
+        let newer = status_at(200, "us-east-1");
+
+        assert_eq!(
+            latest_status(Some(older.clone()), Some(newer.clone()))
+                .unwrap()
+                .timestamp,
+            200
+        );
+        assert_eq!(
+            latest_status(Some(newer), Some(older.clone()))
+                .unwrap()
+                .timestamp,
+            200
+        );
+        assert_eq!(
+            latest_status(None, Some(older.clone())).unwrap().timestamp,
+            100
+        );
+        assert_eq!(latest_status(Some(older), None).unwrap().timestamp, 100);
+        assert!(latest_status(None, None).is_none());
+    }
+
+    #[test]
+    fn test_failover_status_from_item() {
+        let mut item = HashMap::new();
+        item.insert(
+            "backup_id".to_string(),
+            AttributeValue::S("failover_status".to_string()),
+        );
+        item.insert(
+            "timestamp".to_string(),
+            AttributeValue::N("1704556800".to_string()),
+        );
+        item.insert(
+            "action".to_string(),
+            AttributeValue::S("failover".to_string()),
+        );
+        item.insert(
+            "source_region".to_string(),
+            AttributeValue::S("us-east-1".to_string()),
+        );
+        item.insert(
+            "target_region".to_string(),
+            AttributeValue::S("us-west-2".to_string()),
+        );
+        item.insert(
+            "status".to_string(),
+            AttributeValue::S("completed".to_string()),
+        );
+
+        let status = failover_status_from_item(&item).unwrap();
+        assert_eq!(status.timestamp, 1704556800);
+        assert_eq!(status.target_region, "us-west-2");
+
+        item.remove("timestamp");
+        assert!(failover_status_from_item(&item).is_none());
+    }
+
+    #[test]
+    fn test_self_test_request() {
+        let request: Request = serde_json::from_str(r#"{"self_test": true}"#).unwrap();
+        assert!(request.self_test);
+        assert!(request.action.is_empty());
+
+        let response = self_test_response();
+        assert_eq!(response.status, "success");
+        assert_eq!(response.action, "self_test");
+    }
+
+    #[test]
+    fn test_rto_estimate() {
+        assert_eq!(rto_estimate(&[]), None);
+
+        let single = rto_estimate(&[42.0]).unwrap();
+        assert_eq!(single.samples, 1);
+        assert_eq!(single.average_seconds, 42.0);
+        assert_eq!(single.p95_seconds, 42.0);
+
+        let durations: Vec<f64> = (1..=20).map(|d| d as f64).collect();
+        let estimate = rto_estimate(&durations).unwrap();
+        assert_eq!(estimate.samples, 20);
+        assert_eq!(estimate.average_seconds, 10.5);
+        assert_eq!(estimate.p95_seconds, 19.0);
+    }
+
+    #[test]
+    fn test_history_duration_seconds() {
+        let mut item = HashMap::new();
+        item.insert(
+            "started_at_ms".to_string(),
+            AttributeValue::N("1704556800000".to_string()),
+        );
+        item.insert(
+            "completed_at_ms".to_string(),
+            AttributeValue::N("1704556812500".to_string()),
+        );
+        assert_eq!(history_duration_seconds(&item), Some(12.5));
+
+        item.remove("started_at_ms");
+        assert_eq!(history_duration_seconds(&item), None);
+    }
+
+    #[test]
+    fn test_next_probe_state() {
+        let healthy = next_probe_state(HealthProbeState::default(), true, 1000, 300);
+        assert_eq!(healthy, HealthProbeState::default());
+
+        let first = next_probe_state(HealthProbeState::default(), false, 1000, 300);
+        assert_eq!(first.consecutive_unhealthy, 1);
+        assert_eq!(first.first_unhealthy_at, Some(1000));
+
+        let second = next_probe_state(first, false, 1060, 300);
+        assert_eq!(second.consecutive_unhealthy, 2);
+        assert_eq!(second.first_unhealthy_at, Some(1000));
+
+        // A healthy probe in between resets the run
+        assert_eq!(
+            next_probe_state(second, true, 1120, 300).consecutive_unhealthy,
+            0
+        );
+
+        // Failures spread beyond the window start a new run
+        let stale = next_probe_state(second, false, 1400, 300);
+        assert_eq!(stale.consecutive_unhealthy, 1);
+        assert_eq!(stale.first_unhealthy_at, Some(1400));
+    }
+
+    #[test]
+    fn test_health_probe_state_from_item() {
+        let mut item = HashMap::new();
+        item.insert(
+            "consecutive_unhealthy".to_string(),
+            AttributeValue::N("2".to_string()),
+        );
+        item.insert(
+            "first_unhealthy_at".to_string(),
+            AttributeValue::N("1704556800".to_string()),
+        );
+
+        let state = health_probe_state_from_item(&item);
+        assert_eq!(state.consecutive_unhealthy, 2);
+        assert_eq!(state.first_unhealthy_at, Some(1704556800));
+        assert_eq!(
+            health_probe_state_from_item(&HashMap::new()),
+            HealthProbeState::default()
+        );
+    }
+
+    #[test]
+    fn test_check_runbook() {
+        let runbook: Runbook = serde_json::from_str(
+            r#"{"approved_target_region": "us-west-2", "max_replication_lag_seconds": 30}"#,
+        )
+        .unwrap();
+        assert!(!runbook.allow_force);
+
+        assert!(check_runbook(&runbook, "us-west-2", ForceFlags::default(), Some(10)).is_ok());
+        assert!(
+            check_runbook(&runbook, "eu-west-1", ForceFlags::default(), Some(10))
+                .unwrap_err()
+                .contains("not the approved region")
+        );
+        assert!(check_runbook(&runbook, "us-west-2", ForceFlags::all(), Some(10)).is_err());
+        assert!(check_runbook(&runbook, "us-west-2", ForceFlags::default(), Some(45)).is_err());
+        assert!(check_runbook(&runbook, "us-west-2", ForceFlags::default(), None).is_err());
+
+        let permissive = Runbook {
+            allow_force: true,
+            max_replication_lag_seconds: None,
+            ..runbook
+        };
+        assert!(check_runbook(&permissive, "us-west-2", ForceFlags::all(), None).is_ok());
+    }
+
+    #[test]
+    fn test_parse_event_reads_function_url_query() {
+        let event = serde_json::json!({
+            "rawPath": "/",
+            "requestContext": {"http": {"method": "GET"}},
+            "queryStringParameters": {
+                "action": "failover",
+                "target_region": "us-west-2",
+                "force": "true"
+            }
+        });
+        let request = parse_event(event).unwrap();
+        assert_eq!(request.action, "failover");
+        assert_eq!(request.target_region, "us-west-2");
+        assert_eq!(request.force, Some(true));
+        assert_eq!(request.force_flags, None);
+        assert!(!request.self_test);
+
+        let event = serde_json::json!({
+            "requestContext": {"http": {"method": "GET"}},
+            "queryStringParameters": {"action": "failback", "target_region": "us-east-1", "skip_lag": "1"}
+        });
+        let request = parse_event(event).unwrap();
+        assert_eq!(request.force, None);
+        assert_eq!(
+            request.force_flags,
+            Some(ForceFlags {
+                skip_lag: true,
+                ..ForceFlags::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_event_passes_direct_invocations_through() {
+        let event = serde_json::json!({"action": "failover", "target_region": "us-west-2"});
+        let request = parse_event(event).unwrap();
+        assert_eq!(request.action, "failover");
+        assert_eq!(request.target_region, "us-west-2");
+        assert_eq!(request.force, None);
+    }
+
+    #[test]
+    fn test_parse_standby_regions() {
+        assert_eq!(
+            parse_standby_regions("us-west-2, eu-west-1,,"),
+            Some(vec!["us-west-2".to_string(), "eu-west-1".to_string()])
+        );
+        assert_eq!(parse_standby_regions(" , "), None);
+    }
+
+    #[test]
+    fn test_preferred_healthy_waits_for_higher_priority() {
+        // A lower-priority healthy result can't win while a better one is pending
+        assert_eq!(preferred_healthy(&[None, Some(true)]), None);
+        assert_eq!(
+            preferred_healthy(&[Some(false), Some(true), None]),
+            Some(Some(1))
+        );
+        assert_eq!(preferred_healthy(&[Some(true), None, None]), Some(Some(0)));
+        assert_eq!(preferred_healthy(&[Some(false), Some(false)]), Some(None));
+        assert_eq!(preferred_healthy(&[]), Some(None));
+    }
+
+    #[test]
+    fn test_failed_failover_notification() {
+        let outcome = Ok(Response {
+            version: version(),
+            init: None,
+            consecutive_unhealthy: None,
+            config_trace: None,
+            status: "failed".to_string(),
+            message: "Target region us-west-2 is not healthy".to_string(),
+            action: "failover".to_string(),
+            timestamp: "2024-05-01T12:00:00+00:00".to_string(),
+        });
+        let (subject, message) =
+            outcome_notification("failover", "us-east-1", "us-west-2", &outcome);
+
+        let input = publish_input(
+            "arn:aws:sns:us-east-1:123456789012:failover",
+            &subject,
+            &message,
+        )
+        .unwrap();
+        assert_eq!(input.subject(), Some("Failover to us-west-2 failed"));
+        assert_eq!(
+            input.message(),
+            Some(
+                "Action: failover\nStatus: failed\nSource region: us-east-1\nTarget region: us-west-2\nTimestamp: 2024-05-01T12:00:00+00:00\nDetails: Target region us-west-2 is not healthy"
+            )
+        );
+
+        // An error is reported as a failure with the error as the reason
+        let outcome = Err(Error::from("status write failed"));
+        let (subject, message) =
+            outcome_notification("failback", "us-west-2", "us-east-1", &outcome);
+        assert_eq!(subject, "Failback to us-east-1 failed");
+        assert!(message.ends_with("Details: status write failed"));
+    }
+
+    #[test]
+    fn test_health_verdict_needs_consecutive_agreement() {
+        assert_eq!(health_verdict(&[], 2), None);
+        assert_eq!(health_verdict(&[false], 2), None);
+        // A single blip is outvoted by the probes after it
+        assert_eq!(health_verdict(&[false, true], 2), None);
+        assert_eq!(health_verdict(&[false, true, true], 2), Some(true));
+        assert_eq!(health_verdict(&[true, false, false], 2), Some(false));
+        assert_eq!(health_verdict(&[false], 1), Some(false));
+        assert_eq!(health_verdict(&[true], 0), Some(true));
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        assert_eq!(invalid_request("failover", "us-west-2"), None);
+        assert_eq!(invalid_request("failback", "us-east-1"), None);
+        assert_eq!(
+            invalid_request("restart", "us-west-2"),
+            Some("Invalid action: restart".to_string())
+        );
+        assert_eq!(
+            invalid_request("failover", ""),
+            Some("target_region is required for failover".to_string())
+        );
+        assert_eq!(
+            invalid_request("failback", "us-west"),
+            Some("Invalid target region: us-west".to_string())
+        );
+        assert_eq!(
+            invalid_request("failover", "us-fake-99"),
+            Some("Invalid target region: us-fake-99".to_string())
+        );
+    }
 }
@@ -1,14 +1,57 @@
-use failover_controller::{FailoverService, Request, Response};
+use aegis_common::{publish_init_duration, ConfigSource, InitTiming};
+use failover_controller::{
+    parse_event, self_test_response, version, FailoverService, ForceFlags, Response,
+};
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use std::time::Instant;
+use tracing::error;
 
-async fn function_handler(event: LambdaEvent<Request>) -> Result<Response, Error> {
+#[tracing::instrument(skip_all, fields(version = %version()))]
+async fn function_handler(event: LambdaEvent<serde_json::Value>) -> Result<Response, Error> {
+    let request = parse_event(event.payload)?;
+    if request.self_test {
+        return Ok(self_test_response());
+    }
+
+    let init_started = Instant::now();
     let service = FailoverService::new().await?;
+    let init = InitTiming::measure(init_started);
+
+    if let Err(e) =
+        publish_init_duration(&service.cloudwatch_client, env!("CARGO_PKG_NAME"), init).await
+    {
+        error!("Failed to publish init duration metric: {}", e);
+    }
+
+    let action = &request.action;
+    let target_region = &request.target_region;
+    let force = ForceFlags::resolve(request.force, request.force_flags);
+    let allow_same_region = request.allow_same_region.unwrap_or(false);
+
+    let mut response = service
+        .handle_request(action, target_region, force, allow_same_region)
+        .await?;
+    response.init = Some(init);
 
-    let action = &event.payload.action;
-    let target_region = &event.payload.target_region;
-    let force = event.payload.force.unwrap_or(false);
+    if request.debug {
+        let mut config_trace = service.config_trace.clone();
+        config_trace.record("target_region", ConfigSource::Request);
+        config_trace.record(
+            "force",
+            if request.force.is_some() || request.force_flags.is_some() {
+                ConfigSource::Request
+            } else {
+                ConfigSource::Default
+            },
+        );
+        config_trace.record(
+            "allow_same_region",
+            ConfigSource::of_request(&request.allow_same_region, ConfigSource::Default),
+        );
+        response.config_trace = Some(config_trace.into_map());
+    }
 
-    service.handle_request(action, target_region, force).await
+    Ok(response)
 }
 
 #[tokio::main]
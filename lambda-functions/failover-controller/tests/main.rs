@@ -1,5 +1,5 @@
 use failover_controller::{
-    validate_action, validate_region, FailoverService, FailoverStatus, Request, Response,
+    validate_action, validate_region, version, FailoverService, FailoverStatus, Request, Response,
 };
 use lambda_runtime::{Context, LambdaEvent};
 use serde_json::json;
@@ -33,6 +33,10 @@ fn test_request_parsing() {
 #[test]
 fn test_response_structure() {
     let response = Response {
+        version: version(),
+        init: None,
+        consecutive_unhealthy: None,
+        config_trace: None,
         status: "success".to_string(),
         message: "Failover to region us-west-2 completed".to_string(),
         action: "failover".to_string(),
@@ -97,6 +101,10 @@ fn test_region_validation() {
 #[test]
 fn test_error_response_format() {
     let error_response = Response {
+        version: version(),
+        init: None,
+        consecutive_unhealthy: None,
+        config_trace: None,
         status: "failed".to_string(),
         message: "Target region us-west-2 is not healthy".to_string(),
         action: "failover".to_string(),
@@ -118,6 +126,10 @@ mod failover_logic_tests {
             action: "failover".to_string(),
             target_region: "us-west-2".to_string(),
             force: Some(false),
+            force_flags: None,
+            allow_same_region: None,
+            debug: false,
+            self_test: false,
         };
 
         assert!(!request.force.unwrap_or(false));
@@ -130,6 +142,10 @@ mod failover_logic_tests {
             action: "failover".to_string(),
             target_region: "us-west-2".to_string(),
             force: Some(true),
+            force_flags: None,
+            allow_same_region: None,
+            debug: false,
+            self_test: false,
         };
 
         assert!(request.force.unwrap_or(false));
@@ -142,6 +158,10 @@ mod failover_logic_tests {
             action: "invalid-action".to_string(),
             target_region: "us-west-2".to_string(),
             force: None,
+            force_flags: None,
+            allow_same_region: None,
+            debug: false,
+            self_test: false,
         };
 
         assert!(!validate_action(&request.action));
@@ -155,6 +175,10 @@ mod edge_case_tests {
     #[test]
     fn test_special_characters_in_message() {
         let response = Response {
+            version: version(),
+            init: None,
+            consecutive_unhealthy: None,
+            config_trace: None,
             status: "failed".to_string(),
             message: "Region 'us-west-2' check failed: Connection timeout @ 15:30:45 UTC"
                 .to_string(),
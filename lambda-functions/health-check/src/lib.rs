@@ -1,16 +1,45 @@
+use aegis_common::{
+    access_denied, aws_config_loader, build_version, default_publish_metrics, lag_timestamp_attr,
+    publish_init_duration, sentinel_table, with_retry, AegisError, InitTiming, MetricFilter,
+    RetryBudget,
+};
 use aws_sdk_cloudwatch::{
-    types::{MetricDatum, StandardUnit},
+    types::{Dimension, MetricDatum, StandardUnit, StatisticSet},
     Client as CloudWatchClient,
 };
 use aws_sdk_dynamodb::Client as DynamoClient;
-use aws_sdk_s3::Client as S3Client;
+use aws_sdk_s3::{
+    error::{DisplayErrorContext, ProvideErrorMetadata},
+    types::{ReplicationRule, ReplicationRuleStatus},
+    Client as S3Client,
+};
 use chrono::Utc;
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Instant;
+use tracing::{error, info, warn};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Request {
     pub region: Option<String>,
+    // Smoke-test mode: return a canned response without calling AWS
+    #[serde(default)]
+    pub self_test: bool,
+    #[serde(default = "default_publish_metrics")]
+    pub publish_metrics: bool,
+    // Only these metric names are published, e.g. ["ReplicationLag"]
+    pub metric_allowlist: Option<Vec<String>>,
+    // Also confirm the backup bucket replicates to the DR bucket. Only
+    // meaningful in the primary region, where replication is configured.
+    #[serde(default)]
+    pub check_bucket_replication: bool,
+    // Sentinel reads per run; above 1, min/max/average are reported and the
+    // ReplicationLag metric is published as a statistic set
+    pub lag_samples: Option<u32>,
+    // Check each of these regions and return a fleet summary instead
+    pub regions: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -19,6 +48,10 @@ pub struct Response {
     pub region: String,
     pub timestamp: String,
     pub services: ServiceStatus,
+    pub version: String,
+    // cold_start and init_duration_ms, for invocations that built a service
+    #[serde(flatten)]
+    pub init: Option<InitTiming>,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -26,6 +59,236 @@ pub struct ServiceStatus {
     pub dynamodb: bool,
     pub s3: bool,
     pub replication_lag: Option<i64>,
+    // The sentinel writer has stopped updating, so the lag can't be trusted
+    pub sentinel_stale: bool,
+    // Only reported when the request asked for check_bucket_replication
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket_replication_ok: Option<bool>,
+    // Present when the request asked for more than one lag sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replication_lag_stats: Option<LagStatistics>,
+    // Custom probes registered with `with_probe`, by name
+    #[serde(flatten)]
+    pub probes: BTreeMap<String, bool>,
+    // How long each check's calls took, by name, including retries
+    pub latency_ms: BTreeMap<String, u64>,
+    // Why each unhealthy check failed, by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LagStatistics {
+    pub samples: usize,
+    pub min: i64,
+    pub max: i64,
+    pub average: f64,
+}
+
+// Per-region roll-up: a region with only some services failing is `partial`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionHealth {
+    Healthy,
+    Partial,
+    Down,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RegionSummary {
+    pub region: String,
+    pub health: RegionHealth,
+    pub response: Option<Response>,
+    // Set when the check itself failed (e.g. access denied)
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FleetSummary {
+    pub regions: Vec<RegionSummary>,
+    pub healthy: usize,
+    pub partial: usize,
+    pub down: usize,
+    pub timestamp: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplicationLagReading {
+    pub lag: Option<i64>,
+    pub sentinel_stale: bool,
+}
+
+// Default age after which the sentinel is considered abandoned rather than lagging
+pub const DEFAULT_SENTINEL_STALENESS_SECONDS: i64 = 300;
+
+// Tries per service check before reporting it unhealthy
+const HEALTH_CHECK_ATTEMPTS: u32 = 3;
+
+// Pause between sentinel reads when taking several lag samples
+const LAG_SAMPLE_INTERVAL_MS: u64 = 500;
+const MAX_LAG_SAMPLES: u32 = 20;
+
+// One check's outcome; `detail` is the error behind an unhealthy result.
+// ProbeRegistry::run_all fills in latency_ms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub healthy: bool,
+    pub detail: Option<String>,
+    pub latency_ms: u64,
+}
+
+impl CheckResult {
+    pub fn healthy() -> Self {
+        Self {
+            healthy: true,
+            detail: None,
+            latency_ms: 0,
+        }
+    }
+
+    pub fn unhealthy(detail: impl Into<String>) -> Self {
+        Self {
+            healthy: false,
+            detail: Some(detail.into()),
+            latency_ms: 0,
+        }
+    }
+}
+
+// Ok(result), or an error when the probe itself couldn't run (e.g. access
+// denied), so that can't be mistaken for an outage
+pub type ProbeResult = Result<CheckResult, AegisError>;
+
+// A check reported under `name` in the response's services
+pub trait HealthProbe: Send + Sync {
+    fn name(&self) -> &str;
+    fn probe(&self) -> BoxFuture<'_, ProbeResult>;
+}
+
+#[derive(Default)]
+pub struct ProbeRegistry {
+    probes: Vec<Box<dyn HealthProbe>>,
+}
+
+impl ProbeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, probe: impl HealthProbe + 'static) {
+        self.probes.push(Box::new(probe));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.probes.iter().map(|probe| probe.name()).collect()
+    }
+
+    // Runs every probe concurrently, timing each; the first probe error fails
+    // the run
+    pub async fn run_all(&self) -> Result<BTreeMap<String, CheckResult>, AegisError> {
+        let results = futures::future::try_join_all(self.probes.iter().map(|probe| async move {
+            let started = Instant::now();
+            let mut result = probe.probe().await?;
+            result.latency_ms = started.elapsed().as_millis() as u64;
+            if !result.healthy {
+                warn!(
+                    "Health probe {} reported unhealthy: {}",
+                    probe.name(),
+                    result.detail.as_deref().unwrap_or("no detail")
+                );
+            }
+            Ok::<_, AegisError>((probe.name().to_string(), result))
+        }))
+        .await?;
+
+        Ok(results.into_iter().collect())
+    }
+}
+
+// Built-in: can DynamoDB in the region be reached at all
+pub struct DynamoDbProbe {
+    client: DynamoClient,
+    retry_budget: RetryBudget,
+    region: String,
+}
+
+impl DynamoDbProbe {
+    pub fn new(client: DynamoClient, retry_budget: RetryBudget, region: String) -> Self {
+        Self {
+            client,
+            retry_budget,
+            region,
+        }
+    }
+}
+
+impl HealthProbe for DynamoDbProbe {
+    fn name(&self) -> &str {
+        "dynamodb"
+    }
+
+    fn probe(&self) -> BoxFuture<'_, ProbeResult> {
+        Box::pin(async move {
+            let result = with_retry(&self.retry_budget, HEALTH_CHECK_ATTEMPTS, || {
+                self.client.list_tables().limit(1).send()
+            })
+            .await;
+
+            match result {
+                Ok(_) => Ok(CheckResult::healthy()),
+                Err(e) => match access_denied(&e, "dynamodb:ListTables", &self.region) {
+                    Some(denied) => Err(denied.into()),
+                    None => Ok(CheckResult::unhealthy(DisplayErrorContext(&e).to_string())),
+                },
+            }
+        })
+    }
+}
+
+// Built-in: can the backup bucket be listed
+pub struct S3Probe {
+    client: S3Client,
+    retry_budget: RetryBudget,
+    bucket: String,
+}
+
+impl S3Probe {
+    pub fn new(client: S3Client, retry_budget: RetryBudget, bucket: String) -> Self {
+        Self {
+            client,
+            retry_budget,
+            bucket,
+        }
+    }
+}
+
+impl HealthProbe for S3Probe {
+    fn name(&self) -> &str {
+        "s3"
+    }
+
+    fn probe(&self) -> BoxFuture<'_, ProbeResult> {
+        Box::pin(async move {
+            // Try to list objects (with a limit of 1) to check connectivity
+            let result = with_retry(&self.retry_budget, HEALTH_CHECK_ATTEMPTS, || {
+                self.client
+                    .list_objects_v2()
+                    .bucket(&self.bucket)
+                    .max_keys(1)
+                    .send()
+            })
+            .await;
+
+            match result {
+                Ok(_) => Ok(CheckResult::healthy()),
+                Err(e) => match access_denied(&e, "s3:ListBucket", &self.bucket) {
+                    Some(denied) => Err(denied.into()),
+                    None => Ok(CheckResult::unhealthy(DisplayErrorContext(&e).to_string())),
+                },
+            }
+        })
+    }
 }
 
 pub struct HealthCheckService {
@@ -33,54 +296,151 @@ pub struct HealthCheckService {
     s3_client: S3Client,
     cloudwatch_client: CloudWatchClient,
     region: String,
+    sentinel_table: String,
+    sentinel_staleness_seconds: i64,
+    metric_filter: MetricFilter,
+    check_bucket_replication: bool,
+    lag_samples: u32,
+    // The built-in dynamodb and s3 probes, then any added with `with_probe`
+    probes: ProbeRegistry,
 }
 
 impl HealthCheckService {
-    pub async fn new(region: Option<String>) -> Result<Self, lambda_runtime::Error> {
+    // Region precedence: the explicit `region` argument (request field, or the
+    // invoked function ARN when the handler resolved it from the context), then
+    // AWS_REGION, then us-east-1.
+    pub async fn new(region: Option<String>) -> Result<Self, AegisError> {
         let region_str = region.unwrap_or_else(|| {
             std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string())
         });
 
-        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+        let config = aws_config_loader()
+            .region(aws_config::Region::new(region_str.clone()))
             .load()
             .await;
 
+        let sentinel_staleness_seconds = std::env::var("SENTINEL_STALENESS_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_SENTINEL_STALENESS_SECONDS);
+
+        let dynamo_client = DynamoClient::new(&config);
+        let s3_client = S3Client::new(&config);
+        let retry_budget = RetryBudget::from_env();
+
+        let mut probes = ProbeRegistry::new();
+        probes.register(DynamoDbProbe::new(
+            dynamo_client.clone(),
+            retry_budget.clone(),
+            region_str.clone(),
+        ));
+        probes.register(S3Probe::new(
+            s3_client.clone(),
+            retry_budget,
+            backup_bucket_for(&region_str),
+        ));
+
         Ok(Self {
-            dynamo_client: DynamoClient::new(&config),
-            s3_client: S3Client::new(&config),
+            dynamo_client,
+            s3_client,
             cloudwatch_client: CloudWatchClient::new(&config),
             region: region_str,
+            sentinel_table: sentinel_table(),
+            sentinel_staleness_seconds,
+            metric_filter: MetricFilter::all(),
+            check_bucket_replication: false,
+            lag_samples: 1,
+            probes,
         })
     }
 
-    pub async fn check_dynamodb_health(&self) -> Result<bool, lambda_runtime::Error> {
-        let result = self.dynamo_client.list_tables().limit(1).send().await;
-        Ok(result.is_ok())
+    pub async fn publish_init_duration(&self, init: InitTiming) -> Result<(), AegisError> {
+        if !self.metric_filter.allows("InitDuration") {
+            return Ok(());
+        }
+
+        publish_init_duration(&self.cloudwatch_client, env!("CARGO_PKG_NAME"), init)
+            .await
+            .map_err(AegisError::from)
     }
 
-    pub async fn check_s3_health(&self) -> Result<bool, lambda_runtime::Error> {
-        // Get bucket name from environment variable or use default
-        let bucket_name = std::env::var("BACKUP_BUCKET")
-            .unwrap_or_else(|_| format!("dr-demo-backup-bucket-{}", self.region));
+    pub fn sentinel_table(&self) -> &str {
+        &self.sentinel_table
+    }
+
+    pub fn with_metric_filter(mut self, metric_filter: MetricFilter) -> Self {
+        self.metric_filter = metric_filter;
+        self
+    }
+
+    pub fn with_bucket_replication_check(mut self, enabled: bool) -> Self {
+        self.check_bucket_replication = enabled;
+        self
+    }
+
+    pub fn with_lag_samples(mut self, samples: u32) -> Self {
+        self.lag_samples = samples.clamp(1, MAX_LAG_SAMPLES);
+        self
+    }
+
+    // Custom probes run alongside the built-in ones and appear under their
+    // name in the response's services
+    pub fn with_probe(mut self, probe: impl HealthProbe + 'static) -> Self {
+        self.probes.register(probe);
+        self
+    }
+
+    fn backup_bucket(&self) -> String {
+        backup_bucket_for(&self.region)
+    }
+
+    // Cross-Region Replication can be switched off without anything else
+    // failing, leaving backups only in the primary region. Healthy when an
+    // enabled rule replicates to DR_BACKUP_BUCKET (default "<bucket>-dr").
+    pub async fn check_bucket_replication(&self) -> Result<bool, AegisError> {
+        let bucket_name = self.backup_bucket();
+        let dr_bucket =
+            std::env::var("DR_BACKUP_BUCKET").unwrap_or_else(|_| format!("{}-dr", bucket_name));
 
-        // Try to list objects (with a limit of 1) to check connectivity
         let result = self
             .s3_client
-            .list_objects_v2()
+            .get_bucket_replication()
             .bucket(&bucket_name)
-            .max_keys(1)
             .send()
             .await;
 
-        Ok(result.is_ok())
+        match result {
+            Ok(output) => {
+                let rules = output
+                    .replication_configuration
+                    .map(|config| config.rules)
+                    .unwrap_or_default();
+                Ok(replicates_to_bucket(&rules, &dr_bucket))
+            }
+            Err(e) => {
+                if let Some(denied) =
+                    access_denied(&e, "s3:GetReplicationConfiguration", &bucket_name)
+                {
+                    return Err(denied.into());
+                }
+
+                if e.code() != Some("ReplicationConfigurationNotFoundError") {
+                    error!(
+                        "Failed to read replication config of {}: {}",
+                        bucket_name, e
+                    );
+                }
+                Ok(false)
+            }
+        }
     }
 
-    pub async fn check_replication_lag(&self) -> Result<Option<i64>, lambda_runtime::Error> {
+    pub async fn check_replication_lag(&self) -> Result<ReplicationLagReading, AegisError> {
         // Check a sentinel record to measure replication lag
         let result = self
             .dynamo_client
             .get_item()
-            .table_name("dr-sentinel-table")
+            .table_name(&self.sentinel_table)
             .key(
                 "id",
                 aws_sdk_dynamodb::types::AttributeValue::S("sentinel".to_string()),
@@ -90,23 +450,46 @@ impl HealthCheckService {
 
         if let Ok(response) = result {
             if let Some(item) = response.item {
-                if let Some(timestamp_attr) = item.get("last_updated") {
+                if let Some(timestamp_attr) = item.get(&lag_timestamp_attr()) {
                     if let Ok(timestamp_str) = timestamp_attr.as_n() {
                         if let Ok(timestamp) = timestamp_str.parse::<i64>() {
                             let current_time = Utc::now().timestamp();
-                            return Ok(Some(current_time - timestamp));
+                            return Ok(classify_sentinel_age(
+                                current_time - timestamp,
+                                self.sentinel_staleness_seconds,
+                            ));
                         }
                     }
                 }
             }
         }
-        Ok(None)
+        Ok(ReplicationLagReading {
+            lag: None,
+            sentinel_stale: false,
+        })
     }
 
-    pub async fn publish_metrics(
+    // Take `lag_samples` readings. The last one is the reported lag; the
+    // statistics cover every sample that produced a lag.
+    async fn sample_replication_lag(
         &self,
-        status: &ServiceStatus,
-    ) -> Result<(), lambda_runtime::Error> {
+    ) -> Result<(ReplicationLagReading, Option<LagStatistics>), AegisError> {
+        let mut reading = self.check_replication_lag().await?;
+        if self.lag_samples <= 1 {
+            return Ok((reading, None));
+        }
+
+        let mut samples: Vec<i64> = reading.lag.into_iter().collect();
+        for _ in 1..self.lag_samples {
+            tokio::time::sleep(std::time::Duration::from_millis(LAG_SAMPLE_INTERVAL_MS)).await;
+            reading = self.check_replication_lag().await?;
+            samples.extend(reading.lag);
+        }
+
+        Ok((reading, summarize_lag_samples(&samples)))
+    }
+
+    pub async fn publish_metrics(&self, status: &ServiceStatus) -> Result<(), AegisError> {
         let namespace = "DisasterRecovery";
         let timestamp = std::time::SystemTime::now();
         let aws_timestamp = aws_sdk_cloudwatch::primitives::DateTime::from(timestamp);
@@ -122,7 +505,9 @@ impl HealthCheckService {
             .timestamp(aws_timestamp.clone())
             .build();
 
-        metrics.push(dynamodb_metric);
+        if self.metric_filter.allows("DynamoDBHealth") {
+            metrics.push(dynamodb_metric);
+        }
 
         // S3 health metric
         let s3_metric = MetricDatum::builder()
@@ -132,10 +517,35 @@ impl HealthCheckService {
             .timestamp(aws_timestamp.clone())
             .build();
 
-        metrics.push(s3_metric);
+        if self.metric_filter.allows("S3Health") {
+            metrics.push(s3_metric);
+        }
+
+        // Replication lag metric (if available), as a statistic set when sampled
+        if let Some(stats) = status
+            .replication_lag_stats
+            .as_ref()
+            .filter(|_| self.metric_filter.allows("ReplicationLag"))
+        {
+            let statistic_set = StatisticSet::builder()
+                .sample_count(stats.samples as f64)
+                .sum(stats.average * stats.samples as f64)
+                .minimum(stats.min as f64)
+                .maximum(stats.max as f64)
+                .build();
 
-        // Replication lag metric (if available)
-        if let Some(lag) = status.replication_lag {
+            metrics.push(
+                MetricDatum::builder()
+                    .metric_name("ReplicationLag")
+                    .statistic_values(statistic_set)
+                    .unit(StandardUnit::Seconds)
+                    .timestamp(aws_timestamp.clone())
+                    .build(),
+            );
+        } else if let Some(lag) = status
+            .replication_lag
+            .filter(|_| self.metric_filter.allows("ReplicationLag"))
+        {
             let replication_metric = MetricDatum::builder()
                 .metric_name("ReplicationLag")
                 .value(lag as f64)
@@ -146,6 +556,21 @@ impl HealthCheckService {
             metrics.push(replication_metric);
         }
 
+        // One latency datum per check, told apart by its Service dimension
+        if self.metric_filter.allows("HealthCheckLatency") {
+            for (service, latency_ms) in &status.latency_ms {
+                metrics.push(
+                    MetricDatum::builder()
+                        .metric_name("HealthCheckLatency")
+                        .dimensions(Dimension::builder().name("Service").value(service).build())
+                        .value(*latency_ms as f64)
+                        .unit(StandardUnit::Milliseconds)
+                        .timestamp(aws_timestamp.clone())
+                        .build(),
+                );
+            }
+        }
+
         // If we have metrics to publish, send them
         if !metrics.is_empty() {
             info!("Publishing {} metrics to CloudWatch", metrics.len());
@@ -162,25 +587,70 @@ impl HealthCheckService {
                 Ok(_) => Ok(()),
                 Err(e) => {
                     error!("Failed to publish metrics: {}", e);
-                    Err(lambda_runtime::Error::from(e))
+                    Err(AegisError::from_sdk(
+                        e,
+                        "cloudwatch:PutMetricData",
+                        namespace,
+                    ))
                 }
             }
         } else {
-            error!("No valid metrics to publish");
+            info!("No metrics selected for publishing");
             Ok(())
         }
     }
 
-    pub async fn run_health_check(&self) -> Result<Response, lambda_runtime::Error> {
-        // Check service health
-        let dynamodb_health = self.check_dynamodb_health().await?;
-        let s3_health = self.check_s3_health().await?;
-        let replication_lag = self.check_replication_lag().await?;
+    pub async fn run_health_check(&self) -> Result<Response, AegisError> {
+        // Check service health; a permissions failure errors rather than
+        // reporting the service unhealthy, so it can't look like an outage
+        let bucket_replication = async {
+            if self.check_bucket_replication {
+                self.check_bucket_replication().await.map(Some)
+            } else {
+                Ok(None)
+            }
+        };
+        let (mut probes, (lag_reading, replication_lag_stats), bucket_replication_ok) =
+            join_checks(
+                self.probes.run_all(),
+                self.sample_replication_lag(),
+                bucket_replication,
+            )
+            .await?;
+        let latency_ms = probes
+            .iter()
+            .map(|(name, result)| (name.clone(), result.latency_ms))
+            .collect();
+        let details = probes
+            .iter_mut()
+            .filter_map(|(name, result)| Some((name.clone(), result.detail.take()?)))
+            .collect();
+        let dynamodb_health = probes.remove("dynamodb").is_some_and(|r| r.healthy);
+        let s3_health = probes.remove("s3").is_some_and(|r| r.healthy);
+        let probes: BTreeMap<String, bool> = probes
+            .into_iter()
+            .map(|(name, result)| (name, result.healthy))
+            .collect();
+        let probes_healthy = probes.values().all(|healthy| *healthy);
+
+        if lag_reading.sentinel_stale {
+            warn!("Sentinel record is stale; the sentinel writer may be down");
+        }
+
+        if bucket_replication_ok == Some(false) {
+            warn!("Backup bucket is not replicating to the DR region");
+        }
 
         let status = ServiceStatus {
             dynamodb: dynamodb_health,
             s3: s3_health,
-            replication_lag,
+            replication_lag: lag_reading.lag,
+            sentinel_stale: lag_reading.sentinel_stale,
+            bucket_replication_ok,
+            replication_lag_stats,
+            probes,
+            latency_ms,
+            details,
         };
 
         // Publish metrics to CloudWatch
@@ -189,7 +659,13 @@ impl HealthCheckService {
         }
 
         Ok(Response {
-            status: if dynamodb_health && s3_health {
+            version: version(),
+            init: None,
+            status: if dynamodb_health
+                && s3_health
+                && probes_healthy
+                && bucket_replication_ok != Some(false)
+            {
                 "healthy"
             } else {
                 "unhealthy"
@@ -202,9 +678,181 @@ impl HealthCheckService {
     }
 }
 
+// The probes, lag sampling and bucket replication check are independent, so
+// they run concurrently; the first error fails the whole check
+async fn join_checks<P, L, B>(
+    probes: impl Future<Output = Result<P, AegisError>>,
+    lag: impl Future<Output = Result<L, AegisError>>,
+    bucket_replication: impl Future<Output = Result<B, AegisError>>,
+) -> Result<(P, L, B), AegisError> {
+    tokio::try_join!(probes, lag, bucket_replication)
+}
+
+// Check every region concurrently. A region whose check errors is reported
+// as down with the error rather than failing the whole run.
+pub async fn run_multi_region_health_check(
+    regions: &[String],
+    metric_filter: MetricFilter,
+) -> FleetSummary {
+    let checks = regions.iter().map(|region| {
+        let metric_filter = metric_filter.clone();
+        async move {
+            let result = match HealthCheckService::new(Some(region.clone())).await {
+                Ok(service) => {
+                    service
+                        .with_metric_filter(metric_filter)
+                        .run_health_check()
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(response) => RegionSummary {
+                    region: region.clone(),
+                    health: classify_region(&response.services),
+                    response: Some(response),
+                    error: None,
+                },
+                Err(e) => {
+                    error!("Health check of {} failed: {}", region, e);
+                    RegionSummary {
+                        region: region.clone(),
+                        health: RegionHealth::Down,
+                        response: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            }
+        }
+    });
+
+    summarize_fleet(futures::future::join_all(checks).await)
+}
+
+pub fn classify_region(services: &ServiceStatus) -> RegionHealth {
+    match (services.dynamodb, services.s3) {
+        (false, false) => RegionHealth::Down,
+        (true, true)
+            if services.bucket_replication_ok != Some(false)
+                && services.probes.values().all(|healthy| *healthy) =>
+        {
+            RegionHealth::Healthy
+        }
+        _ => RegionHealth::Partial,
+    }
+}
+
+// BACKUP_BUCKET, or the demo bucket for the region
+fn backup_bucket_for(region: &str) -> String {
+    std::env::var("BACKUP_BUCKET").unwrap_or_else(|_| format!("dr-demo-backup-bucket-{}", region))
+}
+
+pub fn summarize_fleet(regions: Vec<RegionSummary>) -> FleetSummary {
+    let count = |health: RegionHealth| regions.iter().filter(|r| r.health == health).count();
+
+    FleetSummary {
+        healthy: count(RegionHealth::Healthy),
+        partial: count(RegionHealth::Partial),
+        down: count(RegionHealth::Down),
+        regions,
+        timestamp: Utc::now().to_rfc3339(),
+        version: version(),
+    }
+}
+
+// A sentinel older than the staleness threshold means its writer died, not that
+// replication broke, so report no lag instead of an ever-growing one
+pub fn classify_sentinel_age(age_seconds: i64, staleness_threshold: i64) -> ReplicationLagReading {
+    if age_seconds > staleness_threshold {
+        ReplicationLagReading {
+            lag: None,
+            sentinel_stale: true,
+        }
+    } else {
+        ReplicationLagReading {
+            lag: Some(age_seconds),
+            sentinel_stale: false,
+        }
+    }
+}
+
+pub fn summarize_lag_samples(samples: &[i64]) -> Option<LagStatistics> {
+    let min = *samples.iter().min()?;
+    let max = *samples.iter().max()?;
+    let average = samples.iter().sum::<i64>() as f64 / samples.len() as f64;
+
+    Some(LagStatistics {
+        samples: samples.len(),
+        min,
+        max,
+        average,
+    })
+}
+
+// Rules name their destination by ARN, e.g. arn:aws:s3:::dr-demo-backup-bucket-primary-dr
+pub fn replicates_to_bucket(rules: &[ReplicationRule], dr_bucket: &str) -> bool {
+    let destination_arn = format!("arn:aws:s3:::{}", dr_bucket);
+
+    rules.iter().any(|rule| {
+        rule.status == ReplicationRuleStatus::Enabled
+            && rule
+                .destination
+                .as_ref()
+                .is_some_and(|destination| destination.bucket == destination_arn)
+    })
+}
+
+// Extract the region from a Lambda ARN such as
+// arn:aws:lambda:us-west-2:123456789012:function:dr-health-check
+pub fn region_from_function_arn(arn: &str) -> Option<String> {
+    let mut parts = arn.split(':');
+    if parts.next() != Some("arn") {
+        return None;
+    }
+
+    parts
+        .nth(2)
+        .filter(|region| !region.is_empty())
+        .map(|region| region.to_string())
+}
+
+// Resolve the region to check: request field, then invoked function ARN.
+// Returns None so HealthCheckService::new can fall back to AWS_REGION.
+pub fn resolve_region(requested: Option<String>, invoked_function_arn: &str) -> Option<String> {
+    requested.or_else(|| region_from_function_arn(invoked_function_arn))
+}
+
+// Package version plus the git hash of the build, reported in every response
+pub fn version() -> String {
+    build_version(env!("CARGO_PKG_VERSION"))
+}
+
+pub fn self_test_response(region: Option<String>) -> Response {
+    Response {
+        version: version(),
+        init: None,
+        status: "healthy".to_string(),
+        region: region.unwrap_or_else(|| "unknown".to_string()),
+        timestamp: Utc::now().to_rfc3339(),
+        services: ServiceStatus {
+            dynamodb: true,
+            s3: true,
+            replication_lag: None,
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aegis_common::AccessDeniedError;
 
     #[test]
     fn test_request_deserialization() {
@@ -220,6 +868,8 @@ mod tests {
     #[test]
     fn test_response_serialization() {
         let response = Response {
+            version: version(),
+            init: None,
             status: "healthy".to_string(),
             region: "us-east-1".to_string(),
             timestamp: "2025-01-01T00:00:00Z".to_string(),
@@ -227,6 +877,12 @@ mod tests {
                 dynamodb: true,
                 s3: true,
                 replication_lag: Some(5),
+                sentinel_stale: false,
+                bucket_replication_ok: None,
+                replication_lag_stats: None,
+                probes: BTreeMap::new(),
+                latency_ms: BTreeMap::new(),
+                details: BTreeMap::new(),
             },
         };
 
@@ -245,6 +901,12 @@ mod tests {
             dynamodb: true,
             s3: true,
             replication_lag: Some(5),
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         };
 
         assert!(status.dynamodb);
@@ -258,6 +920,12 @@ mod tests {
             dynamodb: false,
             s3: false,
             replication_lag: None,
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         };
 
         assert!(!status.dynamodb);
@@ -271,18 +939,36 @@ mod tests {
             dynamodb: true,
             s3: true,
             replication_lag: Some(10),
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         };
 
         let unhealthy_dynamo = ServiceStatus {
             dynamodb: false,
             s3: true,
             replication_lag: Some(10),
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         };
 
         let unhealthy_s3 = ServiceStatus {
             dynamodb: true,
             s3: false,
             replication_lag: Some(10),
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         };
 
         // Test the logic for determining overall health
@@ -294,6 +980,8 @@ mod tests {
     #[test]
     fn test_response_equality() {
         let response1 = Response {
+            version: version(),
+            init: None,
             status: "healthy".to_string(),
             region: "us-east-1".to_string(),
             timestamp: "2025-01-01T00:00:00Z".to_string(),
@@ -301,10 +989,18 @@ mod tests {
                 dynamodb: true,
                 s3: true,
                 replication_lag: Some(5),
+                sentinel_stale: false,
+                bucket_replication_ok: None,
+                replication_lag_stats: None,
+                probes: BTreeMap::new(),
+                latency_ms: BTreeMap::new(),
+                details: BTreeMap::new(),
             },
         };
 
         let response2 = Response {
+            version: version(),
+            init: None,
             status: "healthy".to_string(),
             region: "us-east-1".to_string(),
             timestamp: "2025-01-01T00:00:00Z".to_string(),
@@ -312,9 +1008,352 @@ mod tests {
                 dynamodb: true,
                 s3: true,
                 replication_lag: Some(5),
+                sentinel_stale: false,
+                bucket_replication_ok: None,
+                replication_lag_stats: None,
+                probes: BTreeMap::new(),
+                latency_ms: BTreeMap::new(),
+                details: BTreeMap::new(),
             },
         };
 
         assert_eq!(response1, response2);
     }
+
+    #[test]
+    fn test_region_from_function_arn() {
+        assert_eq!(
+            region_from_function_arn(
+                "arn:aws:lambda:us-west-2:123456789012:function:dr-health-check"
+            ),
+            Some("us-west-2".to_string())
+        );
+        assert_eq!(
+            region_from_function_arn(
+                "arn:aws:lambda:eu-west-1:123456789012:function:dr-health-check:live"
+            ),
+            Some("eu-west-1".to_string())
+        );
+        assert_eq!(region_from_function_arn(""), None);
+        assert_eq!(region_from_function_arn("not-an-arn"), None);
+        assert_eq!(
+            region_from_function_arn("arn:aws:lambda::123:function:x"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resolve_region_precedence() {
+        let arn = "arn:aws:lambda:us-west-2:123456789012:function:dr-health-check";
+
+        // Request field wins over the ARN
+        assert_eq!(
+            resolve_region(Some("eu-central-1".to_string()), arn),
+            Some("eu-central-1".to_string())
+        );
+
+        // ARN is used when the request omits the region
+        assert_eq!(resolve_region(None, arn), Some("us-west-2".to_string()));
+
+        // Neither available: defer to AWS_REGION in HealthCheckService::new
+        assert_eq!(resolve_region(None, ""), None);
+    }
+
+    #[test]
+    fn test_classify_sentinel_age() {
+        let fresh = classify_sentinel_age(5, DEFAULT_SENTINEL_STALENESS_SECONDS);
+        assert_eq!(fresh.lag, Some(5));
+        assert!(!fresh.sentinel_stale);
+
+        let at_threshold = classify_sentinel_age(300, 300);
+        assert_eq!(at_threshold.lag, Some(300));
+        assert!(!at_threshold.sentinel_stale);
+
+        let stale = classify_sentinel_age(86_400, 300);
+        assert_eq!(stale.lag, None);
+        assert!(stale.sentinel_stale);
+    }
+
+    #[test]
+    fn test_self_test_request() {
+        let request: Request = serde_json::from_str(r#"{"self_test": true}"#).unwrap();
+        assert!(request.self_test);
+
+        let request: Request = serde_json::from_str(r#"{"region": "us-east-1"}"#).unwrap();
+        assert!(!request.self_test);
+
+        let response = self_test_response(Some("us-east-1".to_string()));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["status"], "healthy");
+        assert_eq!(json["region"], "us-east-1");
+    }
+
+    #[test]
+    fn test_metric_selection_request() {
+        let request: Request = serde_json::from_str(r#"{"region": "us-east-1"}"#).unwrap();
+        assert!(request.publish_metrics);
+        assert_eq!(request.metric_allowlist, None);
+
+        let request: Request = serde_json::from_str(
+            r#"{"publish_metrics": true, "metric_allowlist": ["ReplicationLag"]}"#,
+        )
+        .unwrap();
+        let filter = MetricFilter::new(request.publish_metrics, request.metric_allowlist);
+        assert!(filter.allows("ReplicationLag"));
+        assert!(!filter.allows("DynamoDBHealth"));
+    }
+
+    #[test]
+    fn test_replicates_to_bucket() {
+        use aws_sdk_s3::types::Destination;
+
+        let rule = |status: ReplicationRuleStatus, bucket: &str| {
+            ReplicationRule::builder()
+                .status(status)
+                .destination(Destination::builder().bucket(bucket).build().unwrap())
+                .build()
+                .unwrap()
+        };
+
+        let dr_arn = "arn:aws:s3:::backups-dr";
+        assert!(replicates_to_bucket(
+            &[rule(ReplicationRuleStatus::Enabled, dr_arn)],
+            "backups-dr"
+        ));
+        assert!(!replicates_to_bucket(
+            &[rule(ReplicationRuleStatus::Disabled, dr_arn)],
+            "backups-dr"
+        ));
+        assert!(!replicates_to_bucket(
+            &[rule(
+                ReplicationRuleStatus::Enabled,
+                "arn:aws:s3:::elsewhere"
+            )],
+            "backups-dr"
+        ));
+        assert!(!replicates_to_bucket(&[], "backups-dr"));
+    }
+
+    #[test]
+    fn test_summarize_lag_samples() {
+        let stats = summarize_lag_samples(&[4, 2, 9]).unwrap();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.min, 2);
+        assert_eq!(stats.max, 9);
+        assert_eq!(stats.average, 5.0);
+
+        assert_eq!(summarize_lag_samples(&[]), None);
+    }
+
+    #[test]
+    fn test_classify_region() {
+        let services = |dynamodb, s3, bucket_replication_ok| ServiceStatus {
+            dynamodb,
+            s3,
+            replication_lag: None,
+            sentinel_stale: false,
+            bucket_replication_ok,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
+        };
+
+        assert_eq!(
+            classify_region(&services(true, true, None)),
+            RegionHealth::Healthy
+        );
+        assert_eq!(
+            classify_region(&services(true, false, None)),
+            RegionHealth::Partial
+        );
+        assert_eq!(
+            classify_region(&services(false, true, None)),
+            RegionHealth::Partial
+        );
+        assert_eq!(
+            classify_region(&services(true, true, Some(false))),
+            RegionHealth::Partial
+        );
+        assert_eq!(
+            classify_region(&services(false, false, None)),
+            RegionHealth::Down
+        );
+    }
+
+    #[test]
+    fn test_summarize_fleet() {
+        let summary = |region: &str, health| RegionSummary {
+            region: region.to_string(),
+            health,
+            response: None,
+            error: None,
+        };
+
+        let fleet = summarize_fleet(vec![
+            summary("us-east-1", RegionHealth::Healthy),
+            summary("us-west-2", RegionHealth::Partial),
+            summary("eu-west-1", RegionHealth::Down),
+            summary("eu-central-1", RegionHealth::Healthy),
+        ]);
+
+        assert_eq!(fleet.healthy, 2);
+        assert_eq!(fleet.partial, 1);
+        assert_eq!(fleet.down, 1);
+
+        let json = serde_json::to_value(&fleet).unwrap();
+        assert_eq!(json["regions"][1]["health"], "partial");
+    }
+
+    struct FixedProbe(&'static str, bool);
+
+    impl HealthProbe for FixedProbe {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn probe(&self) -> BoxFuture<'_, ProbeResult> {
+            Box::pin(async move {
+                Ok(match self.1 {
+                    true => CheckResult::healthy(),
+                    false => CheckResult::unhealthy("cluster unreachable"),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_registry_runs_custom_probes() {
+        let mut registry = ProbeRegistry::new();
+        registry.register(FixedProbe("sqs_queue_depth", true));
+        registry.register(FixedProbe("elasticache", false));
+        assert_eq!(registry.names(), vec!["sqs_queue_depth", "elasticache"]);
+
+        let results = registry.run_all().await.unwrap();
+        assert!(results["sqs_queue_depth"].healthy);
+        assert!(!results["elasticache"].healthy);
+        assert_eq!(
+            results["elasticache"].detail.as_deref(),
+            Some("cluster unreachable")
+        );
+        assert_eq!(results["sqs_queue_depth"].detail, None);
+    }
+
+    struct DeniedProbe;
+
+    impl HealthProbe for DeniedProbe {
+        fn name(&self) -> &str {
+            "denied"
+        }
+
+        fn probe(&self) -> BoxFuture<'_, ProbeResult> {
+            Box::pin(async move {
+                Err(AegisError::AccessDenied(AccessDeniedError {
+                    action: "sqs:GetQueueAttributes".to_string(),
+                    resource: "orders".to_string(),
+                    message: None,
+                }))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_probe_registry_keeps_error_variant() {
+        let mut registry = ProbeRegistry::new();
+        registry.register(FixedProbe("dynamodb", true));
+        registry.register(DeniedProbe);
+
+        let err = registry.run_all().await.unwrap_err();
+        assert!(matches!(err, AegisError::AccessDenied(denied) if denied.resource == "orders"));
+    }
+
+    struct DelayedProbe(&'static str, u64);
+
+    impl HealthProbe for DelayedProbe {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn probe(&self) -> BoxFuture<'_, ProbeResult> {
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(self.1)).await;
+                Ok(CheckResult::healthy())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checks_run_concurrently() {
+        let mut registry = ProbeRegistry::new();
+        registry.register(DelayedProbe("dynamodb", 200));
+        registry.register(DelayedProbe("s3", 200));
+        async fn delayed<T>(value: T) -> Result<T, AegisError> {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            Ok(value)
+        }
+
+        let started = std::time::Instant::now();
+        let (probes, lag, bucket) =
+            join_checks(registry.run_all(), delayed(Some(3)), delayed(true))
+                .await
+                .unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(probes.len(), 2);
+        assert!(probes["dynamodb"].latency_ms >= 200);
+        assert_eq!((lag, bucket), (Some(3), true));
+        // Sequential would take at least 600ms (800ms with sequential probes)
+        assert!(
+            elapsed < std::time::Duration::from_millis(450),
+            "{:?}",
+            elapsed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_join_checks_fails_on_first_error() {
+        let result = join_checks(
+            async { Ok(1) },
+            async { Err::<i32, _>(AegisError::Throttled("dynamodb:GetItem".to_string())) },
+            async { Ok(true) },
+        )
+        .await;
+
+        assert!(matches!(result, Err(AegisError::Throttled(_))));
+    }
+
+    #[test]
+    fn test_custom_probes_in_service_map() {
+        let services = ServiceStatus {
+            dynamodb: true,
+            s3: true,
+            replication_lag: None,
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::from([("elasticache".to_string(), false)]),
+            latency_ms: BTreeMap::from([("elasticache".to_string(), 42)]),
+            details: BTreeMap::from([(
+                "elasticache".to_string(),
+                "cluster unreachable".to_string(),
+            )]),
+        };
+
+        let json = serde_json::to_value(&services).unwrap();
+        assert_eq!(json["elasticache"], false);
+        assert_eq!(json["latency_ms"]["elasticache"], 42);
+        assert_eq!(json["details"]["elasticache"], "cluster unreachable");
+        assert_eq!(classify_region(&services), RegionHealth::Partial);
+    }
+
+    #[tokio::test]
+    async fn test_sentinel_table_from_env() {
+        std::env::set_var("SENTINEL_TABLE", "payments-sentinel");
+        let service = HealthCheckService::new(Some("us-east-1".to_string()))
+            .await
+            .unwrap();
+        std::env::remove_var("SENTINEL_TABLE");
+
+        assert_eq!(service.sentinel_table(), "payments-sentinel");
+    }
 }
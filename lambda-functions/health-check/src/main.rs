@@ -1,9 +1,44 @@
-use health_check::{HealthCheckService, Request, Response};
+use aegis_common::{encode_response, InitTiming, MetricFilter, ResponseFormat};
+use health_check::{
+    resolve_region, run_multi_region_health_check, self_test_response, version, HealthCheckService,
+    Request,
+};
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use std::time::Instant;
+use tracing::error;
 
-async fn function_handler(event: LambdaEvent<Request>) -> Result<Response, Error> {
-    let service = HealthCheckService::new(event.payload.region).await?;
-    service.run_health_check().await
+#[tracing::instrument(skip_all, fields(version = %version()))]
+async fn function_handler(event: LambdaEvent<Request>) -> Result<serde_json::Value, Error> {
+    let format = ResponseFormat::from_env();
+    let request = event.payload;
+    let region = resolve_region(request.region, &event.context.invoked_function_arn);
+
+    if request.self_test {
+        return encode_response(&self_test_response(region), format);
+    }
+
+    let metric_filter = MetricFilter::new(request.publish_metrics, request.metric_allowlist);
+
+    if let Some(regions) = request.regions.filter(|regions| !regions.is_empty()) {
+        let fleet = run_multi_region_health_check(&regions, metric_filter).await;
+        return encode_response(&fleet, format);
+    }
+
+    let init_started = Instant::now();
+    let service = HealthCheckService::new(region)
+        .await?
+        .with_metric_filter(metric_filter)
+        .with_bucket_replication_check(request.check_bucket_replication)
+        .with_lag_samples(request.lag_samples.unwrap_or(1));
+    let init = InitTiming::measure(init_started);
+
+    if let Err(e) = service.publish_init_duration(init).await {
+        error!("Failed to publish init duration metric: {}", e);
+    }
+
+    let mut response = service.run_health_check().await?;
+    response.init = Some(init);
+    encode_response(&response, format)
 }
 
 #[tokio::main]
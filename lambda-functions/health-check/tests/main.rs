@@ -1,7 +1,8 @@
-use health_check::{HealthCheckService, Request, Response, ServiceStatus};
+use health_check::{version, HealthCheckService, Request, Response, ServiceStatus};
 use lambda_runtime::{Context, LambdaEvent};
 use mockall::{mock, predicate::*};
 use serde_json::json;
+use std::collections::BTreeMap;
 
 // We can't directly mock AWS SDK structs, so we'll test the higher-level functionality
 
@@ -33,6 +34,8 @@ async fn test_lambda_event_parsing() {
 #[test]
 fn test_response_json_structure() {
     let response = Response {
+        version: version(),
+        init: None,
         status: "healthy".to_string(),
         region: "us-east-1".to_string(),
         timestamp: "2025-01-06T12:00:00Z".to_string(),
@@ -40,6 +43,12 @@ fn test_response_json_structure() {
             dynamodb: true,
             s3: true,
             replication_lag: Some(3),
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         },
     };
 
@@ -60,6 +69,12 @@ fn test_health_status_logic() {
         dynamodb: true,
         s3: true,
         replication_lag: Some(5),
+        sentinel_stale: false,
+        bucket_replication_ok: None,
+        replication_lag_stats: None,
+        probes: BTreeMap::new(),
+        latency_ms: BTreeMap::new(),
+        details: BTreeMap::new(),
     };
 
     let health_status = if healthy_services.dynamodb && healthy_services.s3 {
@@ -75,6 +90,12 @@ fn test_health_status_logic() {
         dynamodb: false,
         s3: true,
         replication_lag: Some(5),
+        sentinel_stale: false,
+        bucket_replication_ok: None,
+        replication_lag_stats: None,
+        probes: BTreeMap::new(),
+        latency_ms: BTreeMap::new(),
+        details: BTreeMap::new(),
     };
 
     let health_status = if dynamo_unhealthy.dynamodb && dynamo_unhealthy.s3 {
@@ -90,6 +111,12 @@ fn test_health_status_logic() {
         dynamodb: true,
         s3: false,
         replication_lag: Some(5),
+        sentinel_stale: false,
+        bucket_replication_ok: None,
+        replication_lag_stats: None,
+        probes: BTreeMap::new(),
+        latency_ms: BTreeMap::new(),
+        details: BTreeMap::new(),
     };
 
     let health_status = if s3_unhealthy.dynamodb && s3_unhealthy.s3 {
@@ -108,6 +135,12 @@ fn test_replication_lag_scenarios() {
         dynamodb: true,
         s3: true,
         replication_lag: Some(30),
+        sentinel_stale: false,
+        bucket_replication_ok: None,
+        replication_lag_stats: None,
+        probes: BTreeMap::new(),
+        latency_ms: BTreeMap::new(),
+        details: BTreeMap::new(),
     };
 
     assert_eq!(with_lag.replication_lag, Some(30));
@@ -117,6 +150,12 @@ fn test_replication_lag_scenarios() {
         dynamodb: true,
         s3: true,
         replication_lag: None,
+        sentinel_stale: false,
+        bucket_replication_ok: None,
+        replication_lag_stats: None,
+        probes: BTreeMap::new(),
+        latency_ms: BTreeMap::new(),
+        details: BTreeMap::new(),
     };
 
     assert_eq!(without_lag.replication_lag, None);
@@ -125,6 +164,8 @@ fn test_replication_lag_scenarios() {
 #[test]
 fn test_error_response_format() {
     let error_response = Response {
+        version: version(),
+        init: None,
         status: "unhealthy".to_string(),
         region: "us-east-1".to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
@@ -132,6 +173,12 @@ fn test_error_response_format() {
             dynamodb: false,
             s3: false,
             replication_lag: None,
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         },
     };
 
@@ -150,6 +197,12 @@ mod boundary_tests {
             dynamodb: true,
             s3: true,
             replication_lag: Some(i64::MAX),
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         };
 
         assert_eq!(large_lag.replication_lag, Some(i64::MAX));
@@ -161,6 +214,12 @@ mod boundary_tests {
             dynamodb: true,
             s3: true,
             replication_lag: Some(0),
+            sentinel_stale: false,
+            bucket_replication_ok: None,
+            replication_lag_stats: None,
+            probes: BTreeMap::new(),
+            latency_ms: BTreeMap::new(),
+            details: BTreeMap::new(),
         };
 
         assert_eq!(zero_lag.replication_lag, Some(0));
@@ -175,6 +234,8 @@ mod performance_tests {
     #[test]
     fn test_response_serialization_performance() {
         let response = Response {
+            version: version(),
+            init: None,
             status: "healthy".to_string(),
             region: "us-east-1".to_string(),
             timestamp: "2025-01-06T12:00:00Z".to_string(),
@@ -182,6 +243,12 @@ mod performance_tests {
                 dynamodb: true,
                 s3: true,
                 replication_lag: Some(5),
+                sentinel_stale: false,
+                bucket_replication_ok: None,
+                replication_lag_stats: None,
+                probes: BTreeMap::new(),
+                latency_ms: BTreeMap::new(),
+                details: BTreeMap::new(),
             },
         };
 
@@ -17,7 +17,7 @@ AWS_ACCOUNT_ID=$(aws sts get-caller-identity --query Account --output text)
 echo "📦 Building Lambda functions..."
 cd lambda-functions
 
-for func in health-check backup-manager failover-controller data-validator; do
+for func in health-check backup-manager failover-controller data-validator dr-overview; do
     echo "Building $func..."
     cd $func
     cargo lambda build --release --arm64